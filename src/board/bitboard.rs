@@ -0,0 +1,268 @@
+// 64-bit bitboards for 8x8 boards, kept alongside `Board` to answer attack and move
+// generation queries without building move matrices.
+//
+// Square indexes go from a1 = 0 to h8 = 63 (rank by rank). Note that `Position` counts
+// rows from the top of the board, so row 0 is rank 8.
+use crate::board::position::Position;
+use crate::chess::color::Color;
+use crate::chess::piece_kind::PieceKind;
+use lazy_static::lazy_static;
+
+const FILE_A: u64 = 0x0101_0101_0101_0101;
+const RANK_1: u64 = 0xFF;
+
+struct AttackTables {
+    knight: [u64; 64],
+    king: [u64; 64],
+    // [color][square], White = 0
+    pawn: [[u64; 64]; 2],
+    file_mask: [u64; 64],
+    diagonal_mask: [u64; 64],
+    anti_diagonal_mask: [u64; 64],
+    // Sliding attacks along the first rank: [file][occupancy of the rank]
+    first_rank: [[u8; 256]; 8],
+}
+
+lazy_static! {
+    static ref TABLES: AttackTables = AttackTables::new();
+}
+
+impl AttackTables {
+    fn new() -> Self {
+        let mut tables = AttackTables {
+            knight: [0; 64],
+            king: [0; 64],
+            pawn: [[0; 64]; 2],
+            file_mask: [0; 64],
+            diagonal_mask: [0; 64],
+            anti_diagonal_mask: [0; 64],
+            first_rank: [[0; 256]; 8],
+        };
+
+        let knight_deltas = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+        let king_deltas = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+
+        for sq in 0..64 {
+            let (file, rank) = ((sq % 8) as isize, (sq / 8) as isize);
+            tables.knight[sq] = leaper_attacks(file, rank, &knight_deltas);
+            tables.king[sq] = leaper_attacks(file, rank, &king_deltas);
+            tables.pawn[0][sq] = leaper_attacks(file, rank, &[(-1, 1), (1, 1)]);
+            tables.pawn[1][sq] = leaper_attacks(file, rank, &[(-1, -1), (1, -1)]);
+
+            let bit = 1u64 << sq;
+            tables.file_mask[sq] = (FILE_A << file) & !bit;
+            tables.diagonal_mask[sq] = ray_mask(file, rank, 1, 1) | ray_mask(file, rank, -1, -1);
+            tables.anti_diagonal_mask[sq] = ray_mask(file, rank, 1, -1) | ray_mask(file, rank, -1, 1);
+        }
+
+        for file in 0..8 {
+            for occupancy in 0..256usize {
+                let mut attacks = 0u8;
+                for f in (file + 1)..8 {
+                    attacks |= 1 << f;
+                    if occupancy & (1 << f) != 0 {
+                        break;
+                    }
+                }
+                for f in (0..file).rev() {
+                    attacks |= 1 << f;
+                    if occupancy & (1 << f) != 0 {
+                        break;
+                    }
+                }
+                tables.first_rank[file][occupancy] = attacks;
+            }
+        }
+
+        tables
+    }
+}
+
+fn leaper_attacks(file: isize, rank: isize, deltas: &[(isize, isize)]) -> u64 {
+    deltas
+        .iter()
+        .map(|&(df, dr)| (file + df, rank + dr))
+        .filter(|&(f, r)| (0..8).contains(&f) && (0..8).contains(&r))
+        .fold(0, |bb, (f, r)| bb | 1u64 << (r * 8 + f))
+}
+
+fn ray_mask(file: isize, rank: isize, df: isize, dr: isize) -> u64 {
+    let mut bb = 0;
+    let (mut f, mut r) = (file + df, rank + dr);
+    while (0..8).contains(&f) && (0..8).contains(&r) {
+        bb |= 1u64 << (r * 8 + f);
+        f += df;
+        r += dr;
+    }
+    bb
+}
+
+// Hyperbola quintessence: attacks of a slider on `sq` along a line (file or diagonal)
+fn line_attacks(occupied: u64, mask: u64, sq: usize) -> u64 {
+    let o = occupied & mask;
+    let r = 1u64 << sq;
+    let forward = o.wrapping_sub(r.wrapping_mul(2));
+    let reverse = o.swap_bytes().wrapping_sub(r.swap_bytes().wrapping_mul(2)).swap_bytes();
+    (forward ^ reverse) & mask
+}
+
+fn rank_attacks(occupied: u64, sq: usize) -> u64 {
+    let shift = sq & 56;
+    let occupancy = ((occupied >> shift) & RANK_1) as usize;
+    (TABLES.first_rank[sq & 7][occupancy] as u64) << shift
+}
+
+pub fn knight_attacks(sq: usize) -> u64 {
+    TABLES.knight[sq]
+}
+
+pub fn king_attacks(sq: usize) -> u64 {
+    TABLES.king[sq]
+}
+
+pub fn pawn_attacks(color: Color, sq: usize) -> u64 {
+    TABLES.pawn[color.index()][sq]
+}
+
+pub fn bishop_attacks(sq: usize, occupied: u64) -> u64 {
+    line_attacks(occupied, TABLES.diagonal_mask[sq], sq) | line_attacks(occupied, TABLES.anti_diagonal_mask[sq], sq)
+}
+
+pub fn rook_attacks(sq: usize, occupied: u64) -> u64 {
+    line_attacks(occupied, TABLES.file_mask[sq], sq) | rank_attacks(occupied, sq)
+}
+
+pub fn queen_attacks(sq: usize, occupied: u64) -> u64 {
+    bishop_attacks(sq, occupied) | rook_attacks(sq, occupied)
+}
+
+pub fn square(position: Position) -> usize {
+    (7 - position.row) * 8 + position.col
+}
+
+pub fn position(sq: usize) -> Position {
+    Position::new(7 - sq / 8, sq % 8)
+}
+
+// Iterates over the square indexes of the set bits
+pub fn squares(mut bb: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if bb == 0 {
+            return None;
+        }
+        let sq = bb.trailing_zeros() as usize;
+        bb &= bb - 1;
+        Some(sq)
+    })
+}
+
+fn kind_index(kind: PieceKind) -> usize {
+    match kind {
+        PieceKind::King => 0,
+        PieceKind::Queen => 1,
+        PieceKind::Rook => 2,
+        PieceKind::Bishop => 3,
+        PieceKind::Knight => 4,
+        PieceKind::Pawn => 5,
+        PieceKind::Archbishop => 6,
+        PieceKind::Chancellor => 7,
+        // ChessMatch stops using bitboards as soon as a custom piece is on the board
+        PieceKind::Custom(_) => unreachable!("Bitboards only hold the built-in pieces"),
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Bitboards {
+    by_color: [u64; 2],
+    by_kind: [u64; 8],
+}
+
+impl Bitboards {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Adds the piece if the square is empty for it, removes it otherwise
+    pub fn toggle(&mut self, kind: PieceKind, color: Color, sq: usize) {
+        let bit = 1u64 << sq;
+        self.by_color[color.index()] ^= bit;
+        self.by_kind[kind_index(kind)] ^= bit;
+    }
+
+    pub fn occupied(&self) -> u64 {
+        self.by_color[0] | self.by_color[1]
+    }
+
+    pub fn color(&self, color: Color) -> u64 {
+        self.by_color[color.index()]
+    }
+
+    pub fn pieces(&self, kind: PieceKind, color: Color) -> u64 {
+        self.by_kind[kind_index(kind)] & self.color(color)
+    }
+
+    // Squares attacked by a piece of the given kind and color standing on `sq`
+    pub fn attacks_from(&self, kind: PieceKind, color: Color, sq: usize) -> u64 {
+        let occupied = self.occupied();
+        match kind {
+            PieceKind::King => king_attacks(sq),
+            PieceKind::Queen => queen_attacks(sq, occupied),
+            PieceKind::Rook => rook_attacks(sq, occupied),
+            PieceKind::Bishop => bishop_attacks(sq, occupied),
+            PieceKind::Knight => knight_attacks(sq),
+            PieceKind::Pawn => pawn_attacks(color, sq),
+            PieceKind::Archbishop => bishop_attacks(sq, occupied) | knight_attacks(sq),
+            PieceKind::Chancellor => rook_attacks(sq, occupied) | knight_attacks(sq),
+            PieceKind::Custom(_) => unreachable!("Bitboards only hold the built-in pieces"),
+        }
+    }
+
+    pub fn is_attacked(&self, sq: usize, by: Color) -> bool {
+        let occupied = self.occupied();
+        let queens = self.pieces(PieceKind::Queen, by);
+        let archbishops = self.pieces(PieceKind::Archbishop, by);
+        let chancellors = self.pieces(PieceKind::Chancellor, by);
+        // A pawn of `by` attacks sq if a pawn of the other color on sq would attack it back
+        pawn_attacks(by.opponent(), sq) & self.pieces(PieceKind::Pawn, by) != 0
+            || knight_attacks(sq) & (self.pieces(PieceKind::Knight, by) | archbishops | chancellors) != 0
+            || king_attacks(sq) & self.pieces(PieceKind::King, by) != 0
+            || bishop_attacks(sq, occupied) & (self.pieces(PieceKind::Bishop, by) | queens | archbishops) != 0
+            || rook_attacks(sq, occupied) & (self.pieces(PieceKind::Rook, by) | queens | chancellors) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bb(squares: &[usize]) -> u64 {
+        squares.iter().fold(0, |bb, &sq| bb | 1u64 << sq)
+    }
+
+    #[test]
+    fn leaper_tables() {
+        // a1 = 0, b3 = 17, c2 = 10
+        assert_eq!(knight_attacks(0), bb(&[17, 10]));
+        assert_eq!(king_attacks(0), bb(&[1, 8, 9]));
+        assert_eq!(pawn_attacks(Color::White, 12), bb(&[19, 21]));
+        assert_eq!(pawn_attacks(Color::Black, 12), bb(&[3, 5]));
+        assert_eq!(pawn_attacks(Color::White, 8), bb(&[17]));
+    }
+
+    #[test]
+    fn sliders_stop_at_the_first_blocker() {
+        // Rook on d4 (27) with blockers on d6 (43) and f4 (29)
+        let occupied = bb(&[27, 43, 29]);
+        assert_eq!(rook_attacks(27, occupied), bb(&[35, 43, 19, 11, 3, 28, 29, 26, 25, 24]));
+        // Bishop on c1 (2) with a blocker on e3 (20)
+        assert_eq!(bishop_attacks(2, bb(&[2, 20])), bb(&[9, 16, 11, 20]));
+    }
+
+    #[test]
+    fn squares_match_positions() {
+        assert_eq!(square(Position::new(7, 0)), 0);
+        assert_eq!(square(Position::new(0, 7)), 63);
+        assert_eq!(position(12), Position::new(6, 4));
+        assert_eq!(squares(bb(&[3, 40, 63])).collect::<Vec<_>>(), vec![3, 40, 63]);
+    }
+}
@@ -1,3 +1,4 @@
+pub mod bitboard;
 pub mod piece;
 pub mod position;
 
@@ -20,11 +21,11 @@ impl Board {
         Ok(Board { rows, cols, pieces })
     }
 
-    pub fn piece(&self, position: Position) -> Option<&Box<dyn Piece + Send + Sync>> {
+    pub fn piece(&self, position: Position) -> Option<&(dyn Piece + Send + Sync)> {
         if !self.position_exists(position) {
             panic!("Position not on the board");
         }
-        self.pieces[position.row][position.col].as_ref()
+        self.pieces[position.row][position.col].as_deref()
     }
 
     pub fn place_piece(&mut self, piece: Box<dyn Piece + Send + Sync>, position: Position) -> Result<(), ChessError> {
@@ -1,21 +1,36 @@
 use super::position::Position;
 use crate::board::Board;
-use crate::chess::{color::Color, ChessMatch};
+use crate::chess::{color::Color, piece_kind::PieceKind, ChessMatch};
 use std::fmt;
 
 pub trait Piece: fmt::Display + Send + Sync {
     fn color(&self) -> Color;
+    // Identifies the piece for the rules; Display is only used for rendering
+    fn kind(&self) -> PieceKind;
     fn move_count(&self) -> u32;
     fn increase_move_count(&mut self);
     fn decrease_move_count(&mut self);
 
     fn possible_moves(&self, board: &Board, position: Position, chess_match: &ChessMatch) -> Vec<Vec<bool>>;
+
+    // Squares this piece threatens. Differs from possible_moves for pieces whose
+    // moves are not captures (pawn pushes, castling).
+    fn attacks(&self, board: &Board, position: Position, chess_match: &ChessMatch) -> Vec<Vec<bool>> {
+        self.possible_moves(board, position, chess_match)
+    }
     
     fn is_there_opponent_piece(&self, position: Position, board: &Board) -> bool {
-        board.piece(position).map_or(false, |p| p.color() != self.color())
+        board.piece(position).is_some_and(|p| p.color() != self.color())
     }
 
     fn box_clone(&self) -> Box<dyn Piece + Send + Sync>;
+
+    // Whether the piece is a promoted pawn. Only pieces a pawn can promote to keep track of it.
+    fn is_promoted(&self) -> bool {
+        false
+    }
+
+    fn set_promoted(&mut self) {}
 }
 
 impl Clone for Box<dyn Piece + Send + Sync> {
@@ -0,0 +1,301 @@
+// Betza notation: fairy pieces described by how they move, e.g. "WN" (a king-like step on
+// the files and ranks plus the knight jump) or "FfmWfcF" (a pawn that can also step
+// diagonally backwards).
+//
+// Each uppercase atom is a leap: W (1,0), F (1,1), D (2,0), N (2,1), A (2,2), H (3,0),
+// C (3,1), Z (3,2) and G (3,3), in all eight directions. K, Q, R and B are shorthands for
+// WF, WWFF, WW and FF. A doubled atom is a rider that repeats the leap until it is blocked
+// ("NN" is the nightrider), and a number after the atom limits the rider's range ("R4").
+// Lowercase modifiers before an atom restrict it:
+//   f, b, l, r  forward, backward, left and right, from the owner's side of the board;
+//               "fl", "fr", "bl" and "br" pick the diagonal-ish directions between them
+//   v, s        vertical and sideways: the directions that are more forward or backward
+//               than sideways, and the other way round
+//   m, c        moves only to an empty square, or only as a capture
+//   i           only as the piece's first move
+//
+// Each game carries the custom pieces of its variant in a CustomPieces table, keyed by letter,
+// so that FEN, SAN and the rest of the engine can refer to them as PieceKind::Custom.
+use crate::chess::piece_kind::PieceKind;
+use crate::error::ChessError;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveMode {
+    MoveOrCapture,
+    MoveOnly,
+    CaptureOnly,
+}
+
+// One direction the piece can go in. Offsets are (files to the right, ranks forward) for
+// White; Black's are mirrored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Leap {
+    pub right: isize,
+    pub forward: isize,
+    // How many times the leap can be repeated in a straight line: 1 for a leaper,
+    // None for a rider with no limit
+    pub range: Option<usize>,
+    pub mode: MoveMode,
+    pub initial_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Betza {
+    pub notation: String,
+    pub leaps: Vec<Leap>,
+}
+
+impl Betza {
+    pub fn parse(notation: &str) -> Result<Self, ChessError> {
+        let chars = notation.trim().chars().collect::<Vec<_>>();
+        if chars.is_empty() {
+            return Err(ChessError("Empty Betza notation".to_string()));
+        }
+
+        let mut leaps = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_lowercase() {
+                i += 1;
+            }
+            let modifiers = &chars[start..i];
+            let Some(&atom) = chars.get(i) else {
+                return Err(ChessError(format!("Betza notation '{}' ends with modifiers and no piece letter", notation)));
+            };
+            i += 1;
+
+            let mut rider = false;
+            if chars.get(i) == Some(&atom) {
+                rider = true;
+                i += 1;
+            }
+            let digits = chars[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+            let limit = match digits {
+                0 => None,
+                _ => {
+                    let number = chars[i..i + digits].iter().collect::<String>();
+                    i += digits;
+                    match number.parse::<usize>() {
+                        Ok(n) if n > 0 => Some(n),
+                        _ => return Err(ChessError(format!("Invalid range '{}' in Betza notation '{}'", number, notation))),
+                    }
+                }
+            };
+
+            let token = chars[start..i].iter().collect::<String>();
+            let before = leaps.len();
+            for (offset, atom_rider) in atom_leaps(atom)
+                .ok_or_else(|| ChessError(format!("Unknown Betza atom '{}' in '{}'", atom, notation)))?
+            {
+                // A number makes any atom a rider with that range
+                let range = match (rider || atom_rider, limit) {
+                    (_, Some(n)) => Some(n),
+                    (true, None) => None,
+                    (false, None) => Some(1),
+                };
+                add_leaps(&mut leaps, offset, range, modifiers, &token)?;
+            }
+            if leaps.len() == before {
+                return Err(ChessError(format!("'{}' in Betza notation '{}' selects no moves", token, notation)));
+            }
+        }
+
+        Ok(Betza { notation: notation.trim().to_string(), leaps })
+    }
+}
+
+// The basic leaps of an atom, and whether the atom is a rider by itself (Q, R, B)
+fn atom_leaps(atom: char) -> Option<Vec<((isize, isize), bool)>> {
+    let leap = |a, b| ((a, b), false);
+    let ride = |a, b| ((a, b), true);
+    Some(match atom {
+        'W' => vec![leap(1, 0)],
+        'F' => vec![leap(1, 1)],
+        'D' => vec![leap(2, 0)],
+        'N' => vec![leap(2, 1)],
+        'A' => vec![leap(2, 2)],
+        'H' => vec![leap(3, 0)],
+        'C' => vec![leap(3, 1)],
+        'Z' => vec![leap(3, 2)],
+        'G' => vec![leap(3, 3)],
+        'K' => vec![leap(1, 0), leap(1, 1)],
+        'Q' => vec![ride(1, 0), ride(1, 1)],
+        'R' => vec![ride(1, 0)],
+        'B' => vec![ride(1, 1)],
+        _ => return None,
+    })
+}
+
+// Adds the directions of the (a, b) leap that the modifiers let through
+fn add_leaps(leaps: &mut Vec<Leap>, (a, b): (isize, isize), range: Option<usize>, modifiers: &[char], token: &str) -> Result<(), ChessError> {
+    let mut mode = MoveMode::MoveOrCapture;
+    let mut initial_only = false;
+    // Each filter keeps the directions (right, forward) it accepts; a direction is used if
+    // any of them accepts it, or if there are none
+    let mut filters: Vec<fn(isize, isize) -> bool> = Vec::new();
+    let mut i = 0;
+    while i < modifiers.len() {
+        let pair = (modifiers[i], modifiers.get(i + 1).copied());
+        let filter: Option<fn(isize, isize) -> bool> = match pair {
+            ('f', Some('l')) => Some(|x, y| y > 0 && x < 0),
+            ('f', Some('r')) => Some(|x, y| y > 0 && x > 0),
+            ('b', Some('l')) => Some(|x, y| y < 0 && x < 0),
+            ('b', Some('r')) => Some(|x, y| y < 0 && x > 0),
+            _ => None,
+        };
+        if let Some(filter) = filter {
+            filters.push(filter);
+            i += 2;
+            continue;
+        }
+        match modifiers[i] {
+            'f' => filters.push(|_, y| y > 0),
+            'b' => filters.push(|_, y| y < 0),
+            'l' => filters.push(|x, _| x < 0),
+            'r' => filters.push(|x, _| x > 0),
+            'v' => filters.push(|x, y| y.abs() > x.abs()),
+            's' => filters.push(|x, y| x.abs() > y.abs()),
+            'm' => mode = MoveMode::MoveOnly,
+            'c' => mode = MoveMode::CaptureOnly,
+            'i' => initial_only = true,
+            other => return Err(ChessError(format!("Unknown Betza modifier '{}' in '{}'", other, token))),
+        }
+        i += 1;
+    }
+
+    let directions = [(a, b), (b, a), (-a, b), (-b, a), (a, -b), (b, -a), (-a, -b), (-b, -a)];
+    for (right, forward) in directions {
+        if !filters.is_empty() && !filters.iter().any(|accepts| accepts(right, forward)) {
+            continue;
+        }
+        let leap = Leap { right, forward, range, mode, initial_only };
+        if !leaps.contains(&leap) {
+            leaps.push(leap);
+        }
+    }
+    Ok(())
+}
+
+// A fairy piece known to the engine by its letter
+#[derive(Debug)]
+pub struct CustomPiece {
+    pub letter: char,
+    pub name: String,
+    pub betza: Betza,
+}
+
+// The custom pieces of one variant, in the order they were added. Another variant may give
+// the same letter to a different piece.
+#[derive(Debug, Clone, Default)]
+pub struct CustomPieces {
+    pieces: Vec<Arc<CustomPiece>>,
+}
+
+impl CustomPieces {
+    // Adds a piece under `letter`, which must be uppercase, not one of the built-in pieces and
+    // not already taken in this table
+    pub fn add(&mut self, letter: char, name: &str, notation: &str) -> Result<(), ChessError> {
+        if !letter.is_ascii_uppercase() {
+            return Err(ChessError(format!("Custom pieces need an uppercase letter, found '{}'", letter)));
+        }
+        if PieceKind::from_letter(letter).is_some() {
+            return Err(ChessError(format!("'{}' is already the letter of a built-in piece", letter)));
+        }
+        let betza = Betza::parse(notation)?;
+        if let Some(piece) = self.get(letter) {
+            return Err(ChessError(format!(
+                "'{}' is already the {} ({}), it can't also be the {} ({})",
+                letter, piece.name, piece.betza.notation, name, betza.notation
+            )));
+        }
+        self.pieces.push(Arc::new(CustomPiece { letter, name: name.to_string(), betza }));
+        Ok(())
+    }
+
+    // The piece with this letter, in either case
+    pub fn get(&self, letter: char) -> Option<&Arc<CustomPiece>> {
+        let letter = letter.to_ascii_uppercase();
+        self.pieces.iter().find(|piece| piece.letter == letter)
+    }
+
+    // Kind of the piece a FEN or SAN letter stands for, built-in or from this table
+    pub fn kind(&self, letter: char) -> Option<PieceKind> {
+        PieceKind::from_letter(letter).or_else(|| self.get(letter).map(|piece| PieceKind::Custom(piece.letter)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<CustomPiece>> {
+        self.pieces.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directions(notation: &str) -> Vec<(isize, isize)> {
+        let mut directions = Betza::parse(notation).unwrap().leaps.iter().map(|leap| (leap.right, leap.forward)).collect::<Vec<_>>();
+        directions.sort();
+        directions
+    }
+
+    #[test]
+    fn atoms_leapers_and_riders() {
+        assert_eq!(directions("W"), vec![(-1, 0), (0, -1), (0, 1), (1, 0)]);
+        assert_eq!(Betza::parse("N").unwrap().leaps.len(), 8);
+        assert_eq!(Betza::parse("WN").unwrap().leaps.len(), 12);
+        assert!(Betza::parse("K").unwrap().leaps.iter().all(|leap| leap.range == Some(1)));
+        assert!(Betza::parse("Q").unwrap().leaps.iter().all(|leap| leap.range.is_none()));
+        assert_eq!(Betza::parse("NN").unwrap().leaps[0].range, None);
+        assert_eq!(Betza::parse("R4").unwrap().leaps[0].range, Some(4));
+        assert_eq!(directions("WW"), directions("R"));
+    }
+
+    #[test]
+    fn modifiers_pick_directions_and_modes() {
+        assert_eq!(directions("fW"), vec![(0, 1)]);
+        assert_eq!(directions("fF"), vec![(-1, 1), (1, 1)]);
+        assert_eq!(directions("frF"), vec![(1, 1)]);
+        assert_eq!(directions("vN"), vec![(-1, -2), (-1, 2), (1, -2), (1, 2)]);
+        assert_eq!(directions("sW"), vec![(-1, 0), (1, 0)]);
+
+        // A pawn that can also step diagonally backwards
+        let betza = Betza::parse("FfmWfcF").unwrap();
+        let mode = |right, forward| betza.leaps.iter().filter(|l| (l.right, l.forward) == (right, forward)).map(|l| l.mode).collect::<Vec<_>>();
+        assert_eq!(mode(0, 1), vec![MoveMode::MoveOnly]);
+        assert_eq!(mode(1, 1), vec![MoveMode::MoveOrCapture, MoveMode::CaptureOnly]);
+        assert_eq!(mode(1, -1), vec![MoveMode::MoveOrCapture]);
+        assert!(Betza::parse("imfD").unwrap().leaps[0].initial_only);
+    }
+
+    #[test]
+    fn bad_notation_is_rejected() {
+        assert_eq!(Betza::parse("").unwrap_err().0, "Empty Betza notation");
+        assert_eq!(Betza::parse("X").unwrap_err().0, "Unknown Betza atom 'X' in 'X'");
+        assert_eq!(Betza::parse("WfF2x").unwrap_err().0, "Betza notation 'WfF2x' ends with modifiers and no piece letter");
+        assert_eq!(Betza::parse("qW").unwrap_err().0, "Unknown Betza modifier 'q' in 'qW'");
+        assert_eq!(Betza::parse("flW").unwrap_err().0, "'flW' in Betza notation 'flW' selects no moves");
+        assert_eq!(Betza::parse("R0").unwrap_err().0, "Invalid range '0' in Betza notation 'R0'");
+    }
+
+    #[test]
+    fn custom_pieces_are_found_by_letter() {
+        let mut pieces = CustomPieces::default();
+        pieces.add('E', "Elephant", "FA").unwrap();
+        assert_eq!(pieces.get('E').unwrap().betza.leaps.len(), 8);
+        assert_eq!(pieces.kind('e'), Some(PieceKind::Custom('E')));
+        assert_eq!(pieces.kind('n'), Some(PieceKind::Knight));
+        assert!(pieces.add('E', "Camel", "C").unwrap_err().0.contains("is already the Elephant"));
+        assert!(pieces.add('Q', "Queen", "Q").is_err());
+        assert!(pieces.add('e', "Elephant", "FA").is_err());
+        assert!(pieces.get('Y').is_none());
+
+        // Each table is separate: here 'E' is a camel, and the first table has no 'Y'
+        let mut others = CustomPieces::default();
+        others.add('E', "Camel", "C").unwrap();
+        assert_eq!(others.get('E').unwrap().name, "Camel");
+        assert_eq!(pieces.get('E').unwrap().name, "Elephant");
+        assert_eq!(PieceKind::from_letter('E'), None);
+    }
+}
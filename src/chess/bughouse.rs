@@ -0,0 +1,125 @@
+// Bughouse: two Crazyhouse games played side by side by two teams of two. Partners sit at
+// different boards with opposite colors, team 0 having White on board 0 and Black on board 1.
+// A piece captured on one board goes to the hand of the capturer's partner, who plays the
+// color of the captured piece, and the team game ends as soon as either board does.
+use crate::chess::color::Color;
+use crate::chess::piece_kind::PieceKind;
+use crate::chess::ChessMatch;
+use crate::network::{GameEndReason, GameMessage};
+
+pub const BOARDS: usize = 2;
+
+// Team (0 or 1) of the player of `color` on `board`
+pub fn team(board: usize, color: Color) -> usize {
+    (board + (color == Color::Black) as usize) % 2
+}
+
+// Color `team` plays on `board`
+pub fn team_color(team: usize, board: usize) -> Color {
+    if (team + board).is_multiple_of(2) { Color::White } else { Color::Black }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BughouseResult {
+    // None for a draw
+    pub winning_team: Option<usize>,
+    pub reason: GameEndReason,
+}
+
+impl BughouseResult {
+    // The winning color as seen on `board`
+    pub fn winner_on(&self, board: usize) -> Option<Color> {
+        self.winning_team.map(|team| team_color(team, board))
+    }
+}
+
+impl ChessMatch {
+    // The piece the last move captured, as it goes to the partner: its color, which the partner
+    // plays, and its kind, a promoted piece turning back into a pawn
+    pub fn passed_piece(&self) -> Option<(Color, PieceKind)> {
+        let captured = self.history.last()?.captured_piece.as_ref()?;
+        let kind = if captured.is_promoted() { PieceKind::Pawn } else { captured.kind() };
+        Some((captured.color(), kind))
+    }
+
+    // Puts a piece the partner captured on the other board into the hand of `color`
+    pub fn receive_piece(&mut self, color: Color, kind: PieceKind) {
+        self.hands[color.index()].push(kind);
+    }
+
+    // Position of this board as shown to the players of the other one
+    pub fn to_partner_board(&self) -> GameMessage {
+        match self.to_game_state(String::new()) {
+            GameMessage::GameState { board, turn_color, hands, .. } => {
+                GameMessage::PartnerBoard { board, turn_color, hands: hands.unwrap_or_default() }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // Result of the team game once this game, played on `board`, is over
+    pub fn bughouse_result(&self, board: usize) -> Option<BughouseResult> {
+        if let Some(winner) = self.winner() {
+            let reason = self.variant_win.map_or(GameEndReason::Checkmate, |(_, reason)| reason);
+            return Some(BughouseResult { winning_team: Some(team(board, winner)), reason });
+        }
+        self.draw_reason().map(|reason| BughouseResult { winning_team: None, reason })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::variant::Variant;
+
+    #[test]
+    fn partners_sit_on_opposite_colors() {
+        assert_eq!((team(0, Color::White), team(1, Color::Black)), (0, 0));
+        assert_eq!((team(0, Color::Black), team(1, Color::White)), (1, 1));
+        for board in 0..BOARDS {
+            for color in [Color::White, Color::Black] {
+                assert_eq!(team_color(team(board, color), board), color);
+            }
+        }
+    }
+
+    #[test]
+    fn captured_pieces_go_to_the_partner() {
+        let mut boards = [ChessMatch::for_variant(Variant::Bughouse, 0), ChessMatch::for_variant(Variant::Bughouse, 0)];
+        for san in ["e4", "d5", "exd5"] {
+            boards[0].perform_san_move(san).unwrap();
+        }
+        // White took a black pawn on board 0, which White's partner plays with on board 1
+        assert_eq!(boards[0].passed_piece(), Some((Color::Black, PieceKind::Pawn)));
+        assert!(boards[0].hand(Color::White).is_empty());
+        boards[1].receive_piece(Color::Black, PieceKind::Pawn);
+        assert_eq!(boards[1].to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[p] w KQkq - 0 1");
+
+        boards[1].perform_san_move("e4").unwrap();
+        boards[1].perform_drop(PieceKind::Pawn, "e5".parse().unwrap()).unwrap();
+        assert!(boards[1].hand(Color::Black).is_empty());
+        assert_eq!(boards[1].history()[1].san, "P@e5");
+        assert_eq!(boards[1].passed_piece(), None);
+    }
+
+    #[test]
+    fn promoted_pieces_are_passed_as_pawns() {
+        let mut chess_match = ChessMatch::test_fen(Variant::Bughouse, "3qk3/8/8/8/8/8/8/3Q~K3[] b - - 0 1");
+        chess_match.perform_san_move("Qxd1+").unwrap();
+        assert_eq!(chess_match.passed_piece(), Some((Color::White, PieceKind::Pawn)));
+        assert!(chess_match.hand(Color::Black).is_empty());
+    }
+
+    #[test]
+    fn either_board_decides_the_team_game() {
+        let mut chess_match = ChessMatch::for_variant(Variant::Bughouse, 0);
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            chess_match.perform_san_move(san).unwrap();
+        }
+        // Black on board 1 is on team 0, who have White on board 0
+        let result = chess_match.bughouse_result(1).unwrap();
+        assert_eq!(result, BughouseResult { winning_team: Some(0), reason: GameEndReason::Checkmate });
+        assert_eq!((result.winner_on(0), result.winner_on(1)), (Some(Color::White), Some(Color::Black)));
+        assert_eq!(ChessMatch::for_variant(Variant::Bughouse, 0).bughouse_result(0), None);
+    }
+}
@@ -0,0 +1,56 @@
+use crate::board::position::Position;
+use crate::chess::chess_position::ChessPosition;
+use crate::chess::color::Color;
+use crate::chess::piece_kind::PieceKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub source: Position,
+    pub target: Position,
+    pub piece: PieceKind,
+    pub color: Color,
+    pub captured: Option<PieceKind>,
+    pub promotion: Option<PieceKind>,
+    // Square of the rook the king castles with. The target of a castling move is the king's
+    // destination when it moves two or more files, and the rook's square otherwise (Chess960).
+    pub castling_rook: Option<Position>,
+    pub is_en_passant: bool,
+    // Crazyhouse: the piece comes from the player's hand onto the target, which is also the source
+    pub is_drop: bool,
+}
+
+impl Move {
+    pub fn is_castling(&self) -> bool {
+        self.castling_rook.is_some()
+    }
+
+    pub fn is_kingside_castling(&self) -> bool {
+        self.castling_rook.is_some_and(|rook| rook.col > self.source.col)
+    }
+
+    pub fn is_capture(&self) -> bool {
+        self.captured.is_some()
+    }
+
+    // Square of the captured piece, which differs from the target on en passant
+    pub fn capture_square(&self) -> Position {
+        if self.is_en_passant {
+            Position::new(self.source.row, self.target.col)
+        } else {
+            self.target
+        }
+    }
+
+    // Coordinate notation on a board of `rows` ranks, e.g. "e2e4", "e7e8q", or "N@f3" for a drop
+    pub fn to_coordinates(&self, rows: usize) -> String {
+        let square = |pos: Position| ChessPosition::from_position(pos, rows);
+        if self.is_drop {
+            return format!("{}@{}", self.piece.letter(), square(self.target));
+        }
+        let mut text = format!("{}{}", square(self.source), square(self.target));
+        if let Some(promotion) = self.promotion {
+            text.push(promotion.letter().to_ascii_lowercase());
+        }
+        text
+    }
+}
@@ -3,30 +3,39 @@ use crate::error::ChessError;
 use std::fmt;
 use std::str::FromStr;
 
+// Files are letters from 'a' and ranks are numbered from 1, counting from White's side, so
+// converting to and from a board Position needs the number of ranks of the board
 #[derive(Debug, Clone, Copy)]
 pub struct ChessPosition {
     pub col: char,
     pub row: u8,
 }
 
+// Largest number of files (a to z) and ranks a square can be named on
+pub const MAX_FILES: usize = 26;
+pub const MAX_RANKS: usize = 26;
+
 impl ChessPosition {
     pub fn new(col: char, row: u8) -> Result<Self, ChessError> {
-        if !('a'..='h').contains(&col) || !(1..=8).contains(&row) {
-            return Err(ChessError("Error instantiating ChessPosition. Valid values are from a1 to h8.".to_string()));
+        if !col.is_ascii_lowercase() || !(1..=MAX_RANKS as u8).contains(&row) {
+            return Err(ChessError("Error instantiating ChessPosition. Valid values are from a1 to z26.".to_string()));
         }
         Ok(ChessPosition { col, row })
     }
 
-    pub fn to_position(&self) -> Position {
-        Position {
-            row: 8 - self.row as usize,
-            col: (self.col as u8 - b'a') as usize,
+    // Position of the square on a board of `rows` ranks and `cols` files
+    pub fn to_position(self, rows: usize, cols: usize) -> Result<Position, ChessError> {
+        let col = (self.col as u8 - b'a') as usize;
+        if self.row as usize > rows || col >= cols {
+            let last = ChessPosition::from_position(Position::new(0, cols - 1), rows);
+            return Err(ChessError(format!("Square {} is off the board, which goes from a1 to {}", self, last)));
         }
+        Ok(Position { row: rows - self.row as usize, col })
     }
 
-    pub fn from_position(position: Position) -> Self {
+    pub fn from_position(position: Position, rows: usize) -> Self {
         let col = (b'a' + position.col as u8) as char;
-        let row = (8 - position.row) as u8;
+        let row = (rows - position.row) as u8;
         Self { col, row }
     }
 }
@@ -34,15 +43,17 @@ impl ChessPosition {
 impl FromStr for ChessPosition {
     type Err = ChessError;
 
+    // A file letter followed by the rank number, e.g. "a1", "j8" or "c10"
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let s = s.trim();
-        if s.len() != 2 {
+        let mut chars = s.chars();
+        let (Some(col), rank) = (chars.next(), chars.as_str()) else {
+            return Err(ChessError("Invalid input. Expected format is like 'a1'.".to_string()));
+        };
+        if rank.is_empty() || !rank.chars().all(|c| c.is_ascii_digit()) {
             return Err(ChessError("Invalid input. Expected format is like 'a1'.".to_string()));
         }
-        let col = s.chars().next().unwrap();
-        let row_char = s.chars().nth(1).unwrap();
-        // let row = row_char.to_digit(10).ok_or_else(|| ChessError("Invalid row number.".to_string()))? as u8;
-        let row = row_char.to_string().parse::<u8>().map_err(|_| ChessError("Invalid row number.".to_string()))?;
+        let row = rank.parse::<u8>().map_err(|_| ChessError("Invalid row number.".to_string()))?;
         ChessPosition::new(col, row)
     }
 }
@@ -51,4 +62,4 @@ impl fmt::Display for ChessPosition {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}{}", self.col, self.row)
     }
-}
\ No newline at end of file
+}
@@ -4,4 +4,15 @@ use serde::{Deserialize, Serialize};
 pub enum Color {
     Black,
     White,
-}
\ No newline at end of file
+}
+
+impl Color {
+    pub fn opponent(self) -> Color {
+        if self == Color::White { Color::Black } else { Color::White }
+    }
+
+    // Position of the color in per-side arrays, White first
+    pub fn index(self) -> usize {
+        if self == Color::White { 0 } else { 1 }
+    }
+}
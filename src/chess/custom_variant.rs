@@ -0,0 +1,176 @@
+// Variants defined in a JSON file rather than in code: one of the built-in variants as the
+// rules, an optional starting position and fairy pieces in Betza notation, e.g.
+//
+//   {
+//     "name": "Elephant chess",
+//     "variant": "standard",
+//     "fen": "rnbqkenr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKENR w KQkq - 0 1",
+//     "pieces": [{ "letter": "E", "name": "Elephant", "betza": "FA" }]
+//   }
+use crate::chess::betza::CustomPieces;
+use crate::chess::variant::Variant;
+use crate::chess::ChessMatch;
+use crate::error::ChessError;
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Debug, Clone)]
+pub struct CustomVariant {
+    pub name: String,
+    pub variant: Variant,
+    // Starting position; the variant's own when there is none
+    pub fen: Option<String>,
+    pub pieces: CustomPieces,
+}
+
+#[derive(Deserialize)]
+struct VariantFile {
+    name: String,
+    variant: Option<String>,
+    fen: Option<String>,
+    #[serde(default)]
+    pieces: Vec<PieceEntry>,
+}
+
+#[derive(Deserialize)]
+struct PieceEntry {
+    letter: char,
+    name: String,
+    betza: String,
+}
+
+impl CustomVariant {
+    pub fn load(path: &Path) -> Result<Self, ChessError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| ChessError(format!("Can't read the variant file {}: {}", path.display(), e)))?;
+        Self::from_json(&text)
+    }
+
+    // Reads the pieces and checks that the starting position can be set up with them
+    pub fn from_json(text: &str) -> Result<Self, ChessError> {
+        let file: VariantFile = serde_json::from_str(text).map_err(|e| ChessError(format!("Invalid variant file: {}", e)))?;
+        let variant = file.variant.as_deref().map(str::parse::<Variant>).transpose()?.unwrap_or_default();
+
+        let mut pieces = CustomPieces::default();
+        for entry in &file.pieces {
+            pieces
+                .add(entry.letter, &entry.name, &entry.betza)
+                .map_err(|e| ChessError(format!("Invalid variant file: {}", e.0)))?;
+        }
+        let custom_variant = CustomVariant { name: file.name, variant, fen: file.fen, pieces };
+        custom_variant.new_match(0).map_err(|e| ChessError(format!("Invalid variant file: {}", e.0)))?;
+        Ok(custom_variant)
+    }
+
+    // New game with the variant's pieces; `seed` picks the Chess960 starting position when there
+    // is no FEN
+    pub fn new_match(&self, seed: u64) -> Result<ChessMatch, ChessError> {
+        match &self.fen {
+            Some(fen) => ChessMatch::from_custom_fen(self.variant, &self.pieces, fen),
+            None => {
+                let mut chess_match = ChessMatch::for_variant(self.variant, seed);
+                chess_match.custom_pieces = self.pieces.clone();
+                Ok(chess_match)
+            }
+        }
+    }
+
+    // Name and pieces, e.g. "Elephant chess: Elephant (E) moves as FA"
+    pub fn description(&self) -> String {
+        let pieces = self
+            .pieces
+            .iter()
+            .map(|piece| format!("{} ({}) moves as {}", piece.name, piece.letter, piece.betza.notation))
+            .collect::<Vec<_>>();
+        if pieces.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}: {}", self.name, pieces.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::position::Position;
+    use crate::chess::color::Color;
+    use crate::chess::piece_kind::PieceKind;
+
+    const ELEPHANTS: &str = r#"{
+        "name": "Elephant chess",
+        "fen": "rnbqkonr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKONR w KQkq - 0 1",
+        "pieces": [{ "letter": "O", "name": "Elephant", "betza": "FA" }]
+    }"#;
+
+    #[test]
+    fn custom_pieces_play_from_the_file() {
+        let variant = CustomVariant::from_json(ELEPHANTS).unwrap();
+        assert_eq!(variant.variant, Variant::Standard);
+        assert_eq!(variant.description(), "Elephant chess: Elephant (O) moves as FA");
+
+        let mut chess_match = variant.new_match(0).unwrap();
+        // The elephant on f1 jumps over the pawns to d3 or h3, or steps to e2 or g2 once they move
+        assert_eq!(chess_match.legal_moves().len(), 22);
+        chess_match.perform_san_move("Od3").unwrap();
+        chess_match.perform_san_move("e5").unwrap();
+        chess_match.perform_san_move("Of5").unwrap();
+        assert_eq!(chess_match.board.piece(Position::new(3, 5)).unwrap().kind(), PieceKind::Custom('O'));
+        assert_eq!(chess_match.to_fen(), "rnbqkonr/pppp1ppp/8/4pO2/8/8/PPPPPPPP/RNBQK1NR b KQkq - 1 2");
+        assert_eq!(chess_match.history()[2].san, "Of5");
+    }
+
+    #[test]
+    fn custom_pieces_give_check() {
+        let variant = CustomVariant::from_json(ELEPHANTS).unwrap();
+        let chess_match = ChessMatch::from_custom_fen(Variant::Standard, &variant.pieces, "4k3/8/2O5/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert!(chess_match.check);
+        assert_eq!(chess_match.to_fen(), "4k3/8/2O5/8/8/8/8/4K3 b - - 0 1");
+    }
+
+    #[test]
+    fn variants_keep_their_own_pieces() {
+        // 'O' only moves as a camel here, and the elephants of ELEPHANTS are still elephants
+        let camels = CustomVariant::from_json(
+            r#"{ "name": "Camels", "fen": "4k3/8/8/8/8/8/8/4KO2 w - - 0 1", "pieces": [{ "letter": "O", "name": "Camel", "betza": "C" }] }"#,
+        )
+        .unwrap();
+        let elephants = CustomVariant::from_json(ELEPHANTS).unwrap();
+        assert!(camels.new_match(0).unwrap().perform_san_move("Og4").is_ok());
+        assert!(camels.new_match(0).unwrap().perform_san_move("Oe2").is_err());
+        assert_eq!(elephants.new_match(0).unwrap().legal_moves().len(), 22);
+
+        // Without the variant, 'O' isn't a piece
+        assert!(ChessMatch::from_fen("4k3/8/8/8/8/8/8/4KO2 w - - 0 1").is_err());
+    }
+
+    #[test]
+    fn custom_pieces_in_hand_can_be_dropped() {
+        let variant = CustomVariant::from_json(ELEPHANTS).unwrap();
+        let fen = "4k3/8/8/8/8/8/8/4K3[O] w - - 0 1";
+        let mut chess_match = ChessMatch::from_custom_fen(Variant::Crazyhouse, &variant.pieces, fen).unwrap();
+        assert_eq!(chess_match.hand(Color::White), [PieceKind::Custom('O')]);
+        // The king's 5 steps and a drop on each of the other 62 squares
+        assert_eq!(chess_match.legal_moves().len(), 5 + 62);
+        let empty_hand = ChessMatch::from_custom_fen(Variant::Crazyhouse, &variant.pieces, "4k3/8/8/8/8/8/8/4K3[] w - - 0 1").unwrap();
+        assert_ne!(chess_match.zobrist_hash(), empty_hand.zobrist_hash());
+
+        chess_match.perform_drop(PieceKind::Custom('O'), "d4".parse().unwrap()).unwrap();
+        assert_eq!(chess_match.to_fen(), "4k3/8/8/8/3O4/8/8/4K3[] b - - 1 1");
+    }
+
+    #[test]
+    fn bad_files_are_rejected() {
+        let error = |json: &str| CustomVariant::from_json(json).unwrap_err().0;
+        assert!(error("{").starts_with("Invalid variant file: "));
+        assert_eq!(error(r#"{ "name": "x", "variant": "shogi" }"#), "Unknown variant 'shogi'");
+        assert_eq!(
+            error(r#"{ "name": "x", "pieces": [{ "letter": "V", "name": "Vao", "betza": "Y" }] }"#),
+            "Invalid variant file: Unknown Betza atom 'Y' in 'Y'"
+        );
+        assert_eq!(
+            error(r#"{ "name": "x", "fen": "4k3/8/8/8/8/8/8/4KU2 w - - 0 1" }"#),
+            "Invalid variant file: Invalid FEN: unknown piece 'U' on rank 1"
+        );
+    }
+}
@@ -0,0 +1,561 @@
+// Forsyth-Edwards Notation: loading a ChessMatch from a position string and writing it back.
+//
+// The engine derives castling rights from move counts, so the castling field is turned
+// into "already moved" kings and rooks, and pawns away from their start rank are marked
+// as moved so they can't advance two squares. Besides "KQkq", the castling field may name
+// the files of the castling rooks (Shredder-FEN and X-FEN), as Chess960 positions need.
+// Crazyhouse positions list the pieces in hand after the placement, as in
+// "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[Nq]", and mark promoted pieces with "~".
+use crate::board::position::Position;
+use crate::board::Board;
+use crate::chess::betza::CustomPieces;
+use crate::chess::chess_position::ChessPosition;
+use crate::chess::color::Color;
+use crate::chess::piece_kind::PieceKind;
+use crate::chess::variant::Variant;
+use crate::chess::ChessMatch;
+use crate::error::ChessError;
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+pub const CAPABLANCA_START_FEN: &str = "rnabqkbcnr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNABQKBCNR w KQkq - 0 1";
+
+impl ChessMatch {
+    pub fn from_fen(fen: &str) -> Result<Self, ChessError> {
+        Self::from_variant_fen(Variant::Standard, fen)
+    }
+
+    // Position played by the rules of `variant`. A FEN with pieces in hand is Crazyhouse unless
+    // the variant already has drops (Bughouse).
+    pub fn from_variant_fen(variant: Variant, fen: &str) -> Result<Self, ChessError> {
+        Self::from_custom_fen(variant, &CustomPieces::default(), fen)
+    }
+
+    // Same, with the custom pieces of a variant file as further piece letters
+    pub fn from_custom_fen(variant: Variant, custom_pieces: &CustomPieces, fen: &str) -> Result<Self, ChessError> {
+        let mut fields = fen.split_whitespace().collect::<Vec<_>>();
+        // Three-check adds the checks given by each side after the move number, as in "+1+0"
+        let checks = match fields.last() {
+            Some(field) if field.starts_with('+') && fields.len() > 4 => {
+                if variant != Variant::ThreeCheck {
+                    return Err(ChessError(format!("Invalid FEN: checks are only counted in Three-check, found '{}'", field)));
+                }
+                let checks = parse_checks(field)?;
+                fields.pop();
+                checks
+            }
+            _ => [0, 0],
+        };
+        if fields.len() < 4 || fields.len() > 6 {
+            return Err(ChessError(format!(
+                "Invalid FEN: expected 4 to 6 fields separated by spaces, found {}",
+                fields.len()
+            )));
+        }
+
+        let (placement_field, pocket) = match fields[0].split_once('[') {
+            Some((placement, pocket)) => {
+                let pocket = pocket
+                    .strip_suffix(']')
+                    .ok_or_else(|| ChessError("Invalid FEN: the pieces in hand must end with ']'".to_string()))?;
+                (placement, Some(parse_pocket(pocket, custom_pieces)?))
+            }
+            None => (fields[0], None),
+        };
+        let (rows, cols) = variant.board_size();
+        let (placement, promoted) = parse_placement(placement_field, rows, cols, custom_pieces)?;
+        validate_placement(&placement, variant, rows)?;
+        let current_player = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            other => return Err(ChessError(format!("Invalid FEN: side to move must be 'w' or 'b', found '{}'", other))),
+        };
+        if !variant.has_castling() && fields[2] != "-" {
+            return Err(ChessError(format!("Invalid FEN: there is no castling in {}, found '{}'", variant, fields[2])));
+        }
+        let castling = parse_castling(fields[2], &placement, rows, cols)?;
+        let halfmove_clock = parse_number(fields.get(4).copied().unwrap_or("0"), "halfmove clock")?;
+        let fullmove_number = parse_number(fields.get(5).copied().unwrap_or("1"), "fullmove number")?;
+        if fullmove_number == 0 {
+            return Err(ChessError("Invalid FEN: the fullmove number starts at 1".to_string()));
+        }
+
+        let mut chess_match = ChessMatch::with_board(Board::new(rows, cols)?);
+        chess_match.variant = variant;
+        chess_match.custom_pieces = custom_pieces.clone();
+        for (pos, kind, color) in placement.iter().copied() {
+            let mut piece = chess_match.new_piece(kind, color);
+            if has_moved(&castling, pos, kind, color, rows) {
+                piece.increase_move_count();
+            }
+            if promoted.contains(&pos) {
+                piece.set_promoted();
+            }
+            chess_match.put_piece(piece, pos);
+        }
+        if let Some(hands) = pocket {
+            if !variant.has_drops() {
+                chess_match.variant = Variant::Crazyhouse;
+            }
+            chess_match.hands = hands;
+        }
+
+        chess_match.current_player = current_player;
+        chess_match.turn = (fullmove_number - 1) * 2 + 1 + (current_player == Color::Black) as u32;
+        chess_match.halfmove_clock = halfmove_clock;
+        chess_match.en_passant_vulnerable = parse_en_passant(fields[3], current_player, &placement, rows, cols)?;
+        chess_match.checks = checks;
+
+        if chess_match.test_check(current_player.opponent()) {
+            return Err(ChessError("Invalid FEN: the side not to move is in check".to_string()));
+        }
+        chess_match.variant_win = chess_match.variant_win_after(current_player.opponent());
+        chess_match.check = chess_match.variant_win.is_none() && chess_match.test_check(current_player);
+        chess_match.check_mate = chess_match.variant_win.is_none() && chess_match.test_check_mate(current_player);
+        if chess_match.winner().is_some() {
+            // The side that made the last move keeps the turn, as after a mating move
+            chess_match.current_player = current_player.opponent();
+            chess_match.turn = chess_match.turn.saturating_sub(1).max(1);
+        } else {
+            chess_match.stalemate = chess_match.test_stalemate(current_player);
+            chess_match.record_position();
+        }
+
+        let setup_fen = chess_match.to_fen();
+        let start_fen = match variant {
+            Variant::Capablanca => CAPABLANCA_START_FEN.to_string(),
+            Variant::ThreeCheck => format!("{} +0+0", START_FEN),
+            _ => START_FEN.to_string(),
+        };
+        if setup_fen != start_fen {
+            chess_match.setup_fen = Some(setup_fen);
+        }
+        Ok(chess_match)
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut ranks = Vec::new();
+        for row in 0..self.board.rows {
+            let mut rank = String::new();
+            let mut empty = 0;
+            for col in 0..self.board.cols {
+                match self.board.piece(Position::new(row, col)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            rank.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        let letter = piece.kind().letter();
+                        rank.push(if piece.color() == Color::White { letter } else { letter.to_ascii_lowercase() });
+                        if piece.is_promoted() && self.variant.has_drops() {
+                            rank.push('~');
+                        }
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                rank.push_str(&empty.to_string());
+            }
+            ranks.push(rank);
+        }
+
+        let mut placement = ranks.join("/");
+        if self.variant.has_drops() {
+            placement.push('[');
+            for color in [Color::White, Color::Black] {
+                for kind in self.hand(color) {
+                    placement.push(if color == Color::White { kind.letter() } else { kind.letter().to_ascii_lowercase() });
+                }
+            }
+            placement.push(']');
+        }
+
+        // After checkmate the turn stays with the winner, but the mated side is the one to move
+        let won = self.winner().is_some();
+        let side_to_move = if won { self.current_player.opponent() } else { self.current_player };
+        let ply = self.turn + won as u32;
+
+        let mut castling = String::new();
+        for color in [Color::White, Color::Black] {
+            for kingside in [true, false] {
+                if let Some(letter) = self.castling_letter(color, kingside) {
+                    castling.push(letter);
+                }
+            }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        // The square the pawn skipped, whether or not a capture there is possible
+        let en_passant = match self.en_passant_vulnerable {
+            Some(pos) => {
+                let pawn_color = self.board.piece(pos).map_or(Color::White, |p| p.color());
+                let row = if pawn_color == Color::White { pos.row + 1 } else { pos.row - 1 };
+                ChessPosition::from_position(Position::new(row, pos.col), self.board.rows).to_string()
+            }
+            None => "-".to_string(),
+        };
+
+        let mut fen = format!(
+            "{} {} {} {} {} {}",
+            placement,
+            if side_to_move == Color::White { 'w' } else { 'b' },
+            castling,
+            en_passant,
+            self.halfmove_clock,
+            ply.div_ceil(2)
+        );
+        if self.variant == Variant::ThreeCheck {
+            fen.push_str(&format!(" +{}+{}", self.checks[Color::White.index()], self.checks[Color::Black.index()]));
+        }
+        fen
+    }
+}
+
+impl ChessMatch {
+    // X-FEN: "K" or "Q" when the castling rook is the outermost rook on its side of the king,
+    // otherwise the rook's file, as in Shredder-FEN
+    fn castling_letter(&self, color: Color, kingside: bool) -> Option<char> {
+        let rook = self.castling_rook(color, kingside)?;
+        let is_rook = |col: usize| {
+            self.board
+                .piece(Position::new(rook.row, col))
+                .is_some_and(|p| p.kind() == PieceKind::Rook && p.color() == color)
+        };
+        let outermost = if kingside {
+            !(rook.col + 1..self.board.cols).any(is_rook)
+        } else {
+            !(0..rook.col).any(is_rook)
+        };
+        let letter = match (outermost, kingside) {
+            (true, true) => 'K',
+            (true, false) => 'Q',
+            (false, _) => (b'A' + rook.col as u8) as char,
+        };
+        Some(if color == Color::White { letter } else { letter.to_ascii_lowercase() })
+    }
+}
+
+type Placement = Vec<(Position, PieceKind, Color)>;
+
+// Castling rights as the file of the castling rook, (kingside, queenside), for White and Black
+type CastlingRights = [(Option<usize>, Option<usize>); 2];
+
+// The placement, and the squares of the pieces marked as promoted with "~". Runs of empty
+// squares may take more than one digit on boards wider than 9 files, as in "10".
+fn parse_placement(field: &str, rows: usize, cols: usize, custom_pieces: &CustomPieces) -> Result<(Placement, Vec<Position>), ChessError> {
+    let ranks = field.split('/').collect::<Vec<_>>();
+    if ranks.len() != rows {
+        return Err(ChessError(format!("Invalid FEN: expected {} ranks, found {}", rows, ranks.len())));
+    }
+
+    let mut placement = Vec::new();
+    let mut promoted = Vec::new();
+    for (row, rank) in ranks.iter().enumerate() {
+        let rank_number = rows - row;
+        let mut col: usize = 0;
+        let mut chars = rank.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '~' {
+                match placement.last() {
+                    Some(&(pos, _, _)) if pos == Position::new(row, col.wrapping_sub(1)) => promoted.push(pos),
+                    _ => return Err(ChessError(format!("Invalid FEN: '~' must follow a piece, on rank {}", rank_number))),
+                }
+            } else if let Some(digit) = c.to_digit(10).filter(|&d| d != 0) {
+                let mut empty = digit as usize;
+                while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                    // Saturates on long digit runs, which the square count below rejects
+                    empty = empty.saturating_mul(10).saturating_add(digit as usize);
+                    chars.next();
+                }
+                col = col.saturating_add(empty);
+            } else {
+                let kind = custom_pieces
+                    .kind(c)
+                    .ok_or_else(|| ChessError(format!("Invalid FEN: unknown piece '{}' on rank {}", c, rank_number)))?;
+                let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+                if col < cols {
+                    placement.push((Position::new(row, col), kind, color));
+                }
+                col += 1;
+            }
+            if col > cols {
+                return Err(ChessError(format!("Invalid FEN: rank {} has more than {} squares", rank_number, cols)));
+            }
+        }
+        if col < cols {
+            return Err(ChessError(format!("Invalid FEN: rank {} has fewer than {} squares", rank_number, cols)));
+        }
+    }
+    Ok((placement, promoted))
+}
+
+// Crazyhouse pieces in hand: uppercase letters for White, lowercase for Black
+fn parse_pocket(field: &str, custom_pieces: &CustomPieces) -> Result<[Vec<PieceKind>; 2], ChessError> {
+    let mut hands = [Vec::new(), Vec::new()];
+    for c in field.chars() {
+        match custom_pieces.kind(c) {
+            Some(kind) if kind != PieceKind::King => {
+                let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+                hands[color.index()].push(kind);
+            }
+            _ => return Err(ChessError(format!("Invalid FEN: '{}' can't be a piece in hand", c))),
+        }
+    }
+    Ok(hands)
+}
+
+// Each side needs exactly one king, and pawns can't stand on the first or last rank
+fn validate_placement(placement: &Placement, variant: Variant, rows: usize) -> Result<(), ChessError> {
+    for color in [Color::White, Color::Black] {
+        let kings = placement.iter().filter(|&&(_, kind, c)| kind == PieceKind::King && c == color).count();
+        // Antichess kings are ordinary pieces: any number of them, including none
+        if kings != 1 && variant != Variant::Antichess {
+            return Err(ChessError(format!("Invalid FEN: {:?} must have exactly one king, found {}", color, kings)));
+        }
+    }
+    if let Some(&(pos, _, _)) = placement.iter().find(|&&(pos, kind, _)| kind == PieceKind::Pawn && (pos.row == 0 || pos.row == rows - 1)) {
+        return Err(ChessError(format!("Invalid FEN: pawn on {}, pawns can't stand on the first or last rank", ChessPosition::from_position(pos, rows))));
+    }
+    Ok(())
+}
+
+// "K"/"Q" pick the outermost rook on that side of the king; a file letter (Shredder-FEN,
+// X-FEN) picks the rook on that file
+fn parse_castling(field: &str, placement: &Placement, rows: usize, cols: usize) -> Result<CastlingRights, ChessError> {
+    let mut rights = [(None, None); 2];
+    if field == "-" {
+        return Ok(rights);
+    }
+    for c in field.chars() {
+        let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+        let home_row = home_row(color, rows);
+        let king = placement
+            .iter()
+            .find(|&&(_, kind, c)| kind == PieceKind::King && c == color)
+            .map(|&(pos, _, _)| pos)
+            .unwrap();
+        let is_rook = |col: &usize| placement.contains(&(Position::new(home_row, *col), PieceKind::Rook, color));
+
+        let rook_col = match c.to_ascii_lowercase() {
+            'k' => (king.col + 1..cols).rev().find(is_rook),
+            'q' => (0..king.col).find(is_rook),
+            file @ 'a'..='z' if ((file as u8 - b'a') as usize) < cols => {
+                Some((file as u8 - b'a') as usize).filter(|col| *col != king.col && is_rook(col))
+            }
+            _ => return Err(ChessError(format!("Invalid FEN: unknown castling right '{}'", c))),
+        };
+        let rook_col = match rook_col {
+            Some(col) if king.row == home_row => col,
+            _ => {
+                return Err(ChessError(format!(
+                    "Invalid FEN: castling right '{}' needs the king on its first rank and a rook on that side of it",
+                    c
+                )))
+            }
+        };
+        let side = &mut rights[color.index()];
+        if rook_col > king.col { side.0 = Some(rook_col) } else { side.1 = Some(rook_col) }
+    }
+    Ok(rights)
+}
+
+// Whether the piece must be marked as moved for the castling rights and pawn double steps to come out right.
+// Rooks on the first rank keep their right to castle only if the castling field names them.
+fn has_moved(castling: &CastlingRights, pos: Position, kind: PieceKind, color: Color, rows: usize) -> bool {
+    let home_row = home_row(color, rows);
+    let (kingside, queenside) = castling[color.index()];
+    match kind {
+        PieceKind::Pawn => pos.row != if color == Color::White { rows - 2 } else { 1 },
+        PieceKind::King => kingside.is_none() && queenside.is_none(),
+        PieceKind::Rook if pos.row == home_row => kingside != Some(pos.col) && queenside != Some(pos.col),
+        _ => false,
+    }
+}
+
+fn home_row(color: Color, rows: usize) -> usize {
+    if color == Color::White { rows - 1 } else { 0 }
+}
+
+// The FEN names the square behind the pawn; the engine tracks the pawn itself
+fn parse_en_passant(
+    field: &str,
+    current_player: Color,
+    placement: &Placement,
+    rows: usize,
+    cols: usize,
+) -> Result<Option<Position>, ChessError> {
+    if field == "-" {
+        return Ok(None);
+    }
+    let square = field
+        .parse::<ChessPosition>()
+        .map_err(|_| ChessError(format!("Invalid FEN: bad en passant square '{}'", field)))?;
+    let not_behind_a_pawn = || {
+        ChessError(format!(
+            "Invalid FEN: en passant square '{}' is not behind a pawn that just advanced two squares",
+            field
+        ))
+    };
+    let target = square.to_position(rows, cols).map_err(|_| not_behind_a_pawn())?;
+    // Third rank from the side that just moved
+    let (pawn, pawn_color) = match current_player {
+        Color::White if target.row == 2 => (Position::new(target.row + 1, target.col), Color::Black),
+        Color::Black if target.row == rows - 3 => (Position::new(target.row - 1, target.col), Color::White),
+        _ => return Err(not_behind_a_pawn()),
+    };
+    if !placement.contains(&(pawn, PieceKind::Pawn, pawn_color)) {
+        return Err(not_behind_a_pawn());
+    }
+    Ok(Some(pawn))
+}
+
+fn parse_number(field: &str, name: &str) -> Result<u32, ChessError> {
+    field
+        .parse()
+        .map_err(|_| ChessError(format!("Invalid FEN: {} must be a number, found '{}'", name, field)))
+}
+
+// "+W+B": the checks given by White and by Black, at most the three that end the game
+fn parse_checks(field: &str) -> Result<[u8; 2], ChessError> {
+    let invalid = || ChessError(format!("Invalid FEN: check counts must look like '+1+0', found '{}'", field));
+    let (white, black) = field.strip_prefix('+').and_then(|counts| counts.split_once('+')).ok_or_else(invalid)?;
+    let count = |count: &str| count.parse::<u8>().ok().filter(|&count| count <= 3).ok_or_else(invalid);
+    let mut checks = [0, 0];
+    checks[Color::White.index()] = count(white)?;
+    checks[Color::Black.index()] = count(black)?;
+    Ok(checks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fen_error(fen: &str) -> String {
+        match ChessMatch::from_fen(fen) {
+            Ok(_) => panic!("{} should be rejected", fen),
+            Err(e) => e.0,
+        }
+    }
+
+    #[test]
+    fn start_position_round_trip() {
+        let chess_match = ChessMatch::from_fen(START_FEN).unwrap();
+        assert_eq!(chess_match.to_fen(), START_FEN);
+        assert_eq!(ChessMatch::new().to_fen(), START_FEN);
+        assert_eq!(chess_match.zobrist_hash(), ChessMatch::new().zobrist_hash());
+    }
+
+    #[test]
+    fn export_tracks_en_passant_castling_and_clocks() {
+        let mut chess_match = ChessMatch::new();
+        chess_match.play(&[("e2", "e4")]);
+        assert_eq!(chess_match.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+
+        chess_match.play(&[("c7", "c5"), ("g1", "f3"), ("b8", "c6"), ("h1", "g1")]);
+        assert_eq!(chess_match.to_fen(), "r1bqkbnr/pp1ppppp/2n5/2p5/4P3/5N2/PPPP1PPP/RNBQKBR1 b Qkq - 3 3");
+    }
+
+    #[test]
+    fn positions_round_trip() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "4k3/8/8/8/8/8/8/R3K3 b Q - 12 40",
+        ] {
+            assert_eq!(ChessMatch::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn imported_position_follows_the_rules() {
+        // Castling rights and the en passant square come from the FEN, not from the move counts
+        let mut chess_match = ChessMatch::from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w Kq d6 0 1").unwrap();
+        let moves = chess_match.legal_moves().iter().map(|mv| mv.to_coordinates(8)).collect::<Vec<_>>();
+        assert!(moves.contains(&"e1g1".to_string()));
+        assert!(!moves.contains(&"e1c1".to_string()));
+        assert!(moves.contains(&"e5d6".to_string()));
+
+        // Pawns away from their start rank don't get a double step
+        let mut chess_match = ChessMatch::from_fen("4k3/8/8/8/8/4P3/8/4K3 w - - 0 1").unwrap();
+        assert!(!chess_match.legal_moves().iter().any(|mv| mv.to_coordinates(8) == "e3e5"));
+    }
+
+    #[test]
+    fn shredder_and_x_fen_castling() {
+        // Shredder-FEN names the rook files; X-FEN writes them as KQkq when the rook is the outermost one
+        let shredder = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+        let chess_match = ChessMatch::from_fen(shredder).unwrap();
+        assert_eq!(chess_match.to_fen(), "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9");
+
+        // With two rooks on the kingside, the inner one needs its file
+        let inner = "4k3/8/8/8/8/8/8/R3KR1R w FQ - 0 1";
+        let mut chess_match = ChessMatch::from_fen(inner).unwrap();
+        assert_eq!(chess_match.to_fen(), inner);
+        let moves = chess_match.legal_moves().iter().map(|mv| mv.to_coordinates(8)).collect::<Vec<_>>();
+        assert!(moves.contains(&"e1g1".to_string()) && moves.contains(&"e1c1".to_string()));
+        chess_match.perform_chess_move("e1".parse().unwrap(), "g1".parse().unwrap(), None).unwrap();
+        assert_eq!(chess_match.to_fen(), "4k3/8/8/8/8/8/8/R4RKR b - - 1 1");
+
+        assert!(fen_error("4k3/8/8/8/8/8/8/R3K2R w D - 0 1").contains("castling right 'D'"));
+        assert!(fen_error("4k3/8/8/8/8/8/8/R3K2R w E - 0 1").contains("castling right 'E'"));
+    }
+
+    #[test]
+    fn checkmate_and_stalemate_positions() {
+        let mated = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3";
+        let chess_match = ChessMatch::from_fen(mated).unwrap();
+        assert!(chess_match.check_mate);
+        assert_eq!(chess_match.to_fen(), mated);
+
+        let chess_match = ChessMatch::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(chess_match.stalemate);
+    }
+
+    #[test]
+    fn three_check_keeps_the_check_counts() {
+        let fen = "rnb1kbnr/pppp1ppp/8/4p3/4P2q/8/PPPPKPPP/RNBQ1BNR w kq - 2 3 +0+1";
+        let chess_match = ChessMatch::from_variant_fen(Variant::ThreeCheck, fen).unwrap();
+        assert_eq!(chess_match.checks, [0, 1]);
+        assert_eq!(chess_match.to_fen(), fen);
+
+        // Without the field, no check has been given yet
+        let chess_match = ChessMatch::from_variant_fen(Variant::ThreeCheck, START_FEN).unwrap();
+        assert_eq!(chess_match.to_fen(), format!("{} +0+0", START_FEN));
+        assert!(chess_match.setup_fen.is_none());
+
+        assert!(fen_error(&format!("{} +0+1", START_FEN)).contains("only counted in Three-check"));
+        for counts in ["+4+0", "+1", "+a+0"] {
+            let error = ChessMatch::from_variant_fen(Variant::ThreeCheck, &format!("{} {}", START_FEN, counts)).err().unwrap();
+            assert!(error.0.contains("check counts"), "{}", counts);
+        }
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        assert!(fen_error("8/8/8 w - -").contains("expected 8 ranks"));
+        assert!(fen_error("rnbqkbnr/pppppppp/5p3/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").contains("more than 8 squares"));
+        assert!(fen_error("4k3/8/8/8/8/8/8/4K99999999999999999999999 w - - 0 1").contains("more than 8 squares"));
+        assert!(fen_error("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").contains("fewer than 8 squares"));
+        assert!(fen_error("rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").contains("unknown piece 'x'"));
+        assert!(fen_error("4k3/8/8/8/8/8/8/4K3[Qn w - - 0 1").contains("must end with ']'"));
+        assert!(fen_error("4k3/8/8/8/8/8/8/4K3[K] w - - 0 1").contains("'K' can't be a piece in hand"));
+        assert!(fen_error("~4k3/8/8/8/8/8/8/4K3[] w - - 0 1").contains("'~' must follow a piece"));
+        assert!(fen_error("4k3/8/8/8/8/8/8/4K3 x - - 0 1").contains("side to move"));
+        assert!(fen_error("4k3/8/8/8/8/8/8/4K3 w X - 0 1").contains("unknown castling right"));
+        assert!(fen_error("4k3/8/8/8/8/8/8/4K3 w K - 0 1").contains("castling right 'K'"));
+        assert!(fen_error("4k3/8/8/8/8/8/8/4K3 w - e6 0 1").contains("en passant"));
+        assert!(fen_error("4k3/8/8/8/8/8/8/4K3 w - - x 1").contains("halfmove clock"));
+        assert!(fen_error("4k3/8/8/8/8/8/8/4K3 w - - 0 0").contains("fullmove number"));
+    }
+
+    #[test]
+    fn impossible_positions_are_rejected() {
+        assert!(fen_error("8/8/8/8/8/8/8/4K3 w - - 0 1").contains("exactly one king"));
+        assert!(fen_error("4k3/8/8/8/8/8/8/3KK3 w - - 0 1").contains("exactly one king"));
+        assert!(fen_error("4k3/8/8/8/8/8/8/P3K3 w - - 0 1").contains("pawn on a1"));
+        assert!(fen_error("4k2R/8/8/8/8/8/8/4K3 w - - 0 1").contains("not to move is in check"));
+    }
+}
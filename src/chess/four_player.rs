@@ -0,0 +1,876 @@
+// Four-player chess on a 14x14 board shaped like a cross: the 3x3 squares in each corner
+// are not part of the board. Red starts at the bottom, Blue on the left, Yellow at the top
+// and Green on the right, each with the usual eight pieces and eight pawns, and the turn goes
+// round in that order (clockwise).
+//
+// In free-for-all, captures score points (pawn 1, knight 3, bishop and rook 5, queen 9, any
+// promoted piece 1), checkmating a player scores 20, and a stalemated player is out but
+// scores 20 themselves. An eliminated player's pieces stay on the board as dead pieces,
+// which block but don't move or threaten and are worth nothing. The game ends when one
+// player is left, and the highest score wins.
+//
+// In teams, Red and Yellow play against Blue and Green; partners can't capture each other's
+// pieces, and the first checkmate ends the game. A stalemate is a draw.
+//
+// Since three moves pass between a player's turns, a king can be left attacked by someone
+// other than the player who just moved. The next of those attackers to move may capture it,
+// which eliminates its owner like a checkmate. En passant is not played.
+//
+// Pieces other than the pawn move and attack through pieces/movement.rs, as in the two-player
+// game; only the pawns, which advance towards a different side for each player, are moved here.
+use crate::board::position::Position;
+use crate::chess::chess_position::ChessPosition;
+use crate::chess::piece_kind::PieceKind;
+use crate::chess::pieces::movement;
+use crate::error::ChessError;
+use crate::network::{FourPieceView, GameEndReason, GameMessage};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+pub const BOARD_SIZE: usize = 14;
+// Side of the missing square in each corner
+const CORNER: usize = 3;
+
+pub fn is_on_board(pos: Position) -> bool {
+    let edge = |i: usize| !(CORNER..BOARD_SIZE - CORNER).contains(&i);
+    pos.row < BOARD_SIZE && pos.col < BOARD_SIZE && !(edge(pos.row) && edge(pos.col))
+}
+
+// The players' colors come from the seat, so every piece is drawn with the filled glyph
+fn glyph(kind: PieceKind) -> &'static str {
+    match kind {
+        PieceKind::King => "♚",
+        PieceKind::Queen => "♛",
+        PieceKind::Rook => "♜",
+        PieceKind::Bishop => "♝",
+        PieceKind::Knight => "♞",
+        PieceKind::Pawn => "♟",
+        other => unreachable!("{:?} is not a four-player piece", other),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Seat {
+    Red,
+    Blue,
+    Yellow,
+    Green,
+}
+
+impl Seat {
+    // In turn order
+    pub const ALL: [Seat; 4] = [Seat::Red, Seat::Blue, Seat::Yellow, Seat::Green];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn next(self) -> Seat {
+        Seat::ALL[(self.index() + 1) % 4]
+    }
+
+    // The player sitting opposite, who is the partner in teams
+    pub fn partner(self) -> Seat {
+        Seat::ALL[(self.index() + 2) % 4]
+    }
+
+    // Direction the pawns advance in, as (rows, cols)
+    fn forward(self) -> (isize, isize) {
+        match self {
+            Seat::Red => (-1, 0),
+            Seat::Blue => (0, 1),
+            Seat::Yellow => (1, 0),
+            Seat::Green => (0, -1),
+        }
+    }
+
+    // How far the square is from this player's edge of the board: 0 on their back rank
+    fn distance_from_edge(self, pos: Position) -> usize {
+        match self {
+            Seat::Red => BOARD_SIZE - 1 - pos.row,
+            Seat::Blue => pos.col,
+            Seat::Yellow => pos.row,
+            Seat::Green => BOARD_SIZE - 1 - pos.col,
+        }
+    }
+
+    // Square `rank` squares in from this player's edge and `file` squares along it, counting
+    // from the player's left
+    fn square(self, rank: usize, file: usize) -> Position {
+        let far = BOARD_SIZE - 1;
+        match self {
+            Seat::Red => Position::new(far - rank, file),
+            Seat::Blue => Position::new(file, rank),
+            Seat::Yellow => Position::new(rank, far - file),
+            Seat::Green => Position::new(far - file, far - rank),
+        }
+    }
+}
+
+impl fmt::Display for Seat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FourPlayerMode {
+    #[default]
+    FreeForAll,
+    Teams,
+}
+
+impl FromStr for FourPlayerMode {
+    type Err = ChessError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ffa" | "free-for-all" | "freeforall" => Ok(FourPlayerMode::FreeForAll),
+            "teams" | "team" => Ok(FourPlayerMode::Teams),
+            _ => Err(ChessError(format!("Unknown four-player mode '{}'", s))),
+        }
+    }
+}
+
+impl fmt::Display for FourPlayerMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FourPlayerMode::FreeForAll => write!(f, "free-for-all"),
+            FourPlayerMode::Teams => write!(f, "teams"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FourPiece {
+    pub kind: PieceKind,
+    pub seat: Seat,
+    pub move_count: u32,
+    // Belongs to an eliminated player
+    pub dead: bool,
+    pub promoted: bool,
+}
+
+impl FourPiece {
+    fn new(kind: PieceKind, seat: Seat) -> Self {
+        FourPiece { kind, seat, move_count: 0, dead: false, promoted: false }
+    }
+
+    // Points for capturing it in free-for-all; a promoted piece is worth the pawn it was
+    fn value(&self) -> u32 {
+        if self.dead {
+            return 0;
+        }
+        if self.promoted {
+            return 1;
+        }
+        match self.kind {
+            PieceKind::Pawn => 1,
+            PieceKind::Knight => 3,
+            PieceKind::Bishop | PieceKind::Rook => 5,
+            PieceKind::Queen => 9,
+            PieceKind::King => 20,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FourMove {
+    pub source: Position,
+    pub target: Position,
+    pub promotion: Option<PieceKind>,
+    // Castling: where the rook comes from and goes to
+    pub castling_rook: Option<(Position, Position)>,
+}
+
+impl FourMove {
+    // Coordinate notation, e.g. "h2h4" or "h7h8q"
+    pub fn to_coordinates(&self) -> String {
+        let square = |pos: Position| ChessPosition::from_position(pos, BOARD_SIZE).to_string();
+        let promotion = self.promotion.map(|kind| kind.letter().to_ascii_lowercase().to_string()).unwrap_or_default();
+        format!("{}{}{}", square(self.source), square(self.target), promotion)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FourPlayerResult {
+    // Highest scorers in free-for-all, the winning team in teams; none for a draw
+    pub winners: Vec<Seat>,
+    pub reason: GameEndReason,
+}
+
+const BACK_RANK: [PieceKind; 8] = [
+    PieceKind::Rook,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Queen,
+    PieceKind::King,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+    PieceKind::Rook,
+];
+
+#[derive(Clone)]
+pub struct FourPlayerMatch {
+    squares: Vec<Vec<Option<FourPiece>>>,
+    mode: FourPlayerMode,
+    current_player: Seat,
+    // Why each player is out of the game, if they are
+    eliminated: [Option<GameEndReason>; 4],
+    points: [u32; 4],
+    result: Option<FourPlayerResult>,
+    history: Vec<(Seat, FourMove)>,
+}
+
+impl FourPlayerMatch {
+    pub fn new(mode: FourPlayerMode) -> Self {
+        let mut squares = vec![vec![None; BOARD_SIZE]; BOARD_SIZE];
+        for seat in Seat::ALL {
+            for (file, &kind) in BACK_RANK.iter().enumerate() {
+                // Everyone has the queen on their left of the king, as seen from their side
+                let back = seat.square(0, CORNER + file);
+                let pawn = seat.square(1, CORNER + file);
+                squares[back.row][back.col] = Some(FourPiece::new(kind, seat));
+                squares[pawn.row][pawn.col] = Some(FourPiece::new(PieceKind::Pawn, seat));
+            }
+        }
+        FourPlayerMatch {
+            squares,
+            mode,
+            current_player: Seat::Red,
+            eliminated: [None; 4],
+            points: [0; 4],
+            result: None,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn mode(&self) -> FourPlayerMode {
+        self.mode
+    }
+
+    pub fn current_player(&self) -> Seat {
+        self.current_player
+    }
+
+    pub fn piece(&self, pos: Position) -> Option<&FourPiece> {
+        self.squares.get(pos.row)?.get(pos.col)?.as_ref()
+    }
+
+    pub fn points(&self, seat: Seat) -> u32 {
+        self.points[seat.index()]
+    }
+
+    pub fn elimination(&self, seat: Seat) -> Option<GameEndReason> {
+        self.eliminated[seat.index()]
+    }
+
+    pub fn is_active(&self, seat: Seat) -> bool {
+        self.eliminated[seat.index()].is_none()
+    }
+
+    pub fn result(&self) -> Option<&FourPlayerResult> {
+        self.result.as_ref()
+    }
+
+    pub fn history(&self) -> &[(Seat, FourMove)] {
+        &self.history
+    }
+
+    // What the clients are sent after every move; corner squares are always empty
+    pub fn to_state(&self, message: String) -> GameMessage {
+        let board = self
+            .squares
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|square| {
+                        square.map(|piece| FourPieceView {
+                            symbol: glyph(piece.kind).to_string(),
+                            kind: piece.kind,
+                            seat: piece.seat,
+                            dead: piece.dead,
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+        GameMessage::FourPlayerState {
+            board,
+            turn: self.current_player,
+            mode: self.mode,
+            points: Seat::ALL.into_iter().map(|seat| (seat, self.points(seat))).collect(),
+            eliminated: Seat::ALL.into_iter().filter(|&seat| !self.is_active(seat)).collect(),
+            in_check: Seat::ALL.into_iter().filter(|&seat| self.is_in_check(seat)).collect(),
+            message,
+        }
+    }
+
+    pub fn is_in_check(&self, seat: Seat) -> bool {
+        self.is_active(seat) && self.king(seat).is_some_and(|king| self.is_attacked(king, seat))
+    }
+
+    // Whether `attacker`'s pieces may capture `defender`'s
+    fn are_enemies(&self, attacker: Seat, defender: Seat) -> bool {
+        attacker != defender && !(self.mode == FourPlayerMode::Teams && attacker.partner() == defender)
+    }
+
+    fn king(&self, seat: Seat) -> Option<Position> {
+        self.positions().find(|&pos| self.piece(pos).is_some_and(|p| p.kind == PieceKind::King && p.seat == seat && !p.dead))
+    }
+
+    fn positions(&self) -> impl Iterator<Item = Position> {
+        (0..BOARD_SIZE).flat_map(|row| (0..BOARD_SIZE).map(move |col| Position::new(row, col))).filter(|&pos| is_on_board(pos))
+    }
+
+    fn is_occupied(&self, pos: Position) -> bool {
+        self.piece(pos).is_some()
+    }
+
+    // Whether any live piece of an active enemy of `seat` attacks the square
+    fn is_attacked(&self, pos: Position, seat: Seat) -> bool {
+        let is_attacker = |p: Position, kind: PieceKind| {
+            self.piece(p).is_some_and(|piece| !piece.dead && piece.kind == kind && self.are_enemies(piece.seat, seat))
+        };
+        movement::is_attacked(pos, is_on_board, |p| self.is_occupied(p), is_attacker)
+            // A pawn attacks the two squares diagonally ahead of it
+            || Seat::ALL.iter().any(|&pawn_seat| {
+                let (fr, fc) = pawn_seat.forward();
+                let sides = [(fc, fr), (-fc, -fr)];
+                sides.iter().any(|&(sr, sc)| {
+                    movement::offset(pos, (-fr - sr, -fc - sc), is_on_board)
+                        .and_then(|p| self.piece(p).filter(|piece| piece.seat == pawn_seat))
+                        .is_some_and(|piece| piece.kind == PieceKind::Pawn && !piece.dead && self.are_enemies(pawn_seat, seat))
+                })
+            })
+    }
+
+    // Moves of `seat`'s pieces that don't leave their own king attacked
+    pub fn legal_moves(&mut self, seat: Seat) -> Vec<FourMove> {
+        if !self.is_active(seat) {
+            return Vec::new();
+        }
+        self.pseudo_legal_moves(seat).into_iter().filter(|mv| self.is_legal(seat, mv)).collect()
+    }
+
+    // Tries the move on the board and takes it back, as ChessMatch does
+    fn is_legal(&mut self, seat: Seat, mv: &FourMove) -> bool {
+        let captured = self.move_pieces(mv);
+        let legal = self.king(seat).is_none_or(|king| !self.is_attacked(king, seat));
+        self.unmove_pieces(mv, captured);
+        legal
+    }
+
+    fn pseudo_legal_moves(&self, seat: Seat) -> Vec<FourMove> {
+        let mut moves = Vec::new();
+        for source in self.positions() {
+            let Some(piece) = self.piece(source).filter(|p| p.seat == seat && !p.dead) else {
+                continue;
+            };
+            for target in self.targets(source, piece) {
+                let promotes = piece.kind == PieceKind::Pawn && seat.distance_from_edge(target) == self.promotion_rank();
+                moves.push(FourMove { source, target, promotion: promotes.then_some(PieceKind::Queen), castling_rook: None });
+            }
+            if piece.kind == PieceKind::King {
+                moves.extend(self.castling_moves(source, piece));
+            }
+        }
+        moves
+    }
+
+    // Pawns promote on the 8th rank, the middle of the board, in free-for-all, and on the
+    // 11th in teams
+    fn promotion_rank(&self) -> usize {
+        match self.mode {
+            FourPlayerMode::FreeForAll => 7,
+            FourPlayerMode::Teams => 10,
+        }
+    }
+
+    fn can_land_on(&self, piece: &FourPiece, pos: Position) -> bool {
+        self.piece(pos).is_none_or(|other| other.dead || self.are_enemies(piece.seat, other.seat))
+    }
+
+    fn targets(&self, source: Position, piece: &FourPiece) -> Vec<Position> {
+        if piece.kind != PieceKind::Pawn {
+            let mut targets = movement::reach(piece.kind, source, is_on_board, |p| self.is_occupied(p));
+            targets.retain(|&p| self.can_land_on(piece, p));
+            return targets;
+        }
+        // Pawns move towards the opposite side, which is a different direction for each player
+        let mut targets = Vec::new();
+        let (fr, fc) = piece.seat.forward();
+        if let Some(one) = movement::offset(source, (fr, fc), is_on_board).filter(|&p| !self.is_occupied(p)) {
+            targets.push(one);
+            if piece.move_count == 0 {
+                targets.extend(movement::offset(one, (fr, fc), is_on_board).filter(|&p| !self.is_occupied(p)));
+            }
+        }
+        for side in [(fc, fr), (-fc, -fr)] {
+            targets.extend(
+                movement::offset(source, (fr + side.0, fc + side.1), is_on_board)
+                    .filter(|&p| self.is_occupied(p) && self.can_land_on(piece, p)),
+            );
+        }
+        targets
+    }
+
+    // The king goes two squares towards an unmoved rook of its own on its back rank, with
+    // nothing in between, and the rook to the square the king crossed. The king may not be in
+    // check or cross an attacked square.
+    fn castling_moves(&self, king: Position, piece: &FourPiece) -> Vec<FourMove> {
+        let seat = piece.seat;
+        if piece.move_count > 0 || seat.distance_from_edge(king) != 0 || self.is_attacked(king, seat) {
+            return Vec::new();
+        }
+        let (fr, fc) = seat.forward();
+        let mut moves = Vec::new();
+        for direction in [(fc, fr), (-fc, -fr)] {
+            let mut p = king;
+            let rook = loop {
+                match movement::offset(p, direction, is_on_board) {
+                    Some(next) if self.piece(next).is_none() => p = next,
+                    Some(next) => break Some(next),
+                    None => break None,
+                }
+            };
+            let Some(rook) = rook.filter(|&r| {
+                self.piece(r).is_some_and(|p| p.kind == PieceKind::Rook && p.seat == seat && p.move_count == 0 && !p.dead)
+            }) else {
+                continue;
+            };
+            let crossed = movement::offset(king, direction, is_on_board);
+            let target = movement::offset(king, (2 * direction.0, 2 * direction.1), is_on_board);
+            let (Some(crossed), Some(target)) = (crossed, target) else {
+                continue;
+            };
+            // The rook must be beyond the king's target square
+            if rook == crossed || rook == target || self.is_attacked(crossed, seat) {
+                continue;
+            }
+            moves.push(FourMove { source: king, target, promotion: None, castling_rook: Some((rook, crossed)) });
+        }
+        moves
+    }
+
+    // Moves the pieces without any of the game's bookkeeping; returns what was captured
+    fn move_pieces(&mut self, mv: &FourMove) -> Option<FourPiece> {
+        let mut piece = self.squares[mv.source.row][mv.source.col].take().unwrap();
+        piece.move_count += 1;
+        if let Some(kind) = mv.promotion {
+            piece.kind = kind;
+            piece.promoted = true;
+        }
+        let captured = self.squares[mv.target.row][mv.target.col].replace(piece);
+        if let Some((rook_source, rook_target)) = mv.castling_rook {
+            let mut rook = self.squares[rook_source.row][rook_source.col].take().unwrap();
+            rook.move_count += 1;
+            self.squares[rook_target.row][rook_target.col] = Some(rook);
+        }
+        captured
+    }
+
+    // Takes back move_pieces, putting the captured piece back on the target square
+    fn unmove_pieces(&mut self, mv: &FourMove, captured: Option<FourPiece>) {
+        if let Some((rook_source, rook_target)) = mv.castling_rook {
+            let mut rook = self.squares[rook_target.row][rook_target.col].take().unwrap();
+            rook.move_count -= 1;
+            self.squares[rook_source.row][rook_source.col] = Some(rook);
+        }
+        let mut piece = std::mem::replace(&mut self.squares[mv.target.row][mv.target.col], captured).unwrap();
+        piece.move_count -= 1;
+        if mv.promotion.is_some() {
+            piece.kind = PieceKind::Pawn;
+            piece.promoted = false;
+        }
+        self.squares[mv.source.row][mv.source.col] = Some(piece);
+    }
+
+    // Plays a move of the player to move, given as squares on the 14x14 board ("a1" to
+    // "n14"). A pawn reaching its promotion rank becomes a queen unless told otherwise.
+    pub fn perform_move(
+        &mut self,
+        source: ChessPosition,
+        target: ChessPosition,
+        promotion: Option<PieceKind>,
+    ) -> Result<FourMove, ChessError> {
+        if self.result.is_some() {
+            return Err(ChessError("The game is over".to_string()));
+        }
+        let seat = self.current_player;
+        let (source, target) = (source.to_position(BOARD_SIZE, BOARD_SIZE)?, target.to_position(BOARD_SIZE, BOARD_SIZE)?);
+        if !is_on_board(source) || !is_on_board(target) {
+            return Err(ChessError("The corner squares are not part of the board".to_string()));
+        }
+        match self.piece(source) {
+            None => return Err(ChessError("There is no piece on source position".to_string())),
+            Some(piece) if piece.seat != seat || piece.dead => return Err(ChessError("The chosen piece is not yours".to_string())),
+            _ => {}
+        }
+        if promotion.is_some_and(|kind| !matches!(kind, PieceKind::Queen | PieceKind::Rook | PieceKind::Bishop | PieceKind::Knight)) {
+            return Err(ChessError("A pawn can only be promoted to a queen, rook, bishop or knight".to_string()));
+        }
+
+        let candidates = self.pseudo_legal_moves(seat).into_iter().filter(|mv| mv.source == source && mv.target == target).collect::<Vec<_>>();
+        let Some(mut mv) = candidates.first().copied() else {
+            return Err(ChessError("The chosen piece can't move to target position".to_string()));
+        };
+        match (mv.promotion, promotion) {
+            (Some(_), Some(kind)) => mv.promotion = Some(kind),
+            (None, Some(_)) => return Err(ChessError("Only a pawn reaching its promotion rank can be promoted".to_string())),
+            _ => {}
+        }
+        if !self.is_legal(seat, &mv) {
+            return Err(ChessError("You can't put yourself in check".to_string()));
+        }
+
+        self.apply_move(seat, mv);
+        Ok(mv)
+    }
+
+    fn apply_move(&mut self, seat: Seat, mv: FourMove) {
+        let captured = self.move_pieces(&mv);
+        self.history.push((seat, mv));
+        if let Some(captured) = captured {
+            self.score(seat, captured.value());
+            if captured.kind == PieceKind::King && !captured.dead {
+                self.eliminate(captured.seat, GameEndReason::KingCaptured);
+            }
+        }
+
+        // Everyone the move left in check without a way out is mated, in turn order
+        let mut other = seat.next();
+        while other != seat {
+            if self.is_active(other) && self.is_in_check(other) && self.legal_moves(other).is_empty() {
+                self.score(seat, 20);
+                self.eliminate(other, GameEndReason::Checkmate);
+            }
+            other = other.next();
+        }
+        self.next_turn();
+    }
+
+    // Passes the turn to the next player still in the game, who is out if they have no moves
+    fn next_turn(&mut self) {
+        loop {
+            if self.check_game_over() {
+                return;
+            }
+            self.current_player = self.current_player.next();
+            let seat = self.current_player;
+            if !self.is_active(seat) {
+                continue;
+            }
+            if !self.legal_moves(seat).is_empty() {
+                return;
+            }
+            if self.is_in_check(seat) {
+                self.eliminate(seat, GameEndReason::Checkmate);
+            } else {
+                if self.mode == FourPlayerMode::Teams {
+                    self.result = Some(FourPlayerResult { winners: Vec::new(), reason: GameEndReason::Stalemate });
+                    return;
+                }
+                self.score(seat, 20);
+                self.eliminate(seat, GameEndReason::Stalemate);
+            }
+        }
+    }
+
+    // The player leaves the game, e.g. after disconnecting. On their turn, play passes on.
+    pub fn resign(&mut self, seat: Seat) {
+        if self.result.is_some() || !self.is_active(seat) {
+            return;
+        }
+        self.eliminate(seat, GameEndReason::Disconnect);
+        if self.current_player == seat {
+            self.next_turn();
+        } else {
+            self.check_game_over();
+        }
+    }
+
+    fn score(&mut self, seat: Seat, points: u32) {
+        if self.mode == FourPlayerMode::FreeForAll {
+            self.points[seat.index()] += points;
+        }
+    }
+
+    fn eliminate(&mut self, seat: Seat, reason: GameEndReason) {
+        self.eliminated[seat.index()] = Some(reason);
+        for piece in self.squares.iter_mut().flatten().flatten().filter(|p| p.seat == seat) {
+            piece.dead = true;
+        }
+    }
+
+    fn check_game_over(&mut self) -> bool {
+        if self.result.is_some() {
+            return true;
+        }
+        let active = Seat::ALL.into_iter().filter(|&seat| self.is_active(seat)).collect::<Vec<_>>();
+        self.result = match self.mode {
+            // The first player out loses the game for their team
+            FourPlayerMode::Teams => Seat::ALL.into_iter().find_map(|seat| {
+                let winners = Seat::ALL.into_iter().filter(|&other| self.are_enemies(other, seat)).collect();
+                self.elimination(seat).map(|reason| FourPlayerResult { winners, reason })
+            }),
+            FourPlayerMode::FreeForAll if active.len() <= 1 => Some(self.points_result(GameEndReason::LastPlayerStanding)),
+            // Kings alone can't mate each other
+            FourPlayerMode::FreeForAll if self.only_kings_left() => Some(self.points_result(GameEndReason::InsufficientMaterial)),
+            FourPlayerMode::FreeForAll => None,
+        };
+        self.result.is_some()
+    }
+
+    fn only_kings_left(&self) -> bool {
+        self.squares.iter().flatten().flatten().all(|p| p.dead || p.kind == PieceKind::King)
+    }
+
+    // Free-for-all ends with the highest score winning, whether or not that player is still in
+    fn points_result(&self, reason: GameEndReason) -> FourPlayerResult {
+        let best = self.points.iter().copied().max().unwrap_or(0);
+        FourPlayerResult { winners: Seat::ALL.into_iter().filter(|&seat| self.points(seat) == best).collect(), reason }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::chess::{play_moves, square_on};
+
+    fn square(s: &str) -> Position {
+        square_on(s, BOARD_SIZE)
+    }
+
+    fn play(game: &mut FourPlayerMatch, moves: &[(&str, &str)]) {
+        play_moves(moves, |source, target| game.perform_move(source, target, None));
+    }
+
+    // Empty board with just the given pieces, Red to move
+    fn with_pieces(mode: FourPlayerMode, pieces: &[(&str, PieceKind, Seat)]) -> FourPlayerMatch {
+        let mut game = FourPlayerMatch::new(mode);
+        game.squares = vec![vec![None; BOARD_SIZE]; BOARD_SIZE];
+        for &(s, kind, seat) in pieces {
+            let pos = square(s);
+            let mut piece = FourPiece::new(kind, seat);
+            piece.move_count = 1;
+            game.squares[pos.row][pos.col] = Some(piece);
+        }
+        game
+    }
+
+    #[test]
+    fn cross_shaped_board_and_setup() {
+        assert!(!is_on_board(square("a1")) && !is_on_board(square("c3")) && !is_on_board(square("n14")));
+        assert!(is_on_board(square("d1")) && is_on_board(square("a4")) && is_on_board(square("k14")));
+        assert_eq!(Seat::ALL.iter().map(|&s| FourPlayerMatch::new(FourPlayerMode::FreeForAll).legal_moves(s).len()).sum::<usize>(), 80);
+
+        let game = FourPlayerMatch::new(FourPlayerMode::FreeForAll);
+        let kind_at = |s: &str| game.piece(square(s)).map(|p| (p.kind, p.seat));
+        assert_eq!(kind_at("h1"), Some((PieceKind::King, Seat::Red)));
+        assert_eq!(kind_at("g14"), Some((PieceKind::King, Seat::Yellow)));
+        assert_eq!(kind_at("a7"), Some((PieceKind::King, Seat::Blue)));
+        assert_eq!(kind_at("n8"), Some((PieceKind::King, Seat::Green)));
+        assert_eq!(kind_at("b4"), Some((PieceKind::Pawn, Seat::Blue)));
+    }
+
+    #[test]
+    fn turns_go_round_the_table() {
+        let mut game = FourPlayerMatch::new(FourPlayerMode::FreeForAll);
+        play(&mut game, &[("h2", "h4"), ("b7", "d7"), ("g13", "g11"), ("m8", "k8")]);
+        assert_eq!(game.current_player(), Seat::Red);
+        let err = game.perform_move("g14".parse().unwrap(), "g13".parse().unwrap(), None).unwrap_err();
+        assert_eq!(err.0, "The chosen piece is not yours");
+        let err = game.perform_move("a1".parse().unwrap(), "a2".parse().unwrap(), None).unwrap_err();
+        assert_eq!(err.0, "The corner squares are not part of the board");
+        assert_eq!(game.history().len(), 4);
+        assert_eq!(game.history()[1].1.to_coordinates(), "b7d7");
+    }
+
+    #[test]
+    fn captures_score_points_in_free_for_all() {
+        let mut game = with_pieces(
+            FourPlayerMode::FreeForAll,
+            &[
+                ("h1", PieceKind::King, Seat::Red),
+                ("h5", PieceKind::Rook, Seat::Red),
+                ("a7", PieceKind::King, Seat::Blue),
+                ("h9", PieceKind::Queen, Seat::Blue),
+                ("g14", PieceKind::King, Seat::Yellow),
+                ("n8", PieceKind::King, Seat::Green),
+            ],
+        );
+        play(&mut game, &[("h5", "h9")]);
+        assert_eq!(game.points(Seat::Red), 9);
+
+        // Partners can't capture each other in teams
+        let mut game = with_pieces(
+            FourPlayerMode::Teams,
+            &[
+                ("h1", PieceKind::King, Seat::Red),
+                ("h5", PieceKind::Rook, Seat::Red),
+                ("h9", PieceKind::Queen, Seat::Yellow),
+                ("a7", PieceKind::King, Seat::Blue),
+                ("g14", PieceKind::King, Seat::Yellow),
+                ("n8", PieceKind::King, Seat::Green),
+            ],
+        );
+        assert!(!game.legal_moves(Seat::Red).iter().any(|mv| mv.target == square("h9")));
+    }
+
+    #[test]
+    fn checkmate_eliminates_and_the_dead_pieces_stay() {
+        // Blue's king in the corner of its arm, mated by two red rooks along the a and b files
+        let mut game = with_pieces(
+            FourPlayerMode::FreeForAll,
+            &[
+                ("h1", PieceKind::King, Seat::Red),
+                ("b10", PieceKind::Rook, Seat::Red),
+                ("e11", PieceKind::Rook, Seat::Red),
+                ("a4", PieceKind::King, Seat::Blue),
+                ("c8", PieceKind::Pawn, Seat::Blue),
+                ("g14", PieceKind::King, Seat::Yellow),
+                ("n8", PieceKind::King, Seat::Green),
+            ],
+        );
+        play(&mut game, &[("e11", "a11")]);
+        assert_eq!(game.elimination(Seat::Blue), Some(GameEndReason::Checkmate));
+        assert_eq!(game.points(Seat::Red), 20);
+        // Blue is skipped, and its pieces are dead obstacles worth nothing
+        assert_eq!(game.current_player(), Seat::Yellow);
+        assert!(game.piece(square("c8")).unwrap().dead);
+        assert!(game.legal_moves(Seat::Blue).is_empty());
+        assert!(game.result().is_none());
+    }
+
+    #[test]
+    fn last_player_standing_ends_free_for_all_on_points() {
+        let mut game = FourPlayerMatch::new(FourPlayerMode::FreeForAll);
+        game.points = [3, 30, 0, 0];
+        game.resign(Seat::Yellow);
+        game.resign(Seat::Green);
+        assert!(game.result().is_none());
+        game.resign(Seat::Red);
+        assert_eq!(game.result(), Some(&FourPlayerResult { winners: vec![Seat::Blue], reason: GameEndReason::LastPlayerStanding }));
+        assert_eq!(game.perform_move("b7".parse().unwrap(), "c7".parse().unwrap(), None).unwrap_err().0, "The game is over");
+    }
+
+    #[test]
+    fn the_first_checkmate_decides_a_teams_game() {
+        let mut game = with_pieces(
+            FourPlayerMode::Teams,
+            &[
+                ("h1", PieceKind::King, Seat::Red),
+                ("b10", PieceKind::Rook, Seat::Red),
+                ("e11", PieceKind::Rook, Seat::Red),
+                ("a4", PieceKind::King, Seat::Blue),
+                ("g14", PieceKind::King, Seat::Yellow),
+                ("n8", PieceKind::King, Seat::Green),
+            ],
+        );
+        play(&mut game, &[("e11", "a11")]);
+        assert_eq!(game.result(), Some(&FourPlayerResult { winners: vec![Seat::Red, Seat::Yellow], reason: GameEndReason::Checkmate }));
+        assert_eq!(game.points(Seat::Red), 0);
+    }
+
+    #[test]
+    fn an_exposed_king_can_be_captured() {
+        // Red's knight move opens the g-file between Blue's queen and Yellow's king, and Blue
+        // moves before Yellow can get out of the way
+        let mut game = with_pieces(
+            FourPlayerMode::FreeForAll,
+            &[
+                ("h1", PieceKind::King, Seat::Red),
+                ("g9", PieceKind::Knight, Seat::Red),
+                ("a7", PieceKind::King, Seat::Blue),
+                ("g4", PieceKind::Queen, Seat::Blue),
+                ("g14", PieceKind::King, Seat::Yellow),
+                ("n8", PieceKind::King, Seat::Green),
+            ],
+        );
+        play(&mut game, &[("g9", "e10")]);
+        assert!(game.is_in_check(Seat::Yellow));
+        assert!(game.is_active(Seat::Yellow));
+        play(&mut game, &[("g4", "g14")]);
+        assert_eq!(game.elimination(Seat::Yellow), Some(GameEndReason::KingCaptured));
+        assert_eq!(game.points(Seat::Blue), 20);
+        assert_eq!(game.current_player(), Seat::Green);
+    }
+
+    #[test]
+    fn pawns_promote_in_the_middle_in_free_for_all() {
+        let mut game = with_pieces(
+            FourPlayerMode::FreeForAll,
+            &[
+                ("h1", PieceKind::King, Seat::Red),
+                ("e7", PieceKind::Pawn, Seat::Red),
+                ("a7", PieceKind::King, Seat::Blue),
+                ("g14", PieceKind::King, Seat::Yellow),
+                ("n8", PieceKind::King, Seat::Green),
+            ],
+        );
+        play(&mut game, &[("e7", "e8")]);
+        let queen = game.piece(square("e8")).unwrap();
+        assert_eq!((queen.kind, queen.promoted, queen.value()), (PieceKind::Queen, true, 1));
+
+        // Underpromoted pieces are worth 1 too
+        let mut game = with_pieces(
+            FourPlayerMode::FreeForAll,
+            &[
+                ("h1", PieceKind::King, Seat::Red),
+                ("e7", PieceKind::Pawn, Seat::Red),
+                ("a7", PieceKind::King, Seat::Blue),
+                ("e13", PieceKind::Rook, Seat::Blue),
+                ("g14", PieceKind::King, Seat::Yellow),
+                ("n8", PieceKind::King, Seat::Green),
+            ],
+        );
+        game.perform_move("e7".parse().unwrap(), "e8".parse().unwrap(), Some(PieceKind::Rook)).unwrap();
+        play(&mut game, &[("e13", "e8")]);
+        assert_eq!(game.points(Seat::Blue), 1);
+    }
+
+    #[test]
+    fn every_player_castles_along_their_own_back_rank() {
+        let mut game = FourPlayerMatch::new(FourPlayerMode::FreeForAll);
+        play(&mut game, &[("j2", "j3"), ("b5", "c5"), ("e13", "e12"), ("m10", "l10")]);
+        play(&mut game, &[("j1", "i3"), ("a5", "c6"), ("e14", "f12"), ("n10", "l11")]);
+        play(&mut game, &[("i1", "j2"), ("a6", "b5"), ("f14", "e13"), ("n9", "m10")]);
+        play(&mut game, &[("h1", "j1"), ("a7", "a5"), ("g14", "e14"), ("n8", "n10")]);
+        let kind_at = |s: &str| game.piece(square(s)).map(|p| p.kind);
+        assert_eq!((kind_at("j1"), kind_at("i1")), (Some(PieceKind::King), Some(PieceKind::Rook)));
+        assert_eq!((kind_at("a5"), kind_at("a6")), (Some(PieceKind::King), Some(PieceKind::Rook)));
+        assert_eq!((kind_at("e14"), kind_at("f14")), (Some(PieceKind::King), Some(PieceKind::Rook)));
+        assert_eq!((kind_at("n10"), kind_at("n9")), (Some(PieceKind::King), Some(PieceKind::Rook)));
+    }
+
+    #[test]
+    fn trying_moves_leaves_the_board_as_it_was() {
+        let mut game = FourPlayerMatch::new(FourPlayerMode::FreeForAll);
+        play(&mut game, &[("j2", "j3"), ("b5", "c5"), ("e13", "e12"), ("m10", "l10")]);
+        play(&mut game, &[("j1", "i3"), ("a5", "c6"), ("e14", "f12"), ("n10", "l11")]);
+        play(&mut game, &[("i1", "j2"), ("a6", "b5"), ("f14", "e13"), ("n9", "m10")]);
+        let squares = game.squares.clone();
+        assert!(game.legal_moves(Seat::Red).iter().any(|mv| mv.castling_rook.is_some()));
+        assert_eq!(game.squares, squares);
+
+        // Promotions, one of them with a capture
+        let mut game = with_pieces(
+            FourPlayerMode::FreeForAll,
+            &[
+                ("h1", PieceKind::King, Seat::Red),
+                ("e7", PieceKind::Pawn, Seat::Red),
+                ("a7", PieceKind::King, Seat::Blue),
+                ("d8", PieceKind::Rook, Seat::Blue),
+                ("g14", PieceKind::King, Seat::Yellow),
+                ("n8", PieceKind::King, Seat::Green),
+            ],
+        );
+        let squares = game.squares.clone();
+        assert_eq!(game.legal_moves(Seat::Red).iter().filter(|mv| mv.promotion.is_some()).count(), 2);
+        assert_eq!(game.squares, squares);
+    }
+}
@@ -0,0 +1,31 @@
+use crate::board::piece::Piece;
+use crate::board::position::Position;
+use crate::chess::chess_move::Move;
+use crate::chess::color::Color;
+use crate::network::GameEndReason;
+
+// Game state overwritten by a move, kept so the move can be taken back.
+// Castling rights need no copy: undoing the move restores the move counts they come from.
+#[derive(Clone, Copy)]
+pub(super) struct StateSnapshot {
+    pub turn: u32,
+    pub current_player: Color,
+    pub check: bool,
+    pub check_mate: bool,
+    pub variant_win: Option<(Color, GameEndReason)>,
+    pub checks: [u8; 2],
+    pub stalemate: bool,
+    pub en_passant_vulnerable: Option<Position>,
+    pub halfmove_clock: u32,
+    pub claimed_draw: Option<GameEndReason>,
+}
+
+pub struct MoveRecord {
+    pub mv: Move,
+    // The move in SAN, as written at the time it was played
+    pub san: String,
+    pub(super) captured_piece: Option<Box<dyn Piece + Send + Sync>>,
+    pub(super) previous_state: StateSnapshot,
+    // Whether the position after the move was counted in the repetition table
+    pub(super) counted_position: bool,
+}
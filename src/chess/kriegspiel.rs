@@ -0,0 +1,258 @@
+// Kriegspiel: each player sees only their own pieces and tries moves blindly. An umpire, who
+// sees the whole board, rejects the illegal tries and tells both players what they may know:
+// that a move was made, where a capture happened, the direction of a check and how many pawn
+// captures the player to move could try.
+use crate::board::position::Position;
+use crate::chess::chess_position::ChessPosition;
+use crate::chess::color::Color;
+use crate::chess::piece_kind::PieceKind;
+use crate::chess::ChessMatch;
+use crate::error::ChessError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Line along which the king is attacked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CheckDirection {
+    Rank,
+    File,
+    // The longer of the two diagonals through the king's square
+    LongDiagonal,
+    ShortDiagonal,
+    Knight,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Announcement {
+    Illegal,
+    Moved(Color),
+    // Only the square is announced, not what was captured
+    CaptureOn(String),
+    Check(CheckDirection),
+    // Number of pawn captures the player to move may play
+    PawnTries(Color, usize),
+}
+
+impl fmt::Display for Announcement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Announcement::Illegal => write!(f, "Illegal"),
+            Announcement::Moved(color) => write!(f, "{:?} has moved", color),
+            Announcement::CaptureOn(square) => write!(f, "Capture on {}", square),
+            Announcement::Check(CheckDirection::Rank) => write!(f, "Check on the rank"),
+            Announcement::Check(CheckDirection::File) => write!(f, "Check on the file"),
+            Announcement::Check(CheckDirection::LongDiagonal) => write!(f, "Check on the long diagonal"),
+            Announcement::Check(CheckDirection::ShortDiagonal) => write!(f, "Check on the short diagonal"),
+            Announcement::Check(CheckDirection::Knight) => write!(f, "Check by a knight"),
+            Announcement::PawnTries(color, 1) => write!(f, "{:?} has 1 pawn try", color),
+            Announcement::PawnTries(color, tries) => write!(f, "{:?} has {} pawn tries", color, tries),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attempt {
+    pub source: Position,
+    pub target: Position,
+    pub legal: bool,
+}
+
+// Referees a Kriegspiel game and keeps every move each side tried, legal or not
+#[derive(Debug, Clone, Default)]
+pub struct Umpire {
+    attempts: [Vec<Attempt>; 2],
+}
+
+impl Umpire {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn attempts(&self, color: Color) -> &[Attempt] {
+        &self.attempts[color.index()]
+    }
+
+    // Tries the move for the player to move and returns what the umpire announces. Picking a
+    // square without one of the player's own pieces is a plain input error, which isn't
+    // announced or recorded: saying what stands there would give the enemy pieces away.
+    pub fn attempt(
+        &mut self,
+        chess_match: &mut ChessMatch,
+        source: ChessPosition,
+        target: ChessPosition,
+        promotion: Option<PieceKind>,
+    ) -> Result<Vec<Announcement>, ChessError> {
+        let color = chess_match.current_player;
+        let (rows, cols) = (chess_match.board.rows, chess_match.board.cols);
+        let (source_pos, target_pos) = (source.to_position(rows, cols)?, target.to_position(rows, cols)?);
+        if chess_match.board.piece(source_pos).is_none_or(|piece| piece.color() != color) {
+            return Err(ChessError(format!("You have no piece on {}", source)));
+        }
+
+        let result = chess_match.perform_chess_move(source, target, promotion);
+        self.attempts[color.index()].push(Attempt { source: source_pos, target: target_pos, legal: result.is_ok() });
+        let Ok(mv) = result else {
+            return Ok(vec![Announcement::Illegal]);
+        };
+
+        let mut announcements = vec![Announcement::Moved(color)];
+        if mv.is_capture() {
+            announcements.push(Announcement::CaptureOn(ChessPosition::from_position(mv.target, rows).to_string()));
+        }
+        announcements.extend(chess_match.check_directions(color.opponent()).into_iter().map(Announcement::Check));
+        if chess_match.winner().is_none() && chess_match.draw_reason().is_none() {
+            let tries = chess_match.pawn_tries();
+            if tries > 0 {
+                announcements.push(Announcement::PawnTries(color.opponent(), tries));
+            }
+        }
+        Ok(announcements)
+    }
+}
+
+impl ChessMatch {
+    // Directions of every check on `color`'s king, two of them on a double check
+    pub fn check_directions(&self, color: Color) -> Vec<CheckDirection> {
+        let Some(king) = self.king(color) else {
+            return Vec::new();
+        };
+        let mut directions = Vec::new();
+        for &pos in &self.pieces_on_board {
+            let piece = self.board.piece(pos).unwrap();
+            if piece.color() == color || !piece.attacks(&self.board, pos, self)[king.row][king.col] {
+                continue;
+            }
+            let falling = (pos.row < king.row) == (pos.col < king.col);
+            directions.push(if piece.kind() == PieceKind::Knight {
+                CheckDirection::Knight
+            } else if pos.row == king.row {
+                CheckDirection::Rank
+            } else if pos.col == king.col {
+                CheckDirection::File
+            } else if self.diagonal_length(king, falling) >= self.diagonal_length(king, !falling) {
+                CheckDirection::LongDiagonal
+            } else {
+                CheckDirection::ShortDiagonal
+            });
+        }
+        directions.sort_by_key(|&direction| direction as u8);
+        directions
+    }
+
+    // Number of squares on the diagonal through `pos` that runs from top left to bottom right
+    // (`falling`) or from bottom left to top right
+    fn diagonal_length(&self, pos: Position, falling: bool) -> usize {
+        let (row, col) = (pos.row as isize, pos.col as isize);
+        let (rows, cols) = (self.board.rows as isize, self.board.cols as isize);
+        let (start_row, start_col) = if falling {
+            let back = row.min(col);
+            (row - back, col - back)
+        } else {
+            let back = (rows - 1 - row).min(col);
+            (row + back, col - back)
+        };
+        let length = if falling { (rows - start_row).min(cols - start_col) } else { (start_row + 1).min(cols - start_col) };
+        length as usize
+    }
+
+    // Legal pawn captures of the player to move
+    pub fn pawn_tries(&mut self) -> usize {
+        let mut tries = self
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| mv.piece == PieceKind::Pawn && mv.is_capture())
+            .map(|mv| (mv.source, mv.target))
+            .collect::<Vec<_>>();
+        // A capture that promotes is listed once per promotion choice
+        tries.dedup();
+        tries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::variant::Variant;
+    use crate::network::GameMessage;
+
+    fn attempt(umpire: &mut Umpire, chess_match: &mut ChessMatch, source: &str, target: &str) -> Vec<String> {
+        umpire
+            .attempt(chess_match, source.parse().unwrap(), target.parse().unwrap(), None)
+            .unwrap()
+            .iter()
+            .map(Announcement::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn illegal_tries_are_announced_and_recorded() {
+        let mut chess_match = ChessMatch::for_variant(Variant::Kriegspiel, 0);
+        let mut umpire = Umpire::new();
+        assert_eq!(attempt(&mut umpire, &mut chess_match, "e2", "e5"), ["Illegal"]);
+        assert_eq!(attempt(&mut umpire, &mut chess_match, "e2", "e4"), ["White has moved"]);
+        assert_eq!(attempt(&mut umpire, &mut chess_match, "d7", "d5"), ["Black has moved", "White has 1 pawn try"]);
+        // A pawn can't move diagonally onto an empty square
+        assert_eq!(attempt(&mut umpire, &mut chess_match, "e4", "f5"), ["Illegal"]);
+        assert_eq!(attempt(&mut umpire, &mut chess_match, "e4", "d5"), ["White has moved", "Capture on d5"]);
+
+        let white = umpire.attempts(Color::White);
+        assert_eq!(white.iter().map(|a| a.legal).collect::<Vec<_>>(), [false, true, false, true]);
+        assert_eq!(umpire.attempts(Color::Black).len(), 1);
+        assert_eq!(chess_match.history().len(), 3);
+    }
+
+    #[test]
+    fn players_see_only_their_own_pieces() {
+        let mut chess_match = ChessMatch::for_variant(Variant::Kriegspiel, 0);
+        let mut umpire = Umpire::new();
+        attempt(&mut umpire, &mut chess_match, "e2", "e4");
+        attempt(&mut umpire, &mut chess_match, "d7", "d5");
+        attempt(&mut umpire, &mut chess_match, "e4", "d5");
+        match chess_match.to_game_state_for(Color::White, String::new()) {
+            GameMessage::GameState { board, captured_by_white, captured_by_black, visible, .. } => {
+                assert!(board.iter().flatten().flatten().all(|pv| pv.color == Color::White));
+                assert_eq!(board.iter().flatten().flatten().count(), 16);
+                // White only heard of a capture on d5, not what was taken
+                assert!(captured_by_white.is_empty() && captured_by_black.is_empty());
+                assert!(visible.is_none());
+            }
+            _ => unreachable!(),
+        }
+        match chess_match.to_game_state_for(Color::Black, String::new()) {
+            GameMessage::GameState { board, captured_by_white, .. } => {
+                assert_eq!(board.iter().flatten().flatten().count(), 15);
+                assert_eq!(captured_by_white.len(), 1);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn squares_without_an_own_piece_give_nothing_away() {
+        let mut chess_match = ChessMatch::for_variant(Variant::Kriegspiel, 0);
+        let mut umpire = Umpire::new();
+        let enemy = umpire.attempt(&mut chess_match, "e7".parse().unwrap(), "e5".parse().unwrap(), None).unwrap_err();
+        let empty = umpire.attempt(&mut chess_match, "e4".parse().unwrap(), "e5".parse().unwrap(), None).unwrap_err();
+        assert_eq!(enemy.0, "You have no piece on e7");
+        assert_eq!(empty.0, "You have no piece on e4");
+        assert!(umpire.attempts(Color::White).is_empty());
+    }
+
+    #[test]
+    fn checks_are_announced_by_direction() {
+        let mut umpire = Umpire::new();
+        let mut announce = |fen: &str, source: &str, target: &str| attempt(&mut umpire, &mut ChessMatch::test_fen(Variant::Kriegspiel, fen), source, target);
+        assert_eq!(announce("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1", "a8"), ["White has moved", "Check on the rank"]);
+        assert_eq!(announce("4k3/8/8/8/8/8/8/K6R w - - 0 1", "h1", "e1"), ["White has moved", "Check on the file"]);
+        assert_eq!(announce("4k3/8/8/8/4N3/8/8/K7 w - - 0 1", "e4", "d6"), ["White has moved", "Check by a knight"]);
+        // From e8 the diagonal towards a4 has five squares and the one towards h5 four
+        assert_eq!(announce("4k3/8/8/8/8/8/8/K4B2 w - - 0 1", "f1", "b5"), ["White has moved", "Check on the long diagonal"]);
+        assert_eq!(announce("4k3/8/8/8/8/3B4/8/K7 w - - 0 1", "d3", "g6"), ["White has moved", "Check on the short diagonal"]);
+        // The knight uncovers the rook's check, and checks too on f6
+        assert_eq!(announce("4k3/8/8/8/4N3/8/8/K3R3 w - - 0 1", "e4", "c5"), ["White has moved", "Check on the file"]);
+        assert_eq!(
+            announce("4k3/8/8/8/4N3/8/8/K3R3 w - - 0 1", "e4", "f6"),
+            ["White has moved", "Check on the file", "Check by a knight"]
+        );
+    }
+}
@@ -1,17 +1,36 @@
 // src/chess/mod.rs
+pub mod betza;
+pub mod bughouse;
+pub mod chess_move;
 pub mod chess_position;
 pub mod color;
+pub mod custom_variant;
+pub mod fen;
+pub mod four_player;
+pub mod history;
+pub mod kriegspiel;
+pub mod perft;
+pub mod pgn;
+pub mod piece_kind;
 pub mod pieces;
+pub mod san;
+pub mod variant;
+pub mod zobrist;
 
+use crate::board::bitboard::{self, Bitboards};
 use crate::board::{piece::Piece, position::Position, Board};
 use crate::error::ChessError;
+use betza::CustomPieces;
+use chess_move::Move;
 use chess_position::ChessPosition;
 use color::Color;
-use pieces::{
-    bishop::Bishop, king::King, knight::Knight, pawn::Pawn, queen::Queen, rook::Rook,
-};
-use std::collections::HashSet;
-use crate::network::{GameMessage, PieceView}; // Importar
+use history::{MoveRecord, StateSnapshot};
+use piece_kind::PieceKind;
+use pieces::betza_piece::BetzaPiece;
+use pieces::king;
+use std::collections::{HashMap, HashSet};
+use variant::Variant;
+use crate::network::{GameEndReason, GameMessage, PieceView};
 
 pub struct ChessMatch {
     pub board: Board,
@@ -19,27 +38,151 @@ pub struct ChessMatch {
     current_player: Color,
     pub check: bool,
     pub check_mate: bool,
+    pub stalemate: bool,
     en_passant_vulnerable: Option<Position>,
     pieces_on_board: HashSet<Position>,
     pub captured_pieces: Vec<Box<dyn Piece + Send + Sync>>,
+    // Zobrist hash of the pieces on the board, updated as pieces are placed and removed
+    pieces_hash: u64,
+    // Half-moves since the last capture or pawn move
+    halfmove_clock: u32,
+    // How many times each position (by Zobrist hash) has occurred
+    repetitions: HashMap<u64, u32>,
+    claimed_draw: Option<GameEndReason>,
+    history: Vec<MoveRecord>,
+    // Moves taken back with undo, most recent last
+    redo_stack: Vec<Move>,
+    // Fast backend for attack and move generation queries, only available on 8x8 boards
+    bitboards: Option<Bitboards>,
+    // FEN of the starting position when the game didn't start from the standard one
+    setup_fen: Option<String>,
+    variant: Variant,
+    // Crazyhouse and Bughouse: pieces each side can drop, White's first
+    hands: [Vec<PieceKind>; 2],
+    // Winner and reason of a game won by a variant rule rather than by checkmate, such as
+    // exploding the king in Atomic
+    variant_win: Option<(Color, GameEndReason)>,
+    // Checks given by White and Black so far, which win in Three-check
+    checks: [u8; 2],
+    // Atomic: the pieces blown off the board by each capture still on the board, with their squares
+    explosions: Vec<Vec<(Position, Box<dyn Piece + Send + Sync>)>>,
+    // Fairy pieces of a variant file, which FEN and SAN letters can refer to
+    custom_pieces: CustomPieces,
+}
+
+impl Default for ChessMatch {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ChessMatch {
     pub fn new() -> Self {
-        let mut chess_match = ChessMatch {
-            board: Board::new(8, 8).unwrap(),
+        let mut chess_match = ChessMatch::with_board(Board::new(8, 8).unwrap());
+        chess_match.initial_setup(&STANDARD_BACK_RANK);
+        chess_match.record_position();
+        chess_match
+    }
+
+    // Chess960 game from starting position `index`, 0 to 959 (518 is the standard setup)
+    pub fn chess960(index: u32) -> Result<Self, ChessError> {
+        let back_rank = variant::chess960_back_rank(index)?;
+        let mut chess_match = ChessMatch::with_board(Board::new(8, 8).unwrap());
+        chess_match.initial_setup(&back_rank);
+        chess_match.record_position();
+        chess_match.variant = Variant::Chess960;
+        // Chess960 games always record their starting position
+        chess_match.setup_fen = Some(chess_match.to_fen());
+        Ok(chess_match)
+    }
+
+    // Capablanca chess on a 10x8 board, with the archbishop between the knight and the bishop
+    // on the queen's side and the chancellor between the bishop and the knight on the king's
+    pub fn capablanca() -> Self {
+        let (rows, cols) = Variant::Capablanca.board_size();
+        let mut chess_match = ChessMatch::with_board(Board::new(rows, cols).unwrap());
+        chess_match.initial_setup(&CAPABLANCA_BACK_RANK);
+        chess_match.record_position();
+        chess_match.variant = Variant::Capablanca;
+        chess_match
+    }
+
+    // New game of the variant; `seed` picks the Chess960 starting position
+    pub fn for_variant(variant: Variant, seed: u64) -> Self {
+        match variant {
+            Variant::Standard => ChessMatch::new(),
+            Variant::Chess960 => ChessMatch::chess960(variant::chess960_index_from_seed(seed)).unwrap(),
+            Variant::Capablanca => ChessMatch::capablanca(),
+            Variant::Crazyhouse
+            | Variant::Atomic
+            | Variant::Antichess
+            | Variant::DarkChess
+            | Variant::Kriegspiel
+            | Variant::Bughouse
+            | Variant::ThreeCheck
+            | Variant::KingOfTheHill => {
+                let mut chess_match = ChessMatch::new();
+                chess_match.variant = variant;
+                chess_match
+            }
+        }
+    }
+
+    fn with_board(board: Board) -> Self {
+        let bitboards = if board.rows == 8 && board.cols == 8 { Some(Bitboards::new()) } else { None };
+        ChessMatch {
+            board,
             turn: 1,
             current_player: Color::White,
             check: false,
             check_mate: false,
+            stalemate: false,
             en_passant_vulnerable: None,
             pieces_on_board: HashSet::new(),
             captured_pieces: Vec::new(),
-        };
-        chess_match.initial_setup();
-        chess_match
+            pieces_hash: 0,
+            halfmove_clock: 0,
+            repetitions: HashMap::new(),
+            claimed_draw: None,
+            history: Vec::new(),
+            redo_stack: Vec::new(),
+            bitboards,
+            setup_fen: None,
+            variant: Variant::Standard,
+            hands: [Vec::new(), Vec::new()],
+            variant_win: None,
+            checks: [0, 0],
+            explosions: Vec::new(),
+            custom_pieces: CustomPieces::default(),
+        }
     }
     
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    // Crazyhouse and Bughouse: the pieces `color` has in hand, strongest first
+    pub fn hand(&self, color: Color) -> Vec<PieceKind> {
+        let mut hand = self.hands[color.index()].clone();
+        hand.sort_by_key(|&kind| self.hand_order().position(|k| k == kind));
+        hand
+    }
+
+    // Winner and reason when a variant rule ended the game, see variant_win_after
+    pub fn variant_win(&self) -> Option<(Color, GameEndReason)> {
+        self.variant_win
+    }
+
+    // The winner once the game is won. As after checkmate, the turn isn't passed on after the
+    // winning move, even when the rules make its player lose (Antichess).
+    pub fn winner(&self) -> Option<Color> {
+        if self.check_mate {
+            Some(self.current_player)
+        } else {
+            self.variant_win.map(|(winner, _)| winner)
+        }
+    }
+
     pub fn get_current_player(&self) -> Color {
         self.current_player
     }
@@ -49,14 +192,15 @@ impl ChessMatch {
     }
 
     pub fn to_game_state(&self, message: String) -> GameMessage {
-        let mut board_view = vec![vec![None; 8]; 8];
+        let mut board_view = vec![vec![None; self.board.cols]; self.board.rows];
         
-        for r in 0..8 {
-            for c in 0..8 {
+        for (r, row) in board_view.iter_mut().enumerate() {
+            for (c, square) in row.iter_mut().enumerate() {
                 let pos = Position::new(r, c);
                 if let Some(piece) = self.board.piece(pos) {
-                    board_view[r][c] = Some(PieceView {
+                    *square = Some(PieceView {
                         symbol: piece.to_string(),
+                        kind: piece.kind(),
                         color: piece.color(),
                     });
                 }
@@ -68,7 +212,7 @@ impl ChessMatch {
         let mut captured_by_black: Vec<PieceView> = Vec::new();
 
         for cp in &self.captured_pieces {
-            let pv = PieceView { symbol: cp.to_string(), color: cp.color() };
+            let pv = PieceView { symbol: cp.to_string(), kind: cp.kind(), color: cp.color() };
             // Se a peça capturada é preta, foi capturada pelas brancas
             if pv.color == Color::Black {
                 captured_by_white.push(pv);
@@ -77,15 +221,89 @@ impl ChessMatch {
             }
         }
 
+        let hands = self.variant.has_drops().then(|| {
+            let views = |color: Color| {
+                self.hand(color)
+                    .into_iter()
+                    .map(|kind| PieceView { symbol: self.new_piece(kind, color).to_string(), kind, color })
+                    .collect::<Vec<_>>()
+            };
+            (views(Color::White), views(Color::Black))
+        });
+
         GameMessage::GameState {
             board: board_view,
             turn_color: self.current_player,
             is_check: self.check,
             is_check_mate: self.check_mate,
+            variant_win: self.variant_win,
+            is_draw: self.draw_reason().is_some(),
+            can_claim_draw: self.claimable_draw().is_some(),
             message,
             captured_by_white,
             captured_by_black,
+            hands,
+            visible: None,
+            checks: (self.variant == Variant::ThreeCheck).then_some((self.checks[0], self.checks[1])),
+            clocks: None,
+        }
+    }
+
+    // State as `viewer` is allowed to see it, so a client can't peek at the enemy pieces. In dark
+    // chess the squares outside their sight are sent empty and marked hidden; in Kriegspiel the
+    // board only has their own pieces, and the pieces they captured are left out, as the umpire
+    // doesn't say what was taken.
+    pub fn to_game_state_for(&self, viewer: Color, message: String) -> GameMessage {
+        let mut state = self.to_game_state(message);
+        if self.variant == Variant::Kriegspiel {
+            if let GameMessage::GameState { board, captured_by_white, captured_by_black, .. } = &mut state {
+                for square in board.iter_mut().flatten() {
+                    if square.as_ref().is_some_and(|pv| pv.color != viewer) {
+                        *square = None;
+                    }
+                }
+                if viewer == Color::White { captured_by_white.clear() } else { captured_by_black.clear() }
+            }
+            return state;
+        }
+        if self.variant != Variant::DarkChess {
+            return state;
+        }
+        if let GameMessage::GameState { board, visible, .. } = &mut state {
+            let sight = self.visible_squares(viewer);
+            for (row, seen) in board.iter_mut().zip(&sight) {
+                for (square, &seen) in row.iter_mut().zip(seen) {
+                    if !seen {
+                        *square = None;
+                    }
+                }
+            }
+            *visible = Some(sight);
+        }
+        state
+    }
+
+    // Dark chess: the squares `color`'s pieces stand on or can move to, and the square in front
+    // of each pawn, which shows what is blocking it
+    pub fn visible_squares(&self, color: Color) -> Vec<Vec<bool>> {
+        let mut sight = vec![vec![false; self.board.cols]; self.board.rows];
+        for mv in self.pseudo_legal_moves(color) {
+            sight[mv.target.row][mv.target.col] = true;
         }
+        for &pos in &self.pieces_on_board {
+            let piece = self.board.piece(pos).unwrap();
+            if piece.color() != color {
+                continue;
+            }
+            sight[pos.row][pos.col] = true;
+            if piece.kind() == PieceKind::Pawn {
+                let ahead = if color == Color::White { pos.row.checked_sub(1) } else { Some(pos.row + 1).filter(|&r| r < self.board.rows) };
+                if let Some(row) = ahead {
+                    sight[row][pos.col] = true;
+                }
+            }
+        }
+        sight
     }
 
     
@@ -100,45 +318,399 @@ impl ChessMatch {
         &mut self,
         source: ChessPosition,
         target: ChessPosition,
-    ) -> Result<Option<Box<dyn Piece + Send + Sync>>, ChessError> {
-        let source_pos = source.to_position();
-        let target_pos = target.to_position();
+        promotion: Option<PieceKind>,
+    ) -> Result<Move, ChessError> {
+        let (rows, cols) = (self.board.rows, self.board.cols);
+        let source_pos = source.to_position(rows, cols)?;
+        let target_pos = self.castling_input(source_pos, target.to_position(rows, cols)?);
 
         self.validate_source_position(source_pos)?;
         self.validate_target_position(source_pos, target_pos)?;
+        let promotion = self.validate_promotion(source_pos, target_pos, promotion)?;
+        let mv = self.build_move(source_pos, target_pos, promotion);
+
+        if self.variant == Variant::Atomic && mv.piece == PieceKind::King && mv.is_capture() {
+            return Err(ChessError("In Atomic chess the king can't capture".to_string()));
+        }
+        if self.variant == Variant::Antichess && !mv.is_capture() && self.legal_moves().iter().any(Move::is_capture) {
+            return Err(ChessError("Captures are compulsory in Antichess: you must capture".to_string()));
+        }
+        if !self.is_legal(&mv) {
+            return Err(ChessError("You can't put yourself in check".to_string()));
+        }
 
-        let captured_piece = self.make_move(source_pos, target_pos);
+        self.apply_move(mv);
+        self.redo_stack.clear();
+        Ok(mv)
+    }
 
-        if self.test_check(self.current_player) {
-            self.undo_move(source_pos, target_pos, captured_piece);
+    // Crazyhouse and Bughouse: drops a piece from the hand of the player to move onto an empty square
+    pub fn perform_drop(&mut self, kind: PieceKind, target: ChessPosition) -> Result<Move, ChessError> {
+        if !self.variant.has_drops() {
+            return Err(ChessError("Pieces can only be dropped in Crazyhouse and Bughouse".to_string()));
+        }
+        let color = self.current_player;
+        if !self.hands[color.index()].contains(&kind) {
+            return Err(ChessError(format!("You have no {:?} in hand", kind)));
+        }
+        let target = target.to_position(self.board.rows, self.board.cols)?;
+        if self.board.there_is_a_piece(target) {
+            return Err(ChessError("Pieces can only be dropped on an empty square".to_string()));
+        }
+        if kind == PieceKind::Pawn && (target.row == 0 || target.row == self.board.rows - 1) {
+            return Err(ChessError("Pawns can't be dropped on the first or last rank".to_string()));
+        }
+
+        let mv = Self::build_drop(color, kind, target);
+        if !self.is_legal(&mv) {
             return Err(ChessError("You can't put yourself in check".to_string()));
         }
 
-        let moved_piece_at_target = self.board.piece(target_pos).unwrap();
+        self.apply_move(mv);
+        self.redo_stack.clear();
+        Ok(mv)
+    }
 
-        if moved_piece_at_target.to_string().contains("Pawn") || moved_piece_at_target.to_string().contains('♟') || moved_piece_at_target.to_string().contains('♙') {
-            if (source_pos.row as isize - target_pos.row as isize).abs() == 2 {
-                self.en_passant_vulnerable = Some(target_pos);
-            } else {
-                self.en_passant_vulnerable = None;
-            }
+    // Plays a legal move and updates the game state, recording it in the history
+    fn apply_move(&mut self, mv: Move) {
+        let previous_state = self.snapshot();
+        let mut san = self.san_without_suffix(&mv);
+        let captured_piece = self.make_move(&mv);
+        self.en_passant_vulnerable = Self::double_step(&mv);
+
+        if mv.piece == PieceKind::Pawn || mv.is_capture() {
+            self.halfmove_clock = 0;
         } else {
-            self.en_passant_vulnerable = None;
+            self.halfmove_clock += 1;
         }
 
-        let opponent = self.opponent(self.current_player);
-        self.check = self.test_check(opponent);
+        // Three-check counts the check before the win conditions run, as the third one wins; the
+        // game ends there, so the counter never goes past 3.
+        // A king blown up in Atomic or captured in dark chess isn't in check.
+        let opponent = self.current_player.opponent();
+        let gives_check = self.king(opponent).is_some() && self.test_check(opponent);
+        if gives_check && self.variant == Variant::ThreeCheck {
+            self.checks[self.current_player.index()] += 1;
+        }
+        self.variant_win = self.variant_win_after(self.current_player);
+        self.check = self.variant_win.is_none() && gives_check;
 
-        if self.test_check_mate(opponent) {
+        let counted_position = if self.variant_win.is_some() {
+            false
+        } else if self.test_check_mate(opponent) {
             self.check_mate = true;
+            false
         } else {
+            self.stalemate = self.test_stalemate(opponent);
             self.next_turn();
+            self.record_position();
+            true
+        };
+
+        if self.winner() == Some(self.current_player) {
+            san.push('#');
+        } else if self.check {
+            san.push('+');
+        }
+
+        self.history.push(MoveRecord { mv, san, captured_piece, previous_state, counted_position });
+    }
+
+    // Winner and reason when a move by `mover` ends the game by a variant rule, checked after every
+    // move: this is where a variant plugs in its own win conditions, ahead of checkmate
+    fn variant_win_after(&mut self, mover: Color) -> Option<(Color, GameEndReason)> {
+        let opponent = mover.opponent();
+        match self.variant {
+            Variant::ThreeCheck if self.checks[mover.index()] >= 3 => Some((mover, GameEndReason::ThreeChecks)),
+            Variant::KingOfTheHill if self.king(mover).is_some_and(is_on_the_hill) => Some((mover, GameEndReason::KingOfTheHill)),
+            Variant::Atomic if self.king(opponent).is_none() => Some((mover, GameEndReason::KingExploded)),
+            Variant::Antichess if self.pieces_of(opponent) == 0 => Some((opponent, GameEndReason::NoPiecesLeft)),
+            Variant::Antichess => (!self.has_legal_moves(opponent)).then_some((opponent, GameEndReason::NoLegalMoves)),
+            Variant::DarkChess if self.king(opponent).is_none() => Some((mover, GameEndReason::KingCaptured)),
+            _ => None,
+        }
+    }
+
+    // Position of a pawn that has just advanced two squares, which can be captured en passant
+    fn double_step(mv: &Move) -> Option<Position> {
+        if mv.piece == PieceKind::Pawn && mv.source.row.abs_diff(mv.target.row) == 2 {
+            Some(mv.target)
+        } else {
+            None
+        }
+    }
+
+    // Takes back the last move, restoring the whole game state from before it
+    pub fn undo(&mut self) -> Result<Move, ChessError> {
+        let record = self.history.pop().ok_or_else(|| ChessError("There is no move to undo".to_string()))?;
+        if record.counted_position {
+            let hash = self.zobrist_hash();
+            if let Some(count) = self.repetitions.get_mut(&hash) {
+                *count -= 1;
+                if *count == 0 {
+                    self.repetitions.remove(&hash);
+                }
+            }
+        }
+        self.undo_move(&record.mv, record.captured_piece);
+        self.restore(record.previous_state);
+        self.redo_stack.push(record.mv);
+        Ok(record.mv)
+    }
+
+    // Plays again the last move taken back with undo
+    pub fn redo(&mut self) -> Result<Move, ChessError> {
+        let mv = self.redo_stack.pop().ok_or_else(|| ChessError("There is no move to redo".to_string()))?;
+        self.apply_move(mv);
+        Ok(mv)
+    }
+
+    pub fn history(&self) -> &[MoveRecord] {
+        &self.history
+    }
+
+    fn snapshot(&self) -> StateSnapshot {
+        StateSnapshot {
+            turn: self.turn,
+            current_player: self.current_player,
+            check: self.check,
+            check_mate: self.check_mate,
+            variant_win: self.variant_win,
+            checks: self.checks,
+            stalemate: self.stalemate,
+            en_passant_vulnerable: self.en_passant_vulnerable,
+            halfmove_clock: self.halfmove_clock,
+            claimed_draw: self.claimed_draw,
+        }
+    }
+
+    fn restore(&mut self, state: StateSnapshot) {
+        self.turn = state.turn;
+        self.current_player = state.current_player;
+        self.check = state.check;
+        self.check_mate = state.check_mate;
+        self.variant_win = state.variant_win;
+        self.checks = state.checks;
+        self.stalemate = state.stalemate;
+        self.en_passant_vulnerable = state.en_passant_vulnerable;
+        self.halfmove_clock = state.halfmove_clock;
+        self.claimed_draw = state.claimed_draw;
+    }
+
+    // Moves of the player to move that don't leave their own king in check
+    pub fn legal_moves(&mut self) -> Vec<Move> {
+        if self.winner().is_some() {
+            return Vec::new();
+        }
+        let color = self.current_player;
+        let moves = self
+            .pseudo_legal_moves(color)
+            .into_iter()
+            .filter(|mv| self.is_legal(mv))
+            .collect::<Vec<_>>();
+        // Antichess: when a capture is possible, only captures may be played
+        if self.variant == Variant::Antichess && moves.iter().any(Move::is_capture) {
+            return moves.into_iter().filter(Move::is_capture).collect();
         }
+        moves
+    }
+
+    // Moves allowed by the pieces' movement rules, which may still leave the own king in check.
+    // A move to the last rank is listed once per promotion choice.
+    fn pseudo_legal_moves(&self, color: Color) -> Vec<Move> {
+        let mut sources = self
+            .pieces_on_board
+            .iter()
+            .filter(|&&pos| self.board.piece(pos).unwrap().color() == color)
+            .cloned()
+            .collect::<Vec<_>>();
+        sources.sort_by_key(|pos| (pos.row, pos.col));
 
-        Ok(captured_piece)
+        let mut moves = Vec::new();
+        for source in sources {
+            for target in self.possible_targets(source) {
+                if self.variant == Variant::Atomic && self.is_king_capture(source, target) {
+                    continue;
+                }
+                if self.is_promotion_move(source, target) {
+                    for &kind in self.promotion_choices() {
+                        moves.push(self.build_move(source, target, Some(kind)));
+                    }
+                } else {
+                    moves.push(self.build_move(source, target, None));
+                }
+            }
+        }
+        if self.variant.has_drops() {
+            moves.extend(self.drop_moves(color));
+        }
+        moves
+    }
+
+    // Crazyhouse and Bughouse: every drop of a piece in hand onto an empty square, except pawns on the first or last rank
+    fn drop_moves(&self, color: Color) -> Vec<Move> {
+        let hand = &self.hands[color.index()];
+        let mut moves = Vec::new();
+        for kind in self.hand_order().filter(|kind| hand.contains(kind)) {
+            for row in 0..self.board.rows {
+                if kind == PieceKind::Pawn && (row == 0 || row == self.board.rows - 1) {
+                    continue;
+                }
+                for col in 0..self.board.cols {
+                    let target = Position::new(row, col);
+                    if !self.board.there_is_a_piece(target) {
+                        moves.push(Self::build_drop(color, kind, target));
+                    }
+                }
+            }
+        }
+        moves
+    }
+
+    // Squares the piece on source can move to by its movement rules. The bitboards answer this
+    // for every piece but the king, whose castling rules live in King::possible_moves.
+    fn possible_targets(&self, source: Position) -> Vec<Position> {
+        let piece = self.board.piece(source).unwrap();
+        match &self.bitboards {
+            Some(bitboards) if piece.kind() != PieceKind::King => {
+                let sq = bitboard::square(source);
+                let targets = match piece.kind() {
+                    PieceKind::Pawn => self.pawn_targets(bitboards, sq, piece),
+                    kind => bitboards.attacks_from(kind, piece.color(), sq) & !bitboards.color(piece.color()),
+                };
+                // Same order as the matrix scan below, so both backends list moves identically
+                let mut targets = bitboard::squares(targets).map(bitboard::position).collect::<Vec<_>>();
+                targets.sort_by_key(|pos| (pos.row, pos.col));
+                targets
+            }
+            _ => {
+                let possible_moves = self.calculate_possible_moves(source);
+                let mut targets = Vec::new();
+                for (r, row) in possible_moves.iter().enumerate() {
+                    for (c, &possible) in row.iter().enumerate() {
+                        if possible {
+                            targets.push(Position::new(r, c));
+                        }
+                    }
+                }
+                targets
+            }
+        }
+    }
+
+    // Same rules as Pawn::possible_moves, on bitboards
+    fn pawn_targets(&self, bitboards: &Bitboards, sq: usize, pawn: &(dyn Piece + Send + Sync)) -> u64 {
+        let color = pawn.color();
+        let empty = !bitboards.occupied();
+        let step = |sq: usize| if color == Color::White { sq.checked_add(8).filter(|&s| s < 64) } else { sq.checked_sub(8) };
+
+        let mut targets = 0;
+        if let Some(one) = step(sq).filter(|&one| empty & (1u64 << one) != 0) {
+            targets |= 1u64 << one;
+            if let Some(two) = step(one).filter(|&two| pawn.move_count() == 0 && empty & (1u64 << two) != 0) {
+                targets |= 1u64 << two;
+            }
+        }
+
+        let attacks = bitboard::pawn_attacks(color, sq);
+        targets |= attacks & bitboards.color(color.opponent());
+        // En passant: the capture lands on the square the vulnerable pawn skipped
+        if let Some(vulnerable) = self.en_passant_vulnerable.filter(|&vp| bitboards.color(color.opponent()) & (1u64 << bitboard::square(vp)) != 0) {
+            let row = if color == Color::White { vulnerable.row.checked_sub(1) } else { Some(vulnerable.row + 1) };
+            if let Some(row) = row.filter(|&row| row < 8) {
+                targets |= attacks & (1u64 << bitboard::square(Position::new(row, vulnerable.col)));
+            }
+        }
+        targets
+    }
+
+    // Atomic: kings can't capture, since they would explode along with the captured piece
+    fn is_king_capture(&self, source: Position, target: Position) -> bool {
+        match (self.board.piece(source), self.board.piece(target)) {
+            (Some(king), Some(target)) => king.kind() == PieceKind::King && target.color() != king.color(),
+            _ => false,
+        }
+    }
+
+    fn is_legal(&mut self, mv: &Move) -> bool {
+        let color = mv.color;
+        let captured = self.make_move(mv);
+        let in_check = self.test_check(color);
+        self.undo_move(mv, captured);
+        !in_check
+    }
+
+    // Describes moving the piece on source to target, which must be one of its possible moves
+    fn build_move(&self, source: Position, target: Position, promotion: Option<PieceKind>) -> Move {
+        let piece = self.board.piece(source).unwrap().kind();
+        let color = self.board.piece(source).unwrap().color();
+        let is_en_passant = piece == PieceKind::Pawn && source.col != target.col && !self.board.there_is_a_piece(target);
+        let castling_rook = if piece != PieceKind::King {
+            None
+        } else if self.board.piece(target).is_some_and(|p| p.color() == color) {
+            // Only a castling king moves onto its own rook
+            Some(target)
+        } else if target.col.abs_diff(source.col) >= 2 {
+            king::castling_rook(&self.board, source, target.col > source.col)
+        } else {
+            None
+        };
+        let captured = if is_en_passant {
+            Some(PieceKind::Pawn)
+        } else if castling_rook.is_some() {
+            None
+        } else {
+            self.board.piece(target).map(|p| p.kind())
+        };
+        Move {
+            source,
+            target,
+            piece,
+            color,
+            captured,
+            promotion,
+            castling_rook,
+            is_en_passant,
+            is_drop: false,
+        }
+    }
+
+    fn build_drop(color: Color, kind: PieceKind, target: Position) -> Move {
+        Move {
+            source: target,
+            target,
+            piece: kind,
+            color,
+            captured: None,
+            promotion: None,
+            castling_rook: None,
+            is_en_passant: false,
+            is_drop: true,
+        }
     }
     
+    // Castling may also be entered as the king moving onto its own rook, which is how Chess960
+    // interfaces tell it apart from a king step. Turns that into the move's usual target.
+    fn castling_input(&self, source: Position, target: Position) -> Position {
+        let is_own_rook = match (self.board.piece(source), self.board.piece(target)) {
+            (Some(king), Some(rook)) => {
+                king.kind() == PieceKind::King && rook.kind() == PieceKind::Rook && king.color() == rook.color()
+            }
+            _ => false,
+        };
+        if is_own_rook && king::castling_rook(&self.board, source, target.col > source.col) == Some(target) {
+            king::castling_target(self.board.cols, source, target)
+        } else {
+            target
+        }
+    }
+
     fn validate_source_position(&self, pos: Position) -> Result<(), ChessError> {
+        // When the enemy pieces are hidden, an empty square and an enemy piece get the same
+        // answer, so the error can't tell what stands on a square out of sight
+        if self.variant.hides_pieces() && self.board.piece(pos).is_none_or(|piece| piece.color() != self.current_player) {
+            return Err(ChessError(format!("You have no piece on {}", ChessPosition::from_position(pos, self.board.rows))));
+        }
         if let Some(piece) = self.board.piece(pos) {
             if self.current_player != piece.color() {
                 return Err(ChessError("The chosen piece is not yours".to_string()));
@@ -159,58 +731,116 @@ impl ChessMatch {
         Ok(())
     }
 
-    fn make_move(&mut self, source: Position, target: Position) -> Option<Box<dyn Piece + Send + Sync>> {
-        let mut piece = self.board.remove_piece(source).unwrap();
-        piece.increase_move_count();
-        self.pieces_on_board.remove(&source);
+    // A pawn reaching the last rank without an explicit choice is promoted to a queen.
+    fn validate_promotion(&self, source: Position, target: Position, promotion: Option<PieceKind>) -> Result<Option<PieceKind>, ChessError> {
+        if self.is_promotion_move(source, target) {
+            match promotion {
+                Some(kind) if !self.promotion_choices().contains(&kind) => {
+                    let choices = match self.variant {
+                        Variant::Antichess => "a queen, rook, bishop, knight or king",
+                        Variant::Capablanca => "a queen, chancellor, archbishop, rook, bishop or knight",
+                        _ => "a queen, rook, bishop or knight",
+                    };
+                    Err(ChessError(format!("A pawn can only be promoted to {}", choices)))
+                }
+                _ => Ok(Some(promotion.unwrap_or(PieceKind::Queen))),
+            }
+        } else if promotion.is_some() {
+            Err(ChessError("Only a pawn reaching the last rank can be promoted".to_string()))
+        } else {
+            Ok(None)
+        }
+    }
 
-        let mut captured_piece = self.board.remove_piece(target);
-        if captured_piece.is_some() {
-            self.pieces_on_board.remove(&target);
+    // Pieces a pawn may promote to, strongest first; in Antichess also the king, and in
+    // Capablanca chess also the chancellor and the archbishop
+    fn promotion_choices(&self) -> &'static [PieceKind] {
+        const CHOICES: [PieceKind; 5] = [PieceKind::Queen, PieceKind::Rook, PieceKind::Bishop, PieceKind::Knight, PieceKind::King];
+        const CAPABLANCA_CHOICES: [PieceKind; 6] = [
+            PieceKind::Queen,
+            PieceKind::Chancellor,
+            PieceKind::Archbishop,
+            PieceKind::Rook,
+            PieceKind::Bishop,
+            PieceKind::Knight,
+        ];
+        match self.variant {
+            Variant::Antichess => &CHOICES,
+            Variant::Capablanca => &CAPABLANCA_CHOICES,
+            _ => &CHOICES[..4],
         }
+    }
 
-        let piece_display = piece.to_string(); 
-        
-        if piece_display.contains('♟') || piece_display.contains('♙') {
-            if source.col != target.col && captured_piece.is_none() {
-                 let captured_pos = if piece.color() == Color::White {
-                    Position::new(target.row + 1, target.col)
-                } else {
-                    Position::new(target.row - 1, target.col)
-                };
-                captured_piece = self.board.remove_piece(captured_pos);
-                self.pieces_on_board.remove(&captured_pos);
+    fn is_promotion_move(&self, source: Position, target: Position) -> bool {
+        match self.board.piece(source) {
+            Some(piece) => {
+                piece.kind() == PieceKind::Pawn && (target.row == 0 || target.row == self.board.rows - 1)
             }
+            None => false,
         }
+    }
 
-        self.board.place_piece(piece, target).unwrap();
-        self.pieces_on_board.insert(target);
-        
-        if let Some(moved_piece) = self.board.piece(target) {
-             let moved_piece_display = moved_piece.to_string();
-             if moved_piece_display.contains('♔') || moved_piece_display.contains('♚') {
-                if (target.col as isize - source.col as isize).abs() == 2 {
-                    if target.col > source.col {
-                        let rook_source = Position::new(source.row, source.col + 3);
-                        let rook_target = Position::new(source.row, source.col + 1);
-                        let mut rook = self.board.remove_piece(rook_source).unwrap();
-                        rook.increase_move_count();
-                        self.board.place_piece(rook, rook_target).unwrap();
-                        self.pieces_on_board.remove(&rook_source);
-                        self.pieces_on_board.insert(rook_target);
-                    } else {
-                        let rook_source = Position::new(source.row, source.col - 4);
-                        let rook_target = Position::new(source.row, source.col - 1);
-                        let mut rook = self.board.remove_piece(rook_source).unwrap();
-                        rook.increase_move_count();
-                        self.board.place_piece(rook, rook_target).unwrap();
-                        self.pieces_on_board.remove(&rook_source);
-                        self.pieces_on_board.insert(rook_target);
-                    }
+    fn make_move(&mut self, mv: &Move) -> Option<Box<dyn Piece + Send + Sync>> {
+        if let Some(rook_source) = mv.castling_rook {
+            let (king_target, rook_target) = king::castling_destinations(self.board.cols, mv.source.row, mv.is_kingside_castling());
+            // Both leave the board first: in Chess960 each may land where the other stood
+            let mut king = self.take_piece(mv.source).unwrap();
+            let mut rook = self.take_piece(rook_source).unwrap();
+            king.increase_move_count();
+            rook.increase_move_count();
+            self.put_piece(king, king_target);
+            self.put_piece(rook, rook_target);
+            return None;
+        }
+
+        if mv.is_drop {
+            let hand = &mut self.hands[mv.color.index()];
+            hand.remove(hand.iter().position(|&kind| kind == mv.piece).unwrap());
+            let mut piece = self.new_piece(mv.piece, mv.color);
+            // Only a pawn dropped on its start rank keeps its double step; a dropped rook can't castle
+            if mv.piece != PieceKind::Pawn || mv.target.row != self.pawn_start_row(mv.color) {
+                piece.increase_move_count();
+            }
+            self.put_piece(piece, mv.target);
+            return None;
+        }
+
+        let mut piece = self.take_piece(mv.source).unwrap();
+        piece.increase_move_count();
+
+        let captured_piece = self.take_piece(mv.capture_square());
+        if let Some(cp) = captured_piece.as_ref().filter(|_| self.variant == Variant::Atomic) {
+            // The capturing piece never lands: it explodes together with every piece but the
+            // pawns around the target square
+            self.captured_pieces.push(cp.box_clone());
+            piece.decrease_move_count();
+            let mut blast = vec![(mv.source, piece)];
+            for pos in self.neighbours(mv.target) {
+                if self.board.piece(pos).is_some_and(|p| p.kind() != PieceKind::Pawn) {
+                    blast.push((pos, self.take_piece(pos).unwrap()));
                 }
-             }
+            }
+            self.explosions.push(blast);
+            return captured_piece;
+        }
+        if let Some(cp) = captured_piece.as_ref().filter(|_| self.variant == Variant::Crazyhouse) {
+            // A promoted piece goes back to being a pawn in the capturer's hand. In Bughouse it
+            // goes to the partner instead, which the server takes care of.
+            let kind = if cp.is_promoted() { PieceKind::Pawn } else { cp.kind() };
+            self.hands[mv.color.index()].push(kind);
         }
 
+        let piece = match mv.promotion {
+            Some(promotion) => {
+                let mut promoted = self.new_piece(promotion, piece.color());
+                promoted.increase_move_count();
+                promoted.set_promoted();
+                promoted
+            }
+            None => piece,
+        };
+        self.put_piece(piece, mv.target);
+
         if let Some(cp) = &captured_piece {
             self.captured_pieces.push(cp.box_clone());
         }
@@ -218,178 +848,1099 @@ impl ChessMatch {
         captured_piece
     }
 
-    fn undo_move(
-        &mut self,
-        source: Position,
-        target: Position,
-        captured_piece: Option<Box<dyn Piece + Send + Sync>>,
-    ) {
-        // 1. Move a peça principal de volta (Target -> Source)
-        let mut piece = self.board.remove_piece(target).unwrap();
-        piece.decrease_move_count();
-        self.board.place_piece(piece, source).unwrap();
-        
-        self.pieces_on_board.remove(&target);
-        self.pieces_on_board.insert(source);
+    fn undo_move(&mut self, mv: &Move, captured_piece: Option<Box<dyn Piece + Send + Sync>>) {
+        if let Some(rook_source) = mv.castling_rook {
+            let (king_target, rook_target) = king::castling_destinations(self.board.cols, mv.source.row, mv.is_kingside_castling());
+            let mut king = self.take_piece(king_target).unwrap();
+            let mut rook = self.take_piece(rook_target).unwrap();
+            king.decrease_move_count();
+            rook.decrease_move_count();
+            self.put_piece(king, mv.source);
+            self.put_piece(rook, rook_source);
+            return;
+        }
 
-        if let Some(cp) = captured_piece {
-            let color = cp.color();
-            let cp_display = cp.to_string();
-            let is_pawn = cp_display.contains('♟') || cp_display.contains('♙');
-            
-            let mut is_en_passant_capture = false;
-            if let Some(p) = self.board.piece(source) {
-                let p_display = p.to_string();
-                if (p_display.contains('♟') || p_display.contains('♙')) && target.col != source.col {
-                    is_en_passant_capture = true;
+        if mv.is_drop {
+            self.take_piece(mv.target);
+            self.hands[mv.color.index()].push(mv.piece);
+            return;
+        }
+
+        let captured_piece = match captured_piece {
+            Some(cp) if self.variant == Variant::Atomic => {
+                for (pos, piece) in self.explosions.pop().unwrap() {
+                    self.put_piece(piece, pos);
                 }
+                self.put_piece(cp, mv.capture_square());
+                self.captured_pieces.pop();
+                return;
             }
-            
-            let place_pos = if is_pawn && is_en_passant_capture {
-                if color == Color::White {
-                    Position::new(target.row + 1, target.col)
-                } else {
-                    Position::new(target.row - 1, target.col)
-                }
-            } else {
-                target
-            };
+            captured_piece => captured_piece,
+        };
 
-            self.board.place_piece(cp, place_pos).unwrap();
+        // 1. Move a peça principal de volta (Target -> Source)
+        let mut piece = self.take_piece(mv.target).unwrap();
+        if mv.promotion.is_some() {
+            // The promoted piece turns back into the pawn, which had already moved before reaching the last rank
+            let mut pawn = PieceKind::Pawn.to_piece(piece.color());
+            pawn.increase_move_count();
+            piece = pawn;
+        } else {
+            piece.decrease_move_count();
+        }
+        self.put_piece(piece, mv.source);
+
+        if let Some(cp) = captured_piece {
+            if self.variant == Variant::Crazyhouse {
+                let kind = if cp.is_promoted() { PieceKind::Pawn } else { cp.kind() };
+                let hand = &mut self.hands[mv.color.index()];
+                hand.remove(hand.iter().rposition(|&k| k == kind).unwrap());
+            }
+            self.put_piece(cp, mv.capture_square());
             self.captured_pieces.pop();
-            self.pieces_on_board.insert(place_pos);
-        }
-
-        if let Some(moved_piece) = self.board.piece(source) {
-            let moved_piece_display = moved_piece.to_string();
-            if moved_piece_display.contains('♔') || moved_piece_display.contains('♚') {
-                if (target.col as isize - source.col as isize).abs() == 2 {
-                    if target.col > source.col {
-                        // Roque Pequeno (lado do Rei)
-                        let rook_source = Position::new(source.row, source.col + 1);
-                        let rook_target = Position::new(source.row, source.col + 3);
-                        
-                        let mut rook = self.board.remove_piece(rook_source).unwrap();
-                        rook.decrease_move_count();
-                        self.board.place_piece(rook, rook_target).unwrap();
-
-                        self.pieces_on_board.remove(&rook_source);
-                        self.pieces_on_board.insert(rook_target);
-                    } else {
-                        let rook_source = Position::new(source.row, source.col - 1);
-                        let rook_target = Position::new(source.row, source.col - 4);
-                        
-                        let mut rook = self.board.remove_piece(rook_source).unwrap();
-                        rook.decrease_move_count();
-                        self.board.place_piece(rook, rook_target).unwrap();
-                        self.pieces_on_board.remove(&rook_source);
-                        self.pieces_on_board.insert(rook_target);
-                    }
-                }
+        }
+    }
+
+    // Custom kinds only come from FEN letters, SAN letters and pieces already in play, all of
+    // which are looked up in the game's own table
+    fn new_piece(&self, kind: PieceKind, color: Color) -> Box<dyn Piece + Send + Sync> {
+        match kind {
+            PieceKind::Custom(letter) => {
+                let definition = self.custom_pieces.get(letter).expect("custom pieces in play are in the game's table");
+                Box::new(BetzaPiece::new(definition.clone(), color))
             }
+            kind => kind.to_piece(color),
+        }
+    }
+
+    // All board changes go through put_piece/take_piece so the position hash stays in sync
+    fn put_piece(&mut self, piece: Box<dyn Piece + Send + Sync>, pos: Position) {
+        self.pieces_hash ^= zobrist::piece_key(piece.kind(), piece.color(), self.square_index(pos));
+        // The bitboards don't know how custom pieces move, so the piece move matrices take over for good
+        if matches!(piece.kind(), PieceKind::Custom(_)) {
+            self.bitboards = None;
+        }
+        if let Some(bitboards) = &mut self.bitboards {
+            bitboards.toggle(piece.kind(), piece.color(), bitboard::square(pos));
         }
-    } 
+        self.board.place_piece(piece, pos).unwrap();
+        self.pieces_on_board.insert(pos);
+    }
+
+    fn take_piece(&mut self, pos: Position) -> Option<Box<dyn Piece + Send + Sync>> {
+        let piece = self.board.remove_piece(pos)?;
+        self.pieces_hash ^= zobrist::piece_key(piece.kind(), piece.color(), self.square_index(pos));
+        if let Some(bitboards) = &mut self.bitboards {
+            bitboards.toggle(piece.kind(), piece.color(), bitboard::square(pos));
+        }
+        self.pieces_on_board.remove(&pos);
+        Some(piece)
+    }
+
+    fn square_index(&self, pos: Position) -> usize {
+        pos.row * self.board.cols + pos.col
+    }
+
     fn test_check(&self, color: Color) -> bool {
+        // The Antichess and dark chess king can be left attacked and captured
+        if !self.variant.has_check() {
+            return false;
+        }
         let king_pos = self.king(color);
         if king_pos.is_none() { return true; }
         let king_pos = king_pos.unwrap();
-        let opponent = self.opponent(color);
-        let opponent_pieces = self
-            .pieces_on_board
-            .iter()
-            .filter_map(|pos_ref| self.board.piece(*pos_ref).map(|piece| (pos_ref, piece.color())))
-            .filter(|&(_, color)| color == opponent)
-            .map(|(pos_ref, _)| pos_ref)
-            .cloned()
-            .collect::<Vec<_>>();
-
-        for pos in opponent_pieces {
-            let moves = self.calculate_possible_moves(pos);
-            if moves[king_pos.row][king_pos.col] {
-                return true;
+        if self.variant == Variant::Atomic {
+            // Exploding the enemy king wins even out of check, and a king next to the enemy
+            // king can't be taken, as the capture would blow up the capturer's own king
+            match self.king(color.opponent()) {
+                None => return false,
+                Some(enemy_king) if self.neighbours(king_pos).contains(&enemy_king) => return false,
+                _ => {}
             }
         }
-        false
+        self.is_square_attacked(king_pos, color.opponent())
+    }
+
+    pub fn is_square_attacked(&self, position: Position, by: Color) -> bool {
+        if let Some(bitboards) = &self.bitboards {
+            return bitboards.is_attacked(bitboard::square(position), by);
+        }
+        self.pieces_on_board.iter().any(|&pos| {
+            let piece = self.board.piece(pos).unwrap();
+            piece.color() == by && piece.attacks(&self.board, pos, self)[position.row][position.col]
+        })
     }
 
     fn test_check_mate(&mut self, color: Color) -> bool {
-        if !self.test_check(color) { return false; }
-        let player_pieces = self
+        self.test_check(color) && !self.has_legal_moves(color)
+    }
+
+    fn test_stalemate(&mut self, color: Color) -> bool {
+        !self.test_check(color) && !self.has_legal_moves(color)
+    }
+
+    fn has_legal_moves(&mut self, color: Color) -> bool {
+        self.pseudo_legal_moves(color).iter().any(|mv| self.is_legal(mv))
+    }
+
+    // Draws that end the game on their own: stalemate, a claimed draw, a dead position,
+    // fivefold repetition and the 75-move rule
+    pub fn draw_reason(&self) -> Option<GameEndReason> {
+        if self.stalemate {
+            Some(GameEndReason::Stalemate)
+        } else if self.claimed_draw.is_some() {
+            self.claimed_draw
+        } else if self.insufficient_material() {
+            Some(GameEndReason::InsufficientMaterial)
+        } else if self.repetition_count() >= 5 {
+            Some(GameEndReason::FivefoldRepetition)
+        } else if self.halfmove_clock >= 150 {
+            Some(GameEndReason::SeventyFiveMoveRule)
+        } else {
+            None
+        }
+    }
+
+    // Draws the player to move may claim: threefold repetition and the 50-move rule
+    pub fn claimable_draw(&self) -> Option<GameEndReason> {
+        if self.repetition_count() >= 3 {
+            Some(GameEndReason::ThreefoldRepetition)
+        } else if self.halfmove_clock >= 100 {
+            Some(GameEndReason::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    pub fn claim_draw(&mut self) -> Result<GameEndReason, ChessError> {
+        let reason = self.claimable_draw().ok_or_else(|| {
+            ChessError("No draw to claim: the position has not occurred three times and the last 50 moves had captures or pawn moves".to_string())
+        })?;
+        self.claimed_draw = Some(reason);
+        Ok(reason)
+    }
+
+    // Dead position: neither side can ever checkmate. That is K v K, K+minor v K, or
+    // only bishops left besides the kings, all of them on squares of the same color.
+    pub fn insufficient_material(&self) -> bool {
+        // Captured pieces come back in Crazyhouse and Bughouse, so material never runs out
+        if self.variant.has_drops() {
+            return false;
+        }
+        if self.variant == Variant::Antichess {
+            return self.bishops_never_meet();
+        }
+        // A lone king can still walk to the center, and a single minor piece can still give checks
+        if self.variant == Variant::KingOfTheHill {
+            return false;
+        }
+        if self.variant == Variant::ThreeCheck {
+            return self.pieces_on_board.len() == 2;
+        }
+        let mut knights = 0;
+        let mut bishops = 0;
+        let mut bishop_square_colors = HashSet::new();
+        for &pos in &self.pieces_on_board {
+            match self.board.piece(pos).unwrap().kind() {
+                PieceKind::King => {}
+                PieceKind::Knight => knights += 1,
+                PieceKind::Bishop => {
+                    bishops += 1;
+                    bishop_square_colors.insert((pos.row + pos.col) % 2);
+                }
+                _ => return false,
+            }
+        }
+        knights + bishops <= 1 || (knights == 0 && bishop_square_colors.len() == 1)
+    }
+
+    // Antichess dead position: only bishops are left, each side's all on squares of one color and
+    // the two sides on different colors, so that no capture can ever happen again
+    fn bishops_never_meet(&self) -> bool {
+        let mut square_colors = [HashSet::new(), HashSet::new()];
+        for &pos in &self.pieces_on_board {
+            let piece = self.board.piece(pos).unwrap();
+            if piece.kind() != PieceKind::Bishop {
+                return false;
+            }
+            square_colors[piece.color().index()].insert((pos.row + pos.col) % 2);
+        }
+        let [white, black] = &square_colors;
+        white.len() == 1 && black.len() == 1 && white != black
+    }
+
+    // Result when `color` runs out of time: the opponent wins, unless they only have their king left
+    pub fn timeout_result(&self, color: Color) -> (Option<Color>, GameEndReason) {
+        let opponent = color.opponent();
+        let opponent_has_bare_king = self
             .pieces_on_board
             .iter()
-            .filter(|&&pos| self.board.piece(pos).unwrap().color() == color)
-            .cloned()
-            .collect::<Vec<_>>();
+            .map(|&pos| self.board.piece(pos).unwrap())
+            .all(|piece| piece.color() != opponent || piece.kind() == PieceKind::King)
+            && self.hands[opponent.index()].is_empty()
+            // Having nothing left is how Antichess is won
+            && self.variant != Variant::Antichess;
+        if opponent_has_bare_king {
+            (None, GameEndReason::TimeoutVsInsufficientMaterial)
+        } else {
+            (Some(opponent), GameEndReason::Timeout)
+        }
+    }
 
-        for source_pos in player_pieces {
-            let moves = self.calculate_possible_moves(source_pos);
-            for r in 0..self.board.rows {
-                for c in 0..self.board.cols {
-                    if moves[r][c] {
-                        let target_pos = Position::new(r, c);
-                        let captured = self.make_move(source_pos, target_pos);
-                        let still_in_check = self.test_check(color);
-                        self.undo_move(source_pos, target_pos, captured);
-                        if !still_in_check {
-                            return false;
-                        }
-                    }
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    // Number of times the current position has occurred, including now
+    pub fn repetition_count(&self) -> u32 {
+        self.repetitions.get(&self.zobrist_hash()).copied().unwrap_or(0)
+    }
+
+    fn record_position(&mut self) {
+        *self.repetitions.entry(self.zobrist_hash()).or_insert(0) += 1;
+    }
+
+    // Zobrist hash of the whole position: pieces, side to move, castling rights and en passant
+    pub fn zobrist_hash(&self) -> u64 {
+        let mut hash = self.pieces_hash;
+        if self.current_player == Color::Black {
+            hash ^= zobrist::side_to_move_key();
+        }
+        for color in [Color::White, Color::Black] {
+            let (kingside, queenside) = self.castling_rights(color);
+            if kingside {
+                hash ^= zobrist::castling_key(color, true);
+            }
+            if queenside {
+                hash ^= zobrist::castling_key(color, false);
+            }
+        }
+        if let Some(file) = self.en_passant_file() {
+            hash ^= zobrist::en_passant_key(file);
+        }
+        for color in [Color::White, Color::Black] {
+            for kind in self.hand_order() {
+                let count = self.hands[color.index()].iter().filter(|&&k| k == kind).count();
+                for nth in 0..count {
+                    hash ^= zobrist::hand_key(kind, color, nth);
                 }
             }
         }
-        true
+        hash
+    }
+
+    // (kingside, queenside): the king and a rook on that side of it have never moved
+    fn castling_rights(&self, color: Color) -> (bool, bool) {
+        (self.castling_rook(color, true).is_some(), self.castling_rook(color, false).is_some())
+    }
+
+    fn castling_rook(&self, color: Color, kingside: bool) -> Option<Position> {
+        if !self.variant.has_castling() {
+            return None;
+        }
+        king::castling_rook(&self.board, self.king(color)?, kingside)
+    }
+
+    // File of the en passant target, only if a pawn of the side to move can actually capture there
+    fn en_passant_file(&self) -> Option<usize> {
+        let pos = self.en_passant_vulnerable?;
+        let can_capture = [pos.col.checked_sub(1), Some(pos.col + 1)].into_iter().flatten().any(|col| {
+            col < self.board.cols
+                && self.board.piece(Position::new(pos.row, col)).is_some_and(|p| {
+                    p.kind() == PieceKind::Pawn && p.color() == self.current_player
+                })
+        });
+        if can_capture { Some(pos.col) } else { None }
     }
     
     fn king(&self, color: Color) -> Option<Position> {
+        if let Some(bitboards) = &self.bitboards {
+            return bitboard::squares(bitboards.pieces(PieceKind::King, color)).next().map(bitboard::position);
+        }
          self.pieces_on_board
             .iter()
             .find(|&&pos| {
                 let piece = self.board.piece(pos).unwrap();
-                let piece_display = piece.to_string();
-                (piece_display.contains('♔') || piece_display.contains('♚')) && piece.color() == color
+                piece.kind() == PieceKind::King && piece.color() == color
             })
             .cloned()
     }
 
-    fn opponent(&self, color: Color) -> Color {
-        if color == Color::White { Color::Black } else { Color::White }
+    // Number of pieces `color` has on the board
+    fn pieces_of(&self, color: Color) -> usize {
+        self.pieces_on_board.iter().filter(|&&pos| self.board.piece(pos).unwrap().color() == color).count()
+    }
+
+    // Squares around pos, on the board
+    fn neighbours(&self, pos: Position) -> Vec<Position> {
+        let mut squares = Vec::new();
+        for row in pos.row.saturating_sub(1)..=(pos.row + 1).min(self.board.rows - 1) {
+            for col in pos.col.saturating_sub(1)..=(pos.col + 1).min(self.board.cols - 1) {
+                if (row, col) != (pos.row, pos.col) {
+                    squares.push(Position::new(row, col));
+                }
+            }
+        }
+        squares
+    }
+
+    // Order pieces in hand are listed and dropped in: the built-in pieces, then the game's custom ones
+    fn hand_order(&self) -> impl Iterator<Item = PieceKind> + '_ {
+        HAND_ORDER.into_iter().chain(self.custom_pieces.iter().map(|piece| PieceKind::Custom(piece.letter)))
+    }
+
+    fn pawn_start_row(&self, color: Color) -> usize {
+        if color == Color::White { self.board.rows - 2 } else { 1 }
     }
 
     fn next_turn(&mut self) {
         self.turn += 1;
-        self.current_player = self.opponent(self.current_player);
+        self.current_player = self.current_player.opponent();
     }
     
+    // Pawns on the second rank and the back rank pieces, given from the a-file, behind them
+    fn initial_setup(&mut self, back_rank: &[PieceKind]) {
+        let rows = self.board.rows;
+        for (color, back_row, pawn_row) in [(Color::White, rows - 1, rows - 2), (Color::Black, 0, 1)] {
+            for (col, &kind) in back_rank.iter().enumerate() {
+                self.put_piece(kind.to_piece(color), Position::new(back_row, col));
+                self.put_piece(PieceKind::Pawn.to_piece(color), Position::new(pawn_row, col));
+            }
+        }
+    }
+}
+
+// Order the built-in pieces in hand are listed and dropped in
+const HAND_ORDER: [PieceKind; 5] = [PieceKind::Queen, PieceKind::Rook, PieceKind::Bishop, PieceKind::Knight, PieceKind::Pawn];
+
+// King of the Hill: the center squares d4, d5, e4 and e5
+fn is_on_the_hill(pos: Position) -> bool {
+    (3..=4).contains(&pos.row) && (3..=4).contains(&pos.col)
+}
+
+const STANDARD_BACK_RANK: [PieceKind; 8] = [
+    PieceKind::Rook,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Queen,
+    PieceKind::King,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+    PieceKind::Rook,
+];
+
+const CAPABLANCA_BACK_RANK: [PieceKind; 10] = [
+    PieceKind::Rook,
+    PieceKind::Knight,
+    PieceKind::Archbishop,
+    PieceKind::Bishop,
+    PieceKind::Queen,
+    PieceKind::King,
+    PieceKind::Bishop,
+    PieceKind::Chancellor,
+    PieceKind::Knight,
+    PieceKind::Rook,
+];
+
+#[cfg(test)]
+impl ChessMatch {
+    // Empty 8x8 match holding only the given pieces, with White to move
+    fn with_pieces(pieces: &[(&str, PieceKind, Color)]) -> Self {
+        let mut chess_match = ChessMatch::with_board(Board::new(8, 8).unwrap());
+        for &(square, kind, color) in pieces {
+            chess_match.place_new_piece(square.parse().unwrap(), kind.to_piece(color));
+        }
+        chess_match
+    }
+
+    // Position of `variant` from a FEN the test knows to be valid
+    fn test_fen(variant: Variant, fen: &str) -> Self {
+        Self::from_variant_fen(variant, fen).unwrap()
+    }
+
+    // Plays each (source, target) move, all of which must be legal
+    fn play(&mut self, moves: &[(&str, &str)]) {
+        play_moves(moves, |source, target| self.perform_chess_move(source, target, None));
+    }
+
     fn place_new_piece(&mut self, pos: ChessPosition, piece: Box<dyn Piece + Send + Sync>) {
-        let board_pos = pos.to_position();
-        self.board.place_piece(piece, board_pos).unwrap();
-        self.pieces_on_board.insert(board_pos);
-    }
-
-    fn initial_setup(&mut self) {
-        self.place_new_piece(ChessPosition::new('a', 1).unwrap(),Box::new(Rook::new(Color::White)));
-        self.place_new_piece(ChessPosition::new('b', 1).unwrap(),Box::new(Knight::new(Color::White)));
-        self.place_new_piece(ChessPosition::new('c', 1).unwrap(),Box::new(Bishop::new(Color::White)));
-        self.place_new_piece(ChessPosition::new('d', 1).unwrap(),Box::new(Queen::new(Color::White)));
-        self.place_new_piece(ChessPosition::new('e', 1).unwrap(),Box::new(King::new(Color::White)));
-        self.place_new_piece(ChessPosition::new('f', 1).unwrap(),Box::new(Bishop::new(Color::White)));
-        self.place_new_piece(ChessPosition::new('g', 1).unwrap(),Box::new(Knight::new(Color::White)));
-        self.place_new_piece(ChessPosition::new('h', 1).unwrap(),Box::new(Rook::new(Color::White)));
-        for col in 'a'..='h' {
-            self.place_new_piece(ChessPosition::new(col, 2).unwrap(),Box::new(Pawn::new(Color::White)));
-        }
-
-        // --- PEÇAS PRETAS (LINHAS 7 E 8) ---
-        self.place_new_piece(ChessPosition::new('a', 8).unwrap(),Box::new(Rook::new(Color::Black)));
-        self.place_new_piece(ChessPosition::new('b', 8).unwrap(),Box::new(Knight::new(Color::Black)));
-        self.place_new_piece(ChessPosition::new('c', 8).unwrap(),Box::new(Bishop::new(Color::Black)));
-        self.place_new_piece(ChessPosition::new('d', 8).unwrap(),Box::new(Queen::new(Color::Black)));
-        self.place_new_piece(ChessPosition::new('e', 8).unwrap(),Box::new(King::new(Color::Black)));
-        self.place_new_piece(ChessPosition::new('f', 8).unwrap(),Box::new(Bishop::new(Color::Black)));
-        self.place_new_piece(ChessPosition::new('g', 8).unwrap(),Box::new(Knight::new(Color::Black)));
-        self.place_new_piece(ChessPosition::new('h', 8).unwrap(),Box::new(Rook::new(Color::Black)));
-        for col in 'a'..='h' {
-            self.place_new_piece(ChessPosition::new(col, 7).unwrap(),Box::new(Pawn::new(Color::Black)));
-        }
-    }
-}
\ No newline at end of file
+        self.put_piece(piece, pos.to_position(self.board.rows, self.board.cols).unwrap());
+    }
+}
+
+// Plays the moves with `perform`, which gets their squares, and fails on the first one it refuses
+#[cfg(test)]
+fn play_moves<T>(moves: &[(&str, &str)], mut perform: impl FnMut(ChessPosition, ChessPosition) -> Result<T, ChessError>) {
+    for &(source, target) in moves {
+        if let Err(e) = perform(source.parse().unwrap(), target.parse().unwrap()) {
+            panic!("{}-{}: {}", source, target, e.0);
+        }
+    }
+}
+
+// Square of a board of `size` by `size` in algebraic notation, e.g. "e4"
+#[cfg(test)]
+fn square_on(s: &str, size: usize) -> Position {
+    s.parse::<ChessPosition>().unwrap().to_position(size, size).unwrap()
+}
+
+#[cfg(test)]
+fn square(s: &str) -> Position {
+    square_on(s, 8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_stalemate() {
+        let mut chess_match = ChessMatch::with_pieces(&[
+            ("f7", PieceKind::King, Color::White),
+            ("g5", PieceKind::Queen, Color::White),
+            ("h8", PieceKind::King, Color::Black),
+        ]);
+        chess_match.play(&[("g5", "g6")]);
+        assert!(chess_match.stalemate);
+        assert!(!chess_match.check_mate);
+        assert_eq!(chess_match.draw_reason(), Some(GameEndReason::Stalemate));
+    }
+
+    #[test]
+    fn checkmate_is_not_stalemate() {
+        let mut chess_match = ChessMatch::with_pieces(&[
+            ("f6", PieceKind::King, Color::White),
+            ("g5", PieceKind::Queen, Color::White),
+            ("h8", PieceKind::King, Color::Black),
+        ]);
+        chess_match.play(&[("g5", "g7")]);
+        assert!(chess_match.check_mate);
+        assert!(!chess_match.stalemate);
+        assert_eq!(chess_match.draw_reason(), None);
+    }
+
+    #[test]
+    fn side_with_moves_is_not_stalemated() {
+        let mut chess_match = ChessMatch::new();
+        chess_match.play(&[("e2", "e4")]);
+        assert!(!chess_match.stalemate);
+        assert_eq!(chess_match.draw_reason(), None);
+    }
+
+    #[test]
+    fn legality_search_restores_pawn_captures() {
+        // Black to move after e4 d5 exd5 c6 dxc6: the search tries pawn captures and must undo them in place
+        let mut chess_match = ChessMatch::new();
+        chess_match.play(&[("e2", "e4"), ("d7", "d5"), ("e4", "d5"), ("c7", "c6"), ("d5", "c6")]);
+        assert!(!chess_match.board.there_is_a_piece(square("d5")));
+        assert_eq!(chess_match.board.piece(square("c6")).unwrap().color(), Color::White);
+    }
+
+    const KNIGHT_SHUFFLE: [(&str, &str); 4] = [("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")];
+
+    #[test]
+    fn transpositions_have_the_same_hash() {
+        let mut first = ChessMatch::new();
+        first.play(&[("e2", "e4"), ("e7", "e5"), ("g1", "f3"), ("b8", "c6")]);
+        let mut second = ChessMatch::new();
+        second.play(&[("g1", "f3"), ("b8", "c6"), ("e2", "e4"), ("e7", "e5")]);
+        assert_eq!(first.zobrist_hash(), second.zobrist_hash());
+        assert_ne!(first.zobrist_hash(), ChessMatch::new().zobrist_hash());
+    }
+
+    #[test]
+    fn losing_castling_rights_changes_the_hash() {
+        let mut chess_match = ChessMatch::new();
+        chess_match.play(&[("g1", "f3"), ("g8", "f6")]);
+        let before = chess_match.zobrist_hash();
+        chess_match.play(&[("h1", "g1"), ("f6", "g8"), ("g1", "h1"), ("g8", "f6")]);
+        assert_ne!(chess_match.zobrist_hash(), before);
+    }
+
+    #[test]
+    fn threefold_repetition_can_be_claimed() {
+        let mut chess_match = ChessMatch::new();
+        assert!(chess_match.claim_draw().is_err());
+        chess_match.play(&KNIGHT_SHUFFLE);
+        chess_match.play(&KNIGHT_SHUFFLE);
+        assert_eq!(chess_match.repetition_count(), 3);
+        assert_eq!(chess_match.draw_reason(), None);
+        assert_eq!(chess_match.claim_draw().unwrap(), GameEndReason::ThreefoldRepetition);
+        assert_eq!(chess_match.draw_reason(), Some(GameEndReason::ThreefoldRepetition));
+    }
+
+    #[test]
+    fn fivefold_repetition_ends_the_game() {
+        let mut chess_match = ChessMatch::new();
+        for _ in 0..4 {
+            chess_match.play(&KNIGHT_SHUFFLE);
+        }
+        assert_eq!(chess_match.draw_reason(), Some(GameEndReason::FivefoldRepetition));
+    }
+
+    #[test]
+    fn halfmove_clock_resets_on_pawn_moves_and_captures() {
+        let mut chess_match = ChessMatch::new();
+        chess_match.play(&[("g1", "f3"), ("g8", "f6")]);
+        assert_eq!(chess_match.halfmove_clock(), 2);
+        chess_match.play(&[("e2", "e4")]);
+        assert_eq!(chess_match.halfmove_clock(), 0);
+        chess_match.play(&[("f6", "e4")]);
+        assert_eq!(chess_match.halfmove_clock(), 0);
+        chess_match.play(&[("b1", "c3")]);
+        assert_eq!(chess_match.halfmove_clock(), 1);
+    }
+
+    #[test]
+    fn fifty_move_rule_is_claimable_and_seventy_five_is_automatic() {
+        let mut chess_match = ChessMatch::new();
+        chess_match.halfmove_clock = 99;
+        chess_match.play(&[("g1", "f3")]);
+        assert_eq!(chess_match.claimable_draw(), Some(GameEndReason::FiftyMoveRule));
+        assert_eq!(chess_match.draw_reason(), None);
+
+        chess_match.halfmove_clock = 149;
+        chess_match.play(&[("g8", "f6")]);
+        assert_eq!(chess_match.draw_reason(), Some(GameEndReason::SeventyFiveMoveRule));
+    }
+
+    #[test]
+    fn capture_into_king_and_knight_against_king_is_a_draw() {
+        let mut chess_match = ChessMatch::with_pieces(&[
+            ("e1", PieceKind::King, Color::White),
+            ("d3", PieceKind::Knight, Color::White),
+            ("e8", PieceKind::King, Color::Black),
+            ("e5", PieceKind::Pawn, Color::Black),
+        ]);
+        assert_eq!(chess_match.draw_reason(), None);
+        chess_match.play(&[("d3", "e5")]);
+        assert_eq!(chess_match.draw_reason(), Some(GameEndReason::InsufficientMaterial));
+    }
+
+    #[test]
+    fn detects_insufficient_material() {
+        let kings = [("e1", PieceKind::King, Color::White), ("e8", PieceKind::King, Color::Black)];
+        let with = |extra: &[(&'static str, PieceKind, Color)]| {
+            let mut pieces = kings.to_vec();
+            pieces.extend_from_slice(extra);
+            ChessMatch::with_pieces(&pieces).insufficient_material()
+        };
+
+        assert!(with(&[]));
+        assert!(with(&[("c1", PieceKind::Bishop, Color::White)]));
+        assert!(with(&[("g8", PieceKind::Knight, Color::Black)]));
+        // c1 and f8 are both dark squares
+        assert!(with(&[("c1", PieceKind::Bishop, Color::White), ("f8", PieceKind::Bishop, Color::Black)]));
+        assert!(!with(&[("c1", PieceKind::Bishop, Color::White), ("c8", PieceKind::Bishop, Color::Black)]));
+        assert!(!with(&[("c1", PieceKind::Bishop, Color::White), ("g8", PieceKind::Knight, Color::Black)]));
+        assert!(!with(&[("b1", PieceKind::Knight, Color::White), ("g1", PieceKind::Knight, Color::White)]));
+        assert!(!with(&[("a2", PieceKind::Pawn, Color::White)]));
+        assert!(!with(&[("a1", PieceKind::Rook, Color::White)]));
+    }
+
+    #[test]
+    fn flag_fall_against_a_bare_king_is_a_draw() {
+        let chess_match = ChessMatch::with_pieces(&[
+            ("e1", PieceKind::King, Color::White),
+            ("a1", PieceKind::Rook, Color::White),
+            ("e8", PieceKind::King, Color::Black),
+        ]);
+        assert_eq!(chess_match.timeout_result(Color::White), (None, GameEndReason::TimeoutVsInsufficientMaterial));
+        assert_eq!(chess_match.timeout_result(Color::Black), (Some(Color::White), GameEndReason::Timeout));
+    }
+
+    #[test]
+    fn twenty_legal_moves_from_the_start() {
+        let mut chess_match = ChessMatch::new();
+        let moves = chess_match.legal_moves();
+        assert_eq!(moves.len(), 20);
+        assert!(moves.iter().any(|mv| mv.to_coordinates(8) == "e2e4" && mv.piece == PieceKind::Pawn));
+        assert!(moves.iter().any(|mv| mv.to_coordinates(8) == "g1f3" && mv.piece == PieceKind::Knight));
+    }
+
+    #[test]
+    fn legal_moves_exclude_pinned_pieces() {
+        let mut chess_match = ChessMatch::with_pieces(&[
+            ("e1", PieceKind::King, Color::White),
+            ("e2", PieceKind::Knight, Color::White),
+            ("e8", PieceKind::Rook, Color::Black),
+            ("a8", PieceKind::King, Color::Black),
+        ]);
+        let moves = chess_match.legal_moves();
+        assert!(moves.iter().all(|mv| mv.piece == PieceKind::King));
+        assert!(!moves.is_empty());
+    }
+
+    #[test]
+    fn promotions_are_listed_once_per_piece() {
+        let mut chess_match = ChessMatch::with_pieces(&[
+            ("e1", PieceKind::King, Color::White),
+            ("b7", PieceKind::Pawn, Color::White),
+            ("a8", PieceKind::Rook, Color::Black),
+            ("h8", PieceKind::King, Color::Black),
+        ]);
+        let moves = chess_match.legal_moves();
+        let capture_promotions = moves.iter().filter(|mv| mv.target == square("a8")).collect::<Vec<_>>();
+        assert_eq!(capture_promotions.len(), 4);
+        assert!(capture_promotions.iter().all(|mv| mv.captured == Some(PieceKind::Rook) && mv.promotion.is_some()));
+        assert!(moves.iter().any(|mv| mv.to_coordinates(8) == "b7b8n"));
+    }
+
+    #[test]
+    fn castling_and_en_passant_are_flagged() {
+        let mut chess_match = ChessMatch::new();
+        chess_match.play(&[("e2", "e4"), ("a7", "a6"), ("e4", "e5"), ("d7", "d5"), ("g1", "f3"), ("a6", "a5"), ("f1", "e2"), ("a5", "a4")]);
+        let moves = chess_match.legal_moves();
+        let castle = moves.iter().find(|mv| mv.to_coordinates(8) == "e1g1").unwrap();
+        assert!(castle.is_castling() && !castle.is_capture());
+        assert!(!moves.iter().any(|mv| mv.to_coordinates(8) == "e5d6"));
+
+        let mut chess_match = ChessMatch::new();
+        chess_match.play(&[("e2", "e4"), ("a7", "a6"), ("e4", "e5"), ("d7", "d5")]);
+        let moves = chess_match.legal_moves();
+        let en_passant = moves.iter().find(|mv| mv.to_coordinates(8) == "e5d6").unwrap();
+        assert!(en_passant.is_en_passant);
+        assert_eq!(en_passant.captured, Some(PieceKind::Pawn));
+        assert_eq!(en_passant.capture_square(), square("d5"));
+    }
+
+    #[test]
+    fn no_legal_moves_after_checkmate() {
+        let mut chess_match = ChessMatch::new();
+        chess_match.play(&[("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")]);
+        assert!(chess_match.check_mate);
+        assert!(chess_match.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn undo_restores_the_whole_game_state() {
+        let mut chess_match = ChessMatch::new();
+        chess_match.play(&[("e2", "e4"), ("g8", "f6"), ("e4", "e5"), ("d7", "d5")]);
+        let hash = chess_match.zobrist_hash();
+        let en_passant = chess_match.get_en_passant_vulnerable();
+        assert!(en_passant.is_some());
+
+        chess_match.play(&[("e5", "d6")]);
+        assert_eq!(chess_match.captured_pieces.len(), 1);
+        assert_eq!(chess_match.undo().unwrap().to_coordinates(8), "e5d6");
+
+        assert_eq!(chess_match.zobrist_hash(), hash);
+        assert_eq!(chess_match.get_en_passant_vulnerable(), en_passant);
+        assert_eq!(chess_match.get_current_player(), Color::White);
+        assert!(chess_match.captured_pieces.is_empty());
+        assert_eq!(chess_match.board.piece(square("d5")).unwrap().kind(), PieceKind::Pawn);
+        assert_eq!(chess_match.repetition_count(), 1);
+    }
+
+    #[test]
+    fn chess960_games_start_from_the_indexed_position() {
+        let chess_match = ChessMatch::chess960(0).unwrap();
+        assert_eq!(chess_match.to_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+        assert_eq!(chess_match.variant(), Variant::Chess960);
+        assert_eq!(ChessMatch::chess960(518).unwrap().zobrist_hash(), ChessMatch::new().zobrist_hash());
+        assert!(ChessMatch::chess960(960).is_err());
+    }
+
+    #[test]
+    fn undo_restores_castling_rights() {
+        let mut chess_match = ChessMatch::new();
+        chess_match.play(&[("e2", "e4"), ("e7", "e5"), ("g1", "f3"), ("b8", "c6"), ("f1", "c4"), ("g8", "f6")]);
+        let hash = chess_match.zobrist_hash();
+        chess_match.play(&[("e1", "g1")]);
+        chess_match.undo().unwrap();
+        assert_eq!(chess_match.zobrist_hash(), hash);
+        assert_eq!(chess_match.castling_rights(Color::White), (true, true));
+        assert!(chess_match.legal_moves().iter().any(|mv| mv.is_castling()));
+    }
+
+    #[test]
+    fn undo_after_checkmate_and_redo() {
+        let mut chess_match = ChessMatch::new();
+        chess_match.play(&[("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")]);
+        let mate_hash = chess_match.zobrist_hash();
+        chess_match.undo().unwrap();
+        assert!(!chess_match.check_mate);
+        assert!(!chess_match.check);
+        assert_eq!(chess_match.get_current_player(), Color::Black);
+
+        assert_eq!(chess_match.redo().unwrap().to_coordinates(8), "d8h4");
+        assert!(chess_match.check_mate);
+        assert_eq!(chess_match.zobrist_hash(), mate_hash);
+        assert!(chess_match.redo().is_err());
+    }
+
+    #[test]
+    fn a_new_move_clears_the_redo_stack() {
+        let mut chess_match = ChessMatch::new();
+        assert!(chess_match.undo().is_err());
+        chess_match.play(&[("e2", "e4"), ("e7", "e5")]);
+        chess_match.undo().unwrap();
+        chess_match.undo().unwrap();
+        assert_eq!(chess_match.zobrist_hash(), ChessMatch::new().zobrist_hash());
+        chess_match.play(&[("d2", "d4")]);
+        assert!(chess_match.redo().is_err());
+        assert_eq!(chess_match.history().len(), 1);
+    }
+
+    #[test]
+    fn undo_restores_a_promoted_pawn() {
+        let mut chess_match = ChessMatch::with_pieces(&[
+            ("e1", PieceKind::King, Color::White),
+            ("b7", PieceKind::Pawn, Color::White),
+            ("a8", PieceKind::Rook, Color::Black),
+            ("h8", PieceKind::King, Color::Black),
+        ]);
+        chess_match.perform_chess_move("b7".parse().unwrap(), "a8".parse().unwrap(), Some(PieceKind::Knight)).unwrap();
+        assert_eq!(chess_match.board.piece(square("a8")).unwrap().kind(), PieceKind::Knight);
+        chess_match.undo().unwrap();
+        assert_eq!(chess_match.board.piece(square("b7")).unwrap().kind(), PieceKind::Pawn);
+        assert_eq!(chess_match.board.piece(square("a8")).unwrap().kind(), PieceKind::Rook);
+    }
+
+    #[test]
+    fn bitboards_agree_with_the_piece_move_matrices() {
+        let mut chess_match = ChessMatch::new();
+        let moves = [("e2", "e4"), ("a7", "a6"), ("e4", "e5"), ("d7", "d5"), ("g1", "f3"), ("c8", "g4"), ("f1", "c4"), ("b8", "c6")];
+        for (source, target) in moves {
+            chess_match.play(&[(source, target)]);
+            let with_bitboards = chess_match.legal_moves();
+            let bitboards = chess_match.bitboards.take();
+            assert_eq!(chess_match.legal_moves(), with_bitboards);
+            chess_match.bitboards = bitboards;
+        }
+        // e5xd6 en passant is gone by now, but castling is available
+        assert!(chess_match.legal_moves().iter().any(|mv| mv.is_castling()));
+    }
+
+    fn drop(chess_match: &mut ChessMatch, kind: PieceKind, target: &str) -> Result<Move, String> {
+        chess_match.perform_drop(kind, target.parse().unwrap()).map_err(|e| e.0)
+    }
+
+    #[test]
+    fn crazyhouse_captures_go_to_the_hand() {
+        let mut chess_match = ChessMatch::for_variant(Variant::Crazyhouse, 0);
+        chess_match.play(&[("e2", "e4"), ("d7", "d5"), ("e4", "d5"), ("d8", "d5")]);
+        assert_eq!(chess_match.hand(Color::White), [PieceKind::Pawn]);
+        assert_eq!(chess_match.hand(Color::Black), [PieceKind::Pawn]);
+
+        let mv = drop(&mut chess_match, PieceKind::Pawn, "c4").unwrap();
+        assert_eq!(mv.to_coordinates(8), "P@c4");
+        assert!(chess_match.hand(Color::White).is_empty());
+        assert_eq!(chess_match.to_fen(), "rnb1kbnr/ppp1pppp/8/3q4/2P5/8/PPPP1PPP/RNBQKBNR[p] b KQkq - 0 3");
+
+        chess_match.undo().unwrap();
+        assert_eq!(chess_match.hand(Color::White), [PieceKind::Pawn]);
+        assert!(!chess_match.board.there_is_a_piece(square("c4")));
+    }
+
+    #[test]
+    fn crazyhouse_drop_rules() {
+        let mut chess_match = ChessMatch::from_fen("4k3/8/8/8/8/8/8/4K2r[PNp] w - - 0 1").unwrap();
+        assert_eq!(drop(&mut chess_match, PieceKind::Queen, "d4"), Err("You have no Queen in hand".to_string()));
+        assert_eq!(drop(&mut chess_match, PieceKind::Pawn, "e8").unwrap_err(), "Pieces can only be dropped on an empty square");
+        assert_eq!(drop(&mut chess_match, PieceKind::Pawn, "d8").unwrap_err(), "Pawns can't be dropped on the first or last rank");
+        // In check from h1: a drop has to block on f1 or g1
+        assert_eq!(drop(&mut chess_match, PieceKind::Knight, "d4").unwrap_err(), "You can't put yourself in check");
+        assert!(drop(&mut chess_match, PieceKind::Knight, "g1").is_ok());
+
+        let mut standard = ChessMatch::new();
+        assert!(drop(&mut standard, PieceKind::Pawn, "e4").unwrap_err().contains("only be dropped in Crazyhouse"));
+    }
+
+    #[test]
+    fn crazyhouse_promoted_pieces_return_as_pawns() {
+        let mut chess_match = ChessMatch::from_fen("4k3/8/8/8/8/8/8/4Kq~2[] w - - 0 1").unwrap();
+        chess_match.play(&[("e1", "f1")]);
+        assert_eq!(chess_match.hand(Color::White), [PieceKind::Pawn]);
+        assert_eq!(chess_match.to_fen(), "4k3/8/8/8/8/8/8/5K2[P] b - - 0 1");
+        chess_match.undo().unwrap();
+        assert_eq!(chess_match.to_fen(), "4k3/8/8/8/8/8/8/4Kq~2[] w - - 0 1");
+
+        // A pawn promoting becomes a promoted piece
+        let mut chess_match = ChessMatch::from_fen("4k3/P7/8/8/8/8/8/4K3[] w - - 0 1").unwrap();
+        chess_match.play(&[("a7", "a8")]);
+        assert_eq!(chess_match.to_fen(), "Q~3k3/8/8/8/8/8/8/4K3[] b - - 0 1");
+    }
+
+    #[test]
+    fn crazyhouse_drops_can_stop_a_mate() {
+        // Back rank "mate" that a knight drop on f8 blocks
+        let chess_match = ChessMatch::from_fen("R5k1/5ppp/8/8/8/8/8/6K1[n] b - - 0 1").unwrap();
+        assert!(!chess_match.check_mate);
+        assert!(ChessMatch::from_fen("R5k1/5ppp/8/8/8/8/8/6K1[] b - - 0 1").unwrap().check_mate);
+    }
+
+    #[test]
+    fn atomic_captures_explode_the_pieces_around() {
+        let fen = "4k3/8/8/3npb2/4r3/3P4/8/K3R3 w - - 0 1";
+        let mut chess_match = ChessMatch::test_fen(Variant::Atomic, fen);
+        chess_match.play(&[("e1", "e4")]);
+        // The rook, the knight and the bishop are gone with the captured rook; pawns survive
+        assert_eq!(chess_match.to_fen(), "4k3/8/8/4p3/8/3P4/8/K7 b - - 0 1");
+        assert_eq!(chess_match.history()[0].san, "Rxe4");
+        chess_match.undo().unwrap();
+        assert_eq!(chess_match.to_fen(), fen);
+        assert_eq!(chess_match.zobrist_hash(), ChessMatch::test_fen(Variant::Atomic, fen).zobrist_hash());
+    }
+
+    #[test]
+    fn atomic_kings_cannot_capture() {
+        let mut chess_match = ChessMatch::test_fen(Variant::Atomic, "4k3/8/8/8/8/8/4p3/4K3 w - - 0 1");
+        assert!(chess_match.legal_moves().iter().all(|mv| !mv.is_capture()));
+        let err = chess_match.perform_chess_move("e1".parse().unwrap(), "e2".parse().unwrap(), None).unwrap_err();
+        assert_eq!(err.0, "In Atomic chess the king can't capture");
+    }
+
+    #[test]
+    fn atomic_kings_side_by_side_are_not_in_check() {
+        let mut chess_match = ChessMatch::with_pieces(&[
+            ("e4", PieceKind::King, Color::White),
+            ("e5", PieceKind::King, Color::Black),
+            ("a4", PieceKind::Rook, Color::Black),
+            ("h3", PieceKind::Rook, Color::Black),
+        ]);
+        assert!(chess_match.test_check(Color::White));
+        chess_match.variant = Variant::Atomic;
+        assert!(!chess_match.test_check(Color::White));
+        let targets = chess_match.legal_moves().iter().map(|mv| mv.target).collect::<Vec<_>>();
+        // Still next to the black king on d4, but attacked by the rook on h3 once away from it on d3
+        assert!(targets.contains(&square("d4")));
+        assert!(!targets.contains(&square("d3")));
+    }
+
+    #[test]
+    fn atomic_exploding_the_king_wins() {
+        // White is in check, but blowing up the black king comes first
+        let mut chess_match = ChessMatch::test_fen(Variant::Atomic, "4k3/3n4/8/8/8/8/8/3QK2r w - - 0 1");
+        chess_match.play(&[("d1", "d7")]);
+        assert_eq!(chess_match.variant_win(), Some((Color::White, GameEndReason::KingExploded)));
+        assert_eq!(chess_match.winner(), Some(Color::White));
+        assert!(!chess_match.check_mate);
+        assert!(chess_match.legal_moves().is_empty());
+        assert_eq!(chess_match.history()[0].san, "Qxd7#");
+        assert_eq!(chess_match.pgn_result(), "1-0");
+
+        chess_match.undo().unwrap();
+        assert_eq!(chess_match.winner(), None);
+        assert_eq!(chess_match.get_current_player(), Color::White);
+    }
+
+    #[test]
+    fn atomic_captures_next_to_the_own_king_are_illegal() {
+        let mut chess_match = ChessMatch::test_fen(Variant::Atomic, "4k3/8/8/8/8/8/3p4/3QK3 w - - 0 1");
+        let err = chess_match.perform_chess_move("d1".parse().unwrap(), "d2".parse().unwrap(), None).unwrap_err();
+        assert_eq!(err.0, "You can't put yourself in check");
+    }
+
+    #[test]
+    fn antichess_captures_are_compulsory() {
+        let mut chess_match = ChessMatch::for_variant(Variant::Antichess, 0);
+        chess_match.play(&[("e2", "e4"), ("d7", "d5")]);
+        let err = chess_match.perform_chess_move("g1".parse().unwrap(), "f3".parse().unwrap(), None).unwrap_err();
+        assert_eq!(err.0, "Captures are compulsory in Antichess: you must capture");
+        assert_eq!(chess_match.legal_moves().len(), 1);
+        chess_match.play(&[("e4", "d5")]);
+    }
+
+    #[test]
+    fn antichess_kings_are_ordinary_pieces() {
+        assert_eq!(ChessMatch::for_variant(Variant::Antichess, 0).to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1");
+        assert!(ChessMatch::from_variant_fen(Variant::Antichess, fen::START_FEN).is_err());
+
+        // No check: the king walks next to the rook, which then has to capture it
+        let mut chess_match = ChessMatch::test_fen(Variant::Antichess, "8/8/8/8/8/8/7r/4K3 w - - 0 1");
+        chess_match.play(&[("e1", "f2")]);
+        assert!(!chess_match.check);
+        assert_eq!(chess_match.legal_moves().len(), 1);
+        chess_match.play(&[("h2", "f2")]);
+        assert_eq!(chess_match.to_fen(), "8/8/8/8/8/8/5r2/8 w - - 0 2");
+
+        // Pawns may promote to a king, which standard chess doesn't allow
+        let fen = "8/P7/8/8/8/8/8/7k w - - 0 1";
+        let mut chess_match = ChessMatch::test_fen(Variant::Antichess, fen);
+        chess_match.perform_chess_move("a7".parse().unwrap(), "a8".parse().unwrap(), Some(PieceKind::King)).unwrap();
+        assert_eq!(chess_match.history()[0].san, "a8=K");
+        assert!(ChessMatch::from_fen("8/P7/8/8/8/8/8/k6K w - - 0 1").unwrap()
+            .perform_chess_move("a7".parse().unwrap(), "a8".parse().unwrap(), Some(PieceKind::King))
+            .is_err());
+    }
+
+    #[test]
+    fn antichess_is_won_by_losing_every_piece() {
+        let mut chess_match = ChessMatch::test_fen(Variant::Antichess, "r7/8/8/8/8/8/8/R7 w - - 0 1");
+        chess_match.play(&[("a1", "a8")]);
+        assert_eq!(chess_match.variant_win(), Some((Color::Black, GameEndReason::NoPiecesLeft)));
+        assert_eq!(chess_match.winner(), Some(Color::Black));
+        assert_eq!(chess_match.history()[0].san, "Rxa8");
+        assert_eq!(chess_match.pgn_result(), "0-1");
+        assert!(chess_match.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn antichess_is_won_with_no_legal_moves() {
+        let fen = "8/8/8/8/8/p7/P7/8 b - - 0 1";
+        let chess_match = ChessMatch::test_fen(Variant::Antichess, fen);
+        assert_eq!(chess_match.variant_win(), Some((Color::Black, GameEndReason::NoLegalMoves)));
+        assert!(!chess_match.stalemate);
+        assert_eq!(chess_match.to_fen(), fen);
+    }
+
+    #[test]
+    fn antichess_bishops_on_different_colors_never_meet() {
+        assert!(ChessMatch::test_fen(Variant::Antichess, "8/8/8/8/8/8/8/Bb6 w - - 0 1").insufficient_material());
+        assert!(!ChessMatch::test_fen(Variant::Antichess, "8/8/8/8/8/8/1b6/B7 w - - 0 1").insufficient_material());
+        assert!(!ChessMatch::test_fen(Variant::Antichess, "8/8/8/8/8/8/8/Kk6 w - - 0 1").insufficient_material());
+    }
+
+    #[test]
+    fn capablanca_starts_on_a_10x8_board() {
+        let mut chess_match = ChessMatch::capablanca();
+        assert_eq!((chess_match.board.rows, chess_match.board.cols), (8, 10));
+        assert_eq!(chess_match.to_fen(), fen::CAPABLANCA_START_FEN);
+        assert_eq!(chess_match.legal_moves().len(), 28);
+        match chess_match.to_game_state(String::new()) {
+            GameMessage::GameState { board, .. } => assert!(board.len() == 8 && board.iter().all(|rank| rank.len() == 10)),
+            _ => unreachable!(),
+        }
+
+        chess_match.play(&[("j2", "j4")]);
+        chess_match.perform_san_move("Ci6").unwrap();
+        assert_eq!(chess_match.board.piece(Position::new(2, 8)).unwrap().kind(), PieceKind::Chancellor);
+        let err = chess_match.perform_chess_move("k2".parse().unwrap(), "k4".parse().unwrap(), None).err().unwrap();
+        assert_eq!(err.0, "Square k2 is off the board, which goes from a1 to j8");
+    }
+
+    #[test]
+    fn capablanca_archbishop_and_chancellor_moves() {
+        let mut chess_match = ChessMatch::test_fen(Variant::Capablanca, "5k4/10/10/10/10/10/10/A4K3C w - - 0 1");
+        let moves = chess_match.legal_moves().iter().map(|mv| mv.to_coordinates(8)).collect::<Vec<_>>();
+        for mv in ["a1b3", "a1c2", "a1h8", "j1i3", "j1h2", "j1j8", "j1g1"] {
+            assert!(moves.contains(&mv.to_string()), "{} is missing", mv);
+        }
+        for mv in ["a1a2", "a1b1", "j1i2"] {
+            assert!(!moves.contains(&mv.to_string()), "{} is not legal", mv);
+        }
+        // Knight jumps give check too
+        assert!(ChessMatch::test_fen(Variant::Capablanca, "5k4/10/4C5/10/10/10/10/5K4 b - - 0 1").check);
+        assert!(ChessMatch::test_fen(Variant::Capablanca, "5k4/10/6A3/10/10/10/10/5K4 b - - 0 1").check);
+    }
+
+    #[test]
+    fn capablanca_castles_to_the_i_and_c_files() {
+        let mut chess_match = ChessMatch::test_fen(Variant::Capablanca, "r4k3r/10/10/10/10/10/10/R4K3R w KQkq - 0 1");
+        chess_match.perform_san_move("O-O").unwrap();
+        chess_match.perform_san_move("O-O-O").unwrap();
+        assert_eq!(chess_match.to_fen(), "2kr5r/10/10/10/10/10/10/R6RK1 w - - 2 2");
+        assert_eq!(chess_match.history()[0].mv.to_coordinates(8), "f1i1");
+    }
+
+    #[test]
+    fn capablanca_pawns_promote_to_the_new_pieces() {
+        let mut chess_match = ChessMatch::test_fen(Variant::Capablanca, "5k4/1P8/10/10/10/10/10/5K4 w - - 0 1");
+        chess_match.perform_chess_move("b7".parse().unwrap(), "b8".parse().unwrap(), Some(PieceKind::Archbishop)).unwrap();
+        assert_eq!(chess_match.history()[0].san, "b8=A");
+        let mut standard = ChessMatch::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let err = standard.perform_chess_move("b7".parse().unwrap(), "b8".parse().unwrap(), Some(PieceKind::Chancellor)).err().unwrap();
+        assert_eq!(err.0, "A pawn can only be promoted to a queen, rook, bishop or knight");
+    }
+
+    #[test]
+    fn dark_chess_players_only_see_what_their_pieces_reach() {
+        let mut chess_match = ChessMatch::for_variant(Variant::DarkChess, 0);
+        // At the start each side sees its own half of the board and nothing more
+        let sight = chess_match.visible_squares(Color::White);
+        assert!((0..8).all(|row| sight[row].iter().all(|&seen| seen == (row >= 4))));
+
+        chess_match.play(&[("e2", "e4"), ("d7", "d5")]);
+        let (board, visible) = match chess_match.to_game_state_for(Color::White, String::new()) {
+            GameMessage::GameState { board, visible, .. } => (board, visible.unwrap()),
+            _ => unreachable!(),
+        };
+        // The e4 pawn sees the d5 pawn it can take and the empty e5 square, but not the black pieces behind
+        assert!(board[3][3].as_ref().is_some_and(|pv| pv.kind == PieceKind::Pawn && pv.color == Color::Black));
+        assert!(visible[3][4] && board[3][4].is_none());
+        assert!(!visible[0][4] && board[0][4].is_none());
+        assert!(board.iter().flatten().flatten().filter(|pv| pv.color == Color::Black).count() == 1);
+
+        // Other variants send the whole board
+        let standard = ChessMatch::new();
+        match standard.to_game_state_for(Color::White, String::new()) {
+            GameMessage::GameState { board, visible, .. } => {
+                assert!(visible.is_none());
+                assert_eq!(board.iter().flatten().flatten().count(), 32);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn dark_chess_is_won_by_capturing_the_king() {
+        // There is no check, so the king may step next to the rook, which then takes it
+        let mut chess_match = ChessMatch::test_fen(Variant::DarkChess, "4k3/8/8/8/8/8/5r2/4K3 w - - 0 1");
+        chess_match.play(&[("e1", "e2")]);
+        assert!(!chess_match.check);
+        chess_match.play(&[("f2", "e2")]);
+        assert_eq!(chess_match.variant_win(), Some((Color::Black, GameEndReason::KingCaptured)));
+        assert_eq!(chess_match.winner(), Some(Color::Black));
+        assert_eq!(chess_match.history()[1].san, "Rxe2#");
+        assert!(chess_match.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn dark_chess_castles_through_attacked_squares() {
+        let fen = "4kr2/8/8/8/8/8/8/4K2R w K - 0 1";
+        let mut chess_match = ChessMatch::test_fen(Variant::DarkChess, fen);
+        chess_match.perform_san_move("O-O").unwrap();
+        assert_eq!(chess_match.to_fen(), "4kr2/8/8/8/8/8/8/5RK1 b - - 1 1");
+        assert!(ChessMatch::from_fen(fen).unwrap().perform_san_move("O-O").is_err());
+    }
+
+    #[test]
+    fn dark_chess_errors_dont_reveal_hidden_squares() {
+        let mut chess_match = ChessMatch::for_variant(Variant::DarkChess, 0);
+        // Neither e7, with a black pawn, nor the empty e5 can be seen from White's side
+        let enemy = chess_match.perform_chess_move("e7".parse().unwrap(), "e6".parse().unwrap(), None).unwrap_err();
+        let empty = chess_match.perform_chess_move("e5".parse().unwrap(), "e4".parse().unwrap(), None).unwrap_err();
+        assert_eq!(enemy.0, "You have no piece on e7");
+        assert_eq!(empty.0, "You have no piece on e5");
+    }
+
+    #[test]
+    fn three_check_is_won_by_the_third_check() {
+        let mut chess_match = ChessMatch::for_variant(Variant::ThreeCheck, 0);
+        for san in ["e4", "e5", "Bc4", "Nc6", "Bxf7+", "Kxf7", "Qf3+", "Ke7"] {
+            chess_match.perform_san_move(san).unwrap();
+        }
+        match chess_match.to_game_state(String::new()) {
+            GameMessage::GameState { checks, .. } => assert_eq!(checks, Some((2, 0))),
+            _ => unreachable!(),
+        }
+        chess_match.perform_san_move("Qf7").unwrap();
+        assert_eq!(chess_match.variant_win(), Some((Color::White, GameEndReason::ThreeChecks)));
+        assert_eq!(chess_match.history().last().unwrap().san, "Qf7#");
+
+        chess_match.undo().unwrap();
+        assert_eq!(chess_match.checks, [2, 0]);
+        assert_eq!(chess_match.variant_win(), None);
+        assert!(!ChessMatch::from_variant_fen(Variant::ThreeCheck, "4k3/8/8/8/8/8/8/4KN2 w - - 0 1").unwrap().insufficient_material());
+
+        // Other variants don't count checks
+        let mut chess_match = ChessMatch::new();
+        for san in ["e4", "e5", "Bc4", "Nc6", "Bxf7+"] {
+            chess_match.perform_san_move(san).unwrap();
+        }
+        assert_eq!(chess_match.checks, [0, 0]);
+    }
+
+    #[test]
+    fn king_of_the_hill_is_won_in_the_center() {
+        let fen = "k7/8/8/8/8/3K4/8/8 w - - 0 1";
+        let mut chess_match = ChessMatch::from_variant_fen(Variant::KingOfTheHill, fen).unwrap();
+        // A bare king can still win by reaching the hill
+        assert!(!chess_match.insufficient_material());
+        chess_match.perform_san_move("Ke4").unwrap();
+        assert_eq!(chess_match.winner(), Some(Color::White));
+        assert_eq!(chess_match.variant_win(), Some((Color::White, GameEndReason::KingOfTheHill)));
+        assert!(ChessMatch::from_fen(fen).unwrap().insufficient_material());
+    }
+}
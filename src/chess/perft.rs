@@ -0,0 +1,164 @@
+// Performance test: counts the leaf nodes of the legal move tree to a fixed depth.
+//
+// The counts are compared against well-known reference values to check the move
+// generator. The search only plays moves on the board and keeps the en passant state
+// in sync, skipping the check, draw and history bookkeeping of a real game.
+use crate::chess::chess_move::Move;
+use crate::chess::ChessMatch;
+
+impl ChessMatch {
+    pub fn perft(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        moves.into_iter().map(|mv| self.perft_after(mv, depth - 1)).sum()
+    }
+
+    // Node count below each legal move of the current position
+    pub fn divide(&mut self, depth: u32) -> Vec<(Move, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+        self.legal_moves()
+            .into_iter()
+            .map(|mv| (mv, self.perft_after(mv, depth - 1)))
+            .collect()
+    }
+
+    fn perft_after(&mut self, mv: Move, depth: u32) -> u64 {
+        let en_passant_vulnerable = self.en_passant_vulnerable;
+        let captured_piece = self.make_move(&mv);
+        self.en_passant_vulnerable = Self::double_step(&mv);
+        self.current_player = self.current_player.opponent();
+
+        let nodes = self.perft(depth);
+
+        self.current_player = self.current_player.opponent();
+        self.en_passant_vulnerable = en_passant_vulnerable;
+        self.undo_move(&mv, captured_piece);
+        nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess::fen::START_FEN;
+    use crate::chess::variant::Variant;
+    use crate::chess::ChessMatch;
+
+    // Reference positions and node counts from https://www.chessprogramming.org/Perft_Results
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+    const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+    const POSITION_4_MIRRORED: &str = "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+    const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+    const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+    // Chess960 positions from https://www.chessprogramming.org/Chess960_Perft_Results
+    const CHESS960_1: &str = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+    const CHESS960_2: &str = "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9";
+    // Drops only start at ply 5, so the first counts match the standard ones
+    const CRAZYHOUSE_START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1";
+
+    fn assert_perft(fen: &str, expected: &[u64]) {
+        let mut chess_match = ChessMatch::from_fen(fen).unwrap();
+        for (depth, &nodes) in (1..).zip(expected) {
+            assert_eq!(chess_match.perft(depth), nodes, "perft({}) of {}", depth, fen);
+        }
+    }
+
+    #[test]
+    fn start_position() {
+        assert_perft(START_FEN, &[20, 400, 8902]);
+    }
+
+    #[test]
+    fn kiwipete() {
+        assert_perft(KIWIPETE, &[48, 2039, 97862]);
+    }
+
+    #[test]
+    fn position_3() {
+        assert_perft(POSITION_3, &[14, 191, 2812, 43238]);
+    }
+
+    #[test]
+    fn position_4() {
+        assert_perft(POSITION_4, &[6, 264, 9467]);
+        assert_perft(POSITION_4_MIRRORED, &[6, 264, 9467]);
+    }
+
+    #[test]
+    fn position_5() {
+        assert_perft(POSITION_5, &[44, 1486, 62379]);
+    }
+
+    #[test]
+    fn position_6() {
+        assert_perft(POSITION_6, &[46, 2079, 89890]);
+    }
+
+    #[test]
+    fn chess960_positions() {
+        assert_perft(CHESS960_1, &[21, 528, 12189]);
+        assert_perft(CHESS960_2, &[21, 807, 18002]);
+    }
+
+    #[test]
+    fn crazyhouse_drops() {
+        assert_perft(CRAZYHOUSE_START, &[20, 400, 8902]);
+        // King moves, plus a knight or a pawn dropped on any of the 62 empty squares (pawns not on the back ranks)
+        let mut chess_match = ChessMatch::from_fen("4k3/8/8/8/8/8/8/4K3[NNPp] w - - 0 1").unwrap();
+        assert_eq!(chess_match.perft(1), 5 + 62 + 48);
+    }
+
+    #[test]
+    fn atomic_and_antichess_start_positions() {
+        assert_start_perft(Variant::Atomic, &[20, 400, 8902]);
+        // Compulsory captures already cut the tree at depth 3
+        assert_start_perft(Variant::Antichess, &[20, 400, 8067]);
+    }
+
+    #[test]
+    fn capablanca_start_position() {
+        assert_start_perft(Variant::Capablanca, &[28, 784, 25228]);
+    }
+
+    fn assert_start_perft(variant: Variant, expected: &[u64]) {
+        let mut chess_match = ChessMatch::for_variant(variant, 0);
+        for (depth, &nodes) in (1..).zip(expected) {
+            assert_eq!(chess_match.perft(depth), nodes, "perft({}) of the {} start position", depth, variant);
+        }
+    }
+
+    // Deeper searches, too slow for every test run: cargo test --release -- --ignored
+    #[test]
+    #[ignore]
+    fn deep_reference_counts() {
+        assert_perft(START_FEN, &[20, 400, 8902, 197281]);
+        assert_perft(KIWIPETE, &[48, 2039, 97862, 4085603]);
+        assert_perft(POSITION_3, &[14, 191, 2812, 43238, 674624]);
+        assert_perft(POSITION_4, &[6, 264, 9467, 422333]);
+        assert_perft(POSITION_5, &[44, 1486, 62379, 2103487]);
+        assert_perft(POSITION_6, &[46, 2079, 89890, 3894594]);
+        assert_perft(CHESS960_1, &[21, 528, 12189, 326672]);
+        assert_perft(CHESS960_2, &[21, 807, 18002, 667366]);
+        assert_perft(CRAZYHOUSE_START, &[20, 400, 8902, 197281, 4888832]);
+        // Explosions and the king capture ban first show at depth 4
+        assert_start_perft(Variant::Atomic, &[20, 400, 8902, 197326, 4864979]);
+        assert_start_perft(Variant::Antichess, &[20, 400, 8067, 153299, 2732672]);
+        assert_start_perft(Variant::Capablanca, &[28, 784, 25228, 805128]);
+    }
+
+    #[test]
+    fn divide_splits_the_count_by_root_move() {
+        let mut chess_match = ChessMatch::new();
+        let divide = chess_match.divide(2);
+        assert_eq!(divide.len(), 20);
+        assert!(divide.iter().all(|&(_, nodes)| nodes == 20));
+        assert_eq!(chess_match.perft(2), 400);
+    }
+}
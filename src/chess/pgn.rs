@@ -0,0 +1,548 @@
+// Portable Game Notation: writing the moves of a ChessMatch as a PGN game, and reading
+// PGN files back to replay their games.
+use crate::chess::color::Color;
+use crate::chess::variant::Variant;
+use crate::chess::ChessMatch;
+use crate::error::ChessError;
+use crate::network::GameEndReason;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Tags every PGN game has, in this order, with the values used when they are unknown
+pub const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+// Movetext lines are kept under the 80 columns the PGN standard recommends
+const MAX_LINE_LENGTH: usize = 79;
+
+impl ChessMatch {
+    // Result token of the game as it stands on the board: "1-0", "0-1", "1/2-1/2" or "*" while it goes on
+    pub fn pgn_result(&self) -> &'static str {
+        if let Some(winner) = self.winner() {
+            result_token(Some(winner))
+        } else if self.draw_reason().is_some() {
+            result_token(None)
+        } else {
+            "*"
+        }
+    }
+
+    // PGN of the game so far. `tags` fill in or override the Seven Tag Roster and can add
+    // others, which are written after it. Without a "Result" tag the result on the board is used.
+    pub fn to_pgn(&self, tags: &[(&str, &str)]) -> String {
+        let tag_value = |name: &str| tags.iter().find(|(tag, _)| *tag == name).map(|&(_, value)| value);
+        let result = tag_value("Result").unwrap_or_else(|| self.pgn_result());
+
+        let mut pgn = String::new();
+        for (name, default) in SEVEN_TAG_ROSTER {
+            let value = if name == "Result" { result } else { tag_value(name).unwrap_or(default) };
+            pgn.push_str(&tag_pair(name, value));
+        }
+        if let Some(variant) = self.variant.pgn_name() {
+            pgn.push_str(&tag_pair("Variant", variant));
+        }
+        if let Some(fen) = &self.setup_fen {
+            pgn.push_str(&tag_pair("SetUp", "1"));
+            pgn.push_str(&tag_pair("FEN", fen));
+        }
+        for &(name, value) in tags {
+            let is_roster_tag = SEVEN_TAG_ROSTER.iter().any(|&(tag, _)| tag == name);
+            if !is_roster_tag && !["Variant", "SetUp", "FEN"].contains(&name) {
+                pgn.push_str(&tag_pair(name, value));
+            }
+        }
+        pgn.push('\n');
+
+        let mut tokens = Vec::new();
+        let mut color = self.starting_player();
+        let mut move_number = self.starting_move_number();
+        for (i, record) in self.history.iter().enumerate() {
+            if color == Color::White {
+                tokens.push(format!("{}.", move_number));
+            } else if i == 0 {
+                tokens.push(format!("{}...", move_number));
+            }
+            tokens.push(record.san.clone());
+            if color == Color::Black {
+                move_number += 1;
+            }
+            color = color.opponent();
+        }
+        tokens.push(result.to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > MAX_LINE_LENGTH {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        pgn.push_str(&line);
+        pgn.push('\n');
+        pgn
+    }
+
+    fn starting_player(&self) -> Color {
+        match self.history.first() {
+            Some(record) => record.previous_state.current_player,
+            None if self.winner().is_some() => self.current_player.opponent(),
+            None => self.current_player,
+        }
+    }
+
+    fn starting_move_number(&self) -> u32 {
+        let turn = match self.history.first() {
+            Some(record) => record.previous_state.turn,
+            None => self.turn,
+        };
+        turn.div_ceil(2)
+    }
+}
+
+// A game read from PGN: its tags, the SAN moves of the main line and the result token.
+// Comments, NAGs and variations are skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<String>,
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+
+    // Plays the main line from the start position, or from the "FEN" tag when there is one.
+    // An illegal or ambiguous move stops the replay with an error naming its ply.
+    pub fn replay(&self) -> Result<ChessMatch, ChessError> {
+        let variant = self.tag("Variant").map(str::parse::<Variant>).transpose()?.unwrap_or_default();
+        let mut chess_match = match self.tag("FEN") {
+            Some(fen) => ChessMatch::from_variant_fen(variant, fen)?,
+            None if variant == Variant::Capablanca => ChessMatch::capablanca(),
+            None => ChessMatch::new(),
+        };
+        chess_match.variant = variant;
+
+        for (i, san) in self.moves.iter().enumerate() {
+            let ply = i + 1;
+            // After a win the turn isn't passed on, but a further move would be the other side's
+            let (color, turn) = if chess_match.winner().is_some() {
+                (chess_match.current_player.opponent(), chess_match.turn + 1)
+            } else {
+                (chess_match.current_player, chess_match.turn)
+            };
+            let move_label = match color {
+                Color::White => format!("{}. {}", turn.div_ceil(2), san),
+                Color::Black => format!("{}... {}", turn.div_ceil(2), san),
+            };
+            let fail = |e: ChessError| ChessError(format!("Ply {} ({}): {}", ply, move_label, e));
+
+            if chess_match.winner().is_some() || chess_match.draw_reason().is_some() {
+                return Err(fail(ChessError("the game is already over".to_string())));
+            }
+            chess_match.perform_san_move(san).map_err(fail)?;
+        }
+        Ok(chess_match)
+    }
+}
+
+// Reads every game of a PGN file
+pub fn parse_pgn(text: &str) -> Result<Vec<PgnGame>, ChessError> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::default();
+    let mut in_movetext = false;
+    let mut variation_depth = 0;
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+
+    let finish = |game: &mut PgnGame, games: &mut Vec<PgnGame>| {
+        if game.result.is_empty() {
+            game.result = "*".to_string();
+        }
+        games.push(std::mem::take(game));
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            // Rest-of-line comments, and the '%' escape mechanism
+            '%' | ';' => {
+                skip_line(&mut chars);
+                line += 1;
+            }
+            '{' => loop {
+                match chars.next() {
+                    Some('}') => break,
+                    Some('\n') => line += 1,
+                    Some(_) => {}
+                    None => return Err(ChessError(format!("PGN line {}: comment is never closed", line))),
+                }
+            },
+            '(' => variation_depth += 1,
+            ')' => {
+                if variation_depth == 0 {
+                    return Err(ChessError(format!("PGN line {}: ')' without a matching '('", line)));
+                }
+                variation_depth -= 1;
+            }
+            '[' if variation_depth == 0 => {
+                // A tag after the moves starts the next game, even if the result was left out
+                if in_movetext {
+                    finish(&mut game, &mut games);
+                    in_movetext = false;
+                }
+                let (name, value) = read_tag(&mut chars, line)?;
+                game.tags.push((name, value));
+            }
+            _ => {
+                let mut token = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "{}()[];".contains(next) {
+                        break;
+                    }
+                    token.push(next);
+                    chars.next();
+                }
+                if variation_depth > 0 {
+                    continue;
+                }
+                in_movetext = true;
+                match token.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => {
+                        game.result = token;
+                        finish(&mut game, &mut games);
+                        in_movetext = false;
+                    }
+                    _ => {
+                        if let Some(san) = move_in_token(&token) {
+                            game.moves.push(san.to_string());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if variation_depth > 0 {
+        return Err(ChessError(format!("PGN line {}: variation is never closed", line)));
+    }
+    if in_movetext || !game.tags.is_empty() {
+        finish(&mut game, &mut games);
+    }
+    Ok(games)
+}
+
+fn skip_line(chars: &mut std::iter::Peekable<std::str::Chars>) {
+    for c in chars.by_ref() {
+        if c == '\n' {
+            break;
+        }
+    }
+}
+
+// Reads `Name "value"]` after the opening bracket
+fn read_tag(chars: &mut std::iter::Peekable<std::str::Chars>, line: usize) -> Result<(String, String), ChessError> {
+    let malformed = || ChessError(format!("PGN line {}: malformed tag", line));
+
+    let mut name = String::new();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() || c == '"' {
+            break;
+        }
+        name.push(c);
+        chars.next();
+    }
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+    if name.is_empty() || chars.next() != Some('"') {
+        return Err(malformed());
+    }
+
+    let mut value = String::new();
+    loop {
+        match chars.next() {
+            Some('\\') => value.push(chars.next().ok_or_else(malformed)?),
+            Some('"') => break,
+            Some('\n') | None => return Err(malformed()),
+            Some(c) => value.push(c),
+        }
+    }
+    while chars.peek().is_some_and(|c| c.is_whitespace() && *c != '\n') {
+        chars.next();
+    }
+    if chars.next() != Some(']') {
+        return Err(malformed());
+    }
+    Ok((name, value))
+}
+
+// The SAN move in a movetext token, if any: move numbers ("12." or "12...", possibly
+// glued to the move) and NAGs ("$1") carry none, and "!"/"?" annotations are dropped
+fn move_in_token(token: &str) -> Option<&str> {
+    if token.starts_with('$') {
+        return None;
+    }
+    let san = token.trim_start_matches(|c: char| c.is_ascii_digit());
+    let san = if san.len() < token.len() { san.trim_start_matches('.') } else { token };
+    let san = san.trim_end_matches(['!', '?']);
+    if san.is_empty() { None } else { Some(san) }
+}
+
+// Result token for a finished game, given its winner (None for a draw)
+pub fn result_token(winner: Option<Color>) -> &'static str {
+    match winner {
+        Some(Color::White) => "1-0",
+        Some(Color::Black) => "0-1",
+        None => "1/2-1/2",
+    }
+}
+
+// Value of the "Termination" tag for the way a game ended
+pub fn termination(reason: GameEndReason) -> &'static str {
+    match reason {
+        GameEndReason::Timeout | GameEndReason::TimeoutVsInsufficientMaterial => "time forfeit",
+        GameEndReason::Disconnect => "abandoned",
+        _ => "normal",
+    }
+}
+
+// Today's date (UTC) in the PGN format, e.g. "2024.03.09"
+pub fn today() -> String {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() / 86_400).unwrap_or(0);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+// Gregorian date of a day count since 1970-01-01 (Howard Hinnant's algorithm)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+fn tag_pair(name: &str, value: &str) -> String {
+    format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::piece_kind::PieceKind;
+
+    #[test]
+    fn finished_game_with_tags() {
+        let mut chess_match = ChessMatch::new();
+        chess_match.play(&[("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")]);
+        let pgn = chess_match.to_pgn(&[("Event", "Casual game"), ("White", "Fool"), ("Termination", "normal")]);
+        assert_eq!(
+            pgn,
+            "[Event \"Casual game\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n[White \"Fool\"]\n[Black \"?\"]\n[Result \"0-1\"]\n[Termination \"normal\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n"
+        );
+    }
+
+    #[test]
+    fn game_in_progress_and_result_override() {
+        let mut chess_match = ChessMatch::new();
+        chess_match.play(&[("e2", "e4"), ("e7", "e5"), ("g1", "f3")]);
+        assert_eq!(chess_match.pgn_result(), "*");
+        assert!(chess_match.to_pgn(&[]).ends_with("\n\n1. e4 e5 2. Nf3 *\n"));
+
+        let pgn = chess_match.to_pgn(&[("Result", result_token(Some(Color::White)))]);
+        assert!(pgn.contains("[Result \"1-0\"]"));
+        assert!(pgn.ends_with("2. Nf3 1-0\n"));
+    }
+
+    #[test]
+    fn game_from_a_position_starts_with_its_fen() {
+        let fen = "4k3/8/8/8/8/8/4p3/R3K3 b Q - 0 30";
+        let mut chess_match = ChessMatch::from_fen(fen).unwrap();
+        chess_match.play(&[("e8", "d7"), ("e1", "e2")]);
+        let pgn = chess_match.to_pgn(&[]);
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4p3/R3K3 b Q - 0 30\"]\n"));
+        assert!(pgn.ends_with("\n\n30... Kd7 31. Kxe2 *\n"));
+    }
+
+    #[test]
+    fn chess960_games_name_their_variant_and_position() {
+        let mut chess_match = ChessMatch::chess960(0).unwrap();
+        chess_match.play(&[("g2", "g3"), ("g7", "g6"), ("e1", "f3")]);
+        let pgn = chess_match.to_pgn(&[]);
+        assert!(pgn.contains("[Variant \"Chess960\"]\n[SetUp \"1\"]\n[FEN \"bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1\"]\n"));
+        assert!(pgn.ends_with("\n\n1. g3 g6 2. Nf3 *\n"), "{}", pgn);
+
+        let replayed = parse_pgn(&pgn).unwrap()[0].replay().unwrap();
+        assert_eq!(replayed.variant(), Variant::Chess960);
+        assert_eq!(replayed.to_fen(), chess_match.to_fen());
+    }
+
+    #[test]
+    fn crazyhouse_games_read_back() {
+        let mut chess_match = ChessMatch::for_variant(Variant::Crazyhouse, 0);
+        chess_match.play(&[("e2", "e4"), ("d7", "d5"), ("e4", "d5"), ("d8", "d5")]);
+        chess_match.perform_drop(PieceKind::Pawn, "c4".parse().unwrap()).unwrap();
+        let pgn = chess_match.to_pgn(&[]);
+        assert!(pgn.contains("[Variant \"Crazyhouse\"]\n"));
+        assert!(pgn.ends_with("\n\n1. e4 d5 2. exd5 Qxd5 3. P@c4 *\n"), "{}", pgn);
+
+        let replayed = parse_pgn(&pgn).unwrap()[0].replay().unwrap();
+        assert_eq!(replayed.to_fen(), chess_match.to_fen());
+    }
+
+    #[test]
+    fn antichess_games_read_back() {
+        let mut chess_match = ChessMatch::for_variant(Variant::Antichess, 0);
+        chess_match.play(&[("e2", "e3"), ("b7", "b5"), ("f1", "b5")]);
+        let pgn = chess_match.to_pgn(&[]);
+        assert!(pgn.contains("[Variant \"Antichess\"]\n"));
+        assert!(pgn.ends_with("\n\n1. e3 b5 2. Bxb5 *\n"), "{}", pgn);
+        assert_eq!(parse_pgn(&pgn).unwrap()[0].replay().unwrap().to_fen(), chess_match.to_fen());
+
+        // Skipping the capture is illegal once the game is known to be Antichess
+        let skipped = parse_pgn("[Variant \"Antichess\"]\n\n1. e3 b5 2. Nf3 *\n").unwrap();
+        assert_eq!(skipped[0].replay().err().unwrap().0, "Ply 3 (2. Nf3): 'Nf3' is not a legal move");
+    }
+
+    #[test]
+    fn capablanca_games_read_back() {
+        let mut chess_match = ChessMatch::for_variant(Variant::Capablanca, 0);
+        chess_match.play(&[("e2", "e4"), ("i7", "i5"), ("h1", "i3"), ("c8", "d6")]);
+        let pgn = chess_match.to_pgn(&[]);
+        assert!(pgn.contains("[Variant \"Capablanca\"]\n"));
+        assert!(!pgn.contains("[FEN "));
+        assert!(pgn.ends_with("\n\n1. e4 i5 2. Ci3 Ad6 *\n"), "{}", pgn);
+        assert_eq!(parse_pgn(&pgn).unwrap()[0].replay().unwrap().to_fen(), chess_match.to_fen());
+    }
+
+    #[test]
+    fn long_movetext_is_wrapped() {
+        let mut chess_match = ChessMatch::new();
+        for _ in 0..6 {
+            chess_match.play(&[("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")]);
+        }
+        let pgn = chess_match.to_pgn(&[]);
+        let movetext = pgn.split("\n\n").nth(1).unwrap();
+        assert!(movetext.lines().count() > 1);
+        assert!(movetext.lines().all(|line| line.len() <= MAX_LINE_LENGTH));
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_791), (2024, 3, 9));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(today().len(), 10);
+    }
+
+    const MULTI_GAME_FILE: &str = r#"
+[Event "Casual game"]
+[Site "?"]
+[Date "2024.03.09"]
+[Round "1"]
+[White "Ana \"the rook\""]
+[Black "Bruno"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 {The usual} Nc6 3. Bb5 a6 (3... Nf6 4. O-O (4. d3) Nxe4) 4. Ba4
+Nf6 5. O-O $1 Be7 6. Re1!? b5 7. Bb3 d6 8. c3 O-O 9. h3 ; main line
+Nb8 10. d4 Nbd7 1-0
+
+% a line that is skipped
+[Event "Second"]
+[SetUp "1"]
+[FEN "4k3/P7/8/8/8/8/8/4K3 w - - 0 50"]
+
+50.a8=Q+ Kd7 51.Qb7+ *
+
+1. f3 e5 2. g4 Qh4# 0-1
+"#;
+
+    #[test]
+    fn reads_tags_comments_nags_and_variations() {
+        let games = parse_pgn(MULTI_GAME_FILE).unwrap();
+        assert_eq!(games.len(), 3);
+
+        let ruy_lopez = &games[0];
+        assert_eq!(ruy_lopez.tag("White"), Some("Ana \"the rook\""));
+        assert_eq!(ruy_lopez.tag("Round"), Some("1"));
+        assert_eq!(ruy_lopez.result, "1-0");
+        assert_eq!(ruy_lopez.moves.len(), 20);
+        assert_eq!(ruy_lopez.moves[..6], ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]);
+        assert_eq!(ruy_lopez.moves[9], "Be7");
+        assert_eq!(ruy_lopez.moves[10], "Re1");
+
+        assert_eq!(games[1].tag("Event"), Some("Second"));
+        assert_eq!(games[1].moves, ["a8=Q+", "Kd7", "Qb7+"]);
+        assert_eq!(games[1].result, "*");
+
+        assert!(games[2].tags.is_empty());
+        assert_eq!(games[2].result, "0-1");
+    }
+
+    #[test]
+    fn replays_the_main_line() {
+        let games = parse_pgn(MULTI_GAME_FILE).unwrap();
+
+        let chess_match = games[0].replay().unwrap();
+        assert_eq!(chess_match.to_fen(), "r1bq1rk1/2pnbppp/p2p1n2/1p2p3/3PP3/1BP2N1P/PP3PP1/RNBQR1K1 w - - 1 11");
+
+        let chess_match = games[1].replay().unwrap();
+        assert_eq!(chess_match.history().len(), 3);
+
+        let chess_match = games[2].replay().unwrap();
+        assert!(chess_match.check_mate);
+        assert_eq!(chess_match.pgn_result(), games[2].result);
+    }
+
+    #[test]
+    fn exported_games_read_back() {
+        let mut chess_match = ChessMatch::new();
+        chess_match.play(&[("e2", "e4"), ("d7", "d5"), ("e4", "d5"), ("g8", "f6"), ("f1", "b5"), ("c7", "c6")]);
+        let games = parse_pgn(&chess_match.to_pgn(&[("Event", "Round trip")])).unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].tag("Event"), Some("Round trip"));
+        assert_eq!(games[0].replay().unwrap().to_fen(), chess_match.to_fen());
+    }
+
+    #[test]
+    fn replay_errors_name_the_ply() {
+        let illegal = &parse_pgn("1. e4 e5 2. Ke3 *").unwrap()[0];
+        assert_eq!(illegal.replay().err().unwrap().0, "Ply 3 (2. Ke3): 'Ke3' is not a legal move");
+
+        // Both knights can reach d2
+        let ambiguous = &parse_pgn("1. d4 d5 2. Nf3 Nf6 3. Nd2 *").unwrap()[0];
+        let error = ambiguous.replay().err().unwrap().0;
+        assert!(error.starts_with("Ply 5 (3. Nd2): 'Nd2' is ambiguous"), "{}", error);
+
+        let after_mate = &parse_pgn("1. f3 e5 2. g4 Qh4# 3. a3 0-1").unwrap()[0];
+        assert_eq!(after_mate.replay().err().unwrap().0, "Ply 5 (3. a3): the game is already over");
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        assert!(parse_pgn("1. e4 {never closed").is_err());
+        assert!(parse_pgn("1. e4 (1. d4 *").is_err());
+        assert!(parse_pgn("1. e4 ) *").is_err());
+        assert!(parse_pgn("[Event Casual]\n1. e4 *").is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use crate::board::piece::Piece;
+use crate::chess::color::Color;
+use crate::chess::pieces::{
+    archbishop::Archbishop, bishop::Bishop, chancellor::Chancellor, king::King, knight::Knight,
+    pawn::Pawn, queen::Queen, rook::Rook,
+};
+use crate::error::ChessError;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PieceKind {
+    King,
+    Queen,
+    Rook,
+    Bishop,
+    Knight,
+    Pawn,
+    // Capablanca chess: bishop plus knight, and rook plus knight
+    Archbishop,
+    Chancellor,
+    // Fairy piece with this letter in the game's CustomPieces, defined in Betza notation (see betza.rs)
+    Custom(char),
+}
+
+impl PieceKind {
+    // Uppercase letter used by algebraic notation (the pawn has none there, but FEN uses 'P')
+    pub fn letter(self) -> char {
+        match self {
+            PieceKind::King => 'K',
+            PieceKind::Queen => 'Q',
+            PieceKind::Rook => 'R',
+            PieceKind::Bishop => 'B',
+            PieceKind::Knight => 'N',
+            PieceKind::Pawn => 'P',
+            PieceKind::Archbishop => 'A',
+            PieceKind::Chancellor => 'C',
+            PieceKind::Custom(letter) => letter,
+        }
+    }
+
+    // Built-in pieces only; the letters of custom pieces depend on the game (CustomPieces::kind)
+    pub fn from_letter(letter: char) -> Option<Self> {
+        match letter.to_ascii_uppercase() {
+            'K' => Some(PieceKind::King),
+            'Q' => Some(PieceKind::Queen),
+            'R' => Some(PieceKind::Rook),
+            'B' => Some(PieceKind::Bishop),
+            'N' => Some(PieceKind::Knight),
+            'P' => Some(PieceKind::Pawn),
+            'A' => Some(PieceKind::Archbishop),
+            'C' => Some(PieceKind::Chancellor),
+            _ => None,
+        }
+    }
+
+    pub fn is_promotion_choice(self) -> bool {
+        matches!(self, PieceKind::Queen | PieceKind::Rook | PieceKind::Bishop | PieceKind::Knight)
+    }
+
+    pub fn to_piece(self, color: Color) -> Box<dyn Piece + Send + Sync> {
+        match self {
+            PieceKind::King => Box::new(King::new(color)),
+            PieceKind::Queen => Box::new(Queen::new(color)),
+            PieceKind::Rook => Box::new(Rook::new(color)),
+            PieceKind::Bishop => Box::new(Bishop::new(color)),
+            PieceKind::Knight => Box::new(Knight::new(color)),
+            PieceKind::Pawn => Box::new(Pawn::new(color)),
+            PieceKind::Archbishop => Box::new(Archbishop::new(color)),
+            PieceKind::Chancellor => Box::new(Chancellor::new(color)),
+            PieceKind::Custom(_) => unreachable!("Custom pieces are made from the game's table, see ChessMatch::new_piece"),
+        }
+    }
+}
+
+impl FromStr for PieceKind {
+    type Err = ChessError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let mut chars = s.chars();
+        if let (Some(letter), None) = (chars.next(), chars.next()) {
+            return PieceKind::from_letter(letter)
+                .ok_or_else(|| ChessError(format!("Invalid piece letter '{}'.", letter)));
+        }
+        match s.as_str() {
+            "king" => Ok(PieceKind::King),
+            "queen" => Ok(PieceKind::Queen),
+            "rook" => Ok(PieceKind::Rook),
+            "bishop" => Ok(PieceKind::Bishop),
+            "knight" => Ok(PieceKind::Knight),
+            "pawn" => Ok(PieceKind::Pawn),
+            "archbishop" => Ok(PieceKind::Archbishop),
+            "chancellor" => Ok(PieceKind::Chancellor),
+            _ => Err(ChessError(format!("Invalid piece '{}'.", s))),
+        }
+    }
+}
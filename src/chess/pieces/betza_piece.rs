@@ -0,0 +1,87 @@
+use crate::board::piece::Piece;
+use crate::board::position::Position;
+use crate::board::Board;
+use crate::chess::betza::{CustomPiece, MoveMode};
+use crate::chess::{color::Color, piece_kind::PieceKind, ChessMatch};
+use std::fmt;
+use std::sync::Arc;
+
+// A fairy piece that moves as its Betza notation says
+#[derive(Clone)]
+pub struct BetzaPiece {
+    definition: Arc<CustomPiece>,
+    color: Color,
+    move_count: u32,
+    // Came from a promoted pawn, which matters in Crazyhouse
+    promoted: bool,
+}
+
+impl BetzaPiece {
+    pub fn new(definition: Arc<CustomPiece>, color: Color) -> Self {
+        Self { definition, color, move_count: 0, promoted: false }
+    }
+
+    // Squares reached by the leaps whose mode `wanted` accepts. An occupied square ends a
+    // rider's line; it is a target only if it holds an enemy piece and the leap can capture,
+    // and empty squares only if the leap can move without capturing.
+    fn targets(&self, board: &Board, pos: Position, wanted: impl Fn(MoveMode) -> bool, attacks: bool) -> Vec<Vec<bool>> {
+        let mut mat = vec![vec![false; board.cols]; board.rows];
+        // Forward is up the board for White and down for Black, and Black's right is White's left
+        let flip: isize = if self.color == Color::White { 1 } else { -1 };
+
+        for leap in self.definition.betza.leaps.iter().filter(|leap| wanted(leap.mode)) {
+            if leap.initial_only && self.move_count > 0 {
+                continue;
+            }
+            let (dr, dc) = (-leap.forward * flip, leap.right * flip);
+            let (mut row, mut col) = (pos.row as isize, pos.col as isize);
+            for _ in 0..leap.range.unwrap_or(usize::MAX) {
+                row += dr;
+                col += dc;
+                if row < 0 || row >= board.rows as isize || col < 0 || col >= board.cols as isize {
+                    break;
+                }
+                let p = Position::new(row as usize, col as usize);
+                if !board.there_is_a_piece(p) {
+                    mat[p.row][p.col] |= attacks || leap.mode != MoveMode::CaptureOnly;
+                } else {
+                    if leap.mode != MoveMode::MoveOnly && self.is_there_opponent_piece(p, board) {
+                        mat[p.row][p.col] = true;
+                    }
+                    break;
+                }
+            }
+        }
+        mat
+    }
+}
+
+impl fmt::Display for BetzaPiece {
+    // Drawn with its letter, like the Capablanca pieces
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.color {
+            Color::White => write!(f, "{}", self.definition.letter),
+            Color::Black => write!(f, "{}", self.definition.letter.to_ascii_lowercase()),
+        }
+    }
+}
+
+impl Piece for BetzaPiece {
+    fn color(&self) -> Color { self.color }
+    fn kind(&self) -> PieceKind { PieceKind::Custom(self.definition.letter) }
+    fn move_count(&self) -> u32 { self.move_count }
+    fn increase_move_count(&mut self) { self.move_count += 1; }
+    fn decrease_move_count(&mut self) { self.move_count -= 1; }
+    fn box_clone(&self) -> Box<dyn Piece + Send + Sync> { Box::new(self.clone()) }
+    fn is_promoted(&self) -> bool { self.promoted }
+    fn set_promoted(&mut self) { self.promoted = true; }
+
+    fn possible_moves(&self, board: &Board, pos: Position, _: &ChessMatch) -> Vec<Vec<bool>> {
+        self.targets(board, pos, |_| true, false)
+    }
+
+    // Move-only leaps don't threaten anything, and capture-only ones threaten even empty squares
+    fn attacks(&self, board: &Board, pos: Position, _: &ChessMatch) -> Vec<Vec<bool>> {
+        self.targets(board, pos, |mode| mode != MoveMode::MoveOnly, true)
+    }
+}
@@ -0,0 +1,41 @@
+use crate::board::piece::Piece;
+use crate::board::position::Position;
+use crate::board::Board;
+use crate::chess::pieces::movement;
+use crate::chess::{color::Color, piece_kind::PieceKind, ChessMatch};
+use std::fmt;
+
+// Moves both as a rook and as a knight (Capablanca chess)
+#[derive(Clone)]
+pub struct Chancellor {
+    color: Color,
+    move_count: u32,
+    // Came from a promoted pawn, which matters in Crazyhouse
+    promoted: bool,
+}
+
+impl Chancellor { pub fn new(color: Color) -> Self { Self { color, move_count: 0, promoted: false } } }
+impl fmt::Display for Chancellor {
+    // There is no chess glyph for it, so it is drawn with its letter
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.color {
+            Color::White => write!(f, "C"),
+            Color::Black => write!(f, "c"),
+        }
+    }
+}
+
+impl Piece for Chancellor {
+    fn color(&self) -> Color { self.color }
+    fn kind(&self) -> PieceKind { PieceKind::Chancellor }
+    fn move_count(&self) -> u32 { self.move_count }
+    fn increase_move_count(&mut self) { self.move_count += 1; }
+    fn decrease_move_count(&mut self) { self.move_count -= 1; }
+    fn box_clone(&self) -> Box<dyn Piece + Send + Sync> { Box::new(self.clone()) }
+    fn is_promoted(&self) -> bool { self.promoted }
+    fn set_promoted(&mut self) { self.promoted = true; }
+
+    fn possible_moves(&self, board: &Board, pos: Position, _: &ChessMatch) -> Vec<Vec<bool>> {
+        movement::board_moves(self, board, pos)
+    }
+}
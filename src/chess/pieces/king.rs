@@ -1,7 +1,8 @@
 use crate::board::piece::Piece;
 use crate::board::position::Position;
 use crate::board::Board;
-use crate::chess::{color::Color, ChessMatch};
+use crate::chess::pieces::movement;
+use crate::chess::{color::Color, piece_kind::PieceKind, ChessMatch};
 use std::fmt;
 
 #[derive(Clone)]
@@ -10,7 +11,73 @@ pub struct King {
     move_count: u32,
 }
 
-impl King { pub fn new(color: Color) -> Self { Self { color, move_count: 0 } } }
+impl King {
+    pub fn new(color: Color) -> Self { Self { color, move_count: 0 } }
+
+    fn step_moves(&self, board: &Board, pos: Position) -> Vec<Vec<bool>> {
+        movement::board_moves(self, board, pos)
+    }
+
+    // The squares between the king, the rook and their destinations must be empty apart from
+    // the two of them, and the king may not start on, pass through or land on an attacked square
+    // (unless the variant has no check).
+    fn can_castle(&self, board: &Board, pos: Position, rook_pos: Position, chess_match: &ChessMatch) -> bool {
+        let (king_target, rook_target) = castling_destinations(board.cols, pos.row, rook_pos.col > pos.col);
+
+        let cols = [pos.col, rook_pos.col, king_target.col, rook_target.col];
+        let (first, last) = (*cols.iter().min().unwrap(), *cols.iter().max().unwrap());
+        if (first..=last).any(|c| c != pos.col && c != rook_pos.col && board.there_is_a_piece(Position::new(pos.row, c))) {
+            return false;
+        }
+
+        if !chess_match.variant().has_check() {
+            return true;
+        }
+        let opponent = self.color.opponent();
+        (pos.col.min(king_target.col)..=pos.col.max(king_target.col))
+            .all(|c| !chess_match.is_square_attacked(Position::new(pos.row, c), opponent))
+    }
+}
+
+// Squares the king and the rook land on. Kingside castling always ends with the king on the
+// g-file and the rook on the f-file, queenside with the king on c and the rook on d, wherever
+// they started (Chess960). Boards wider than 8 files keep the king on the second file from the edge.
+pub fn castling_destinations(cols: usize, row: usize, kingside: bool) -> (Position, Position) {
+    if kingside {
+        (Position::new(row, cols - 2), Position::new(row, cols - 3))
+    } else {
+        (Position::new(row, 2), Position::new(row, 3))
+    }
+}
+
+// The rook the unmoved king on king_pos can castle with on that side: an unmoved rook of the
+// king's color on the same rank, the outermost one if there are several
+pub fn castling_rook(board: &Board, king_pos: Position, kingside: bool) -> Option<Position> {
+    let king = board.piece(king_pos).filter(|p| p.kind() == PieceKind::King && p.move_count() == 0)?;
+    // Castling is only defined from 8 files up
+    if board.cols < 8 {
+        return None;
+    }
+    let is_castling_rook = |pos: &Position| {
+        board
+            .piece(*pos)
+            .is_some_and(|p| p.kind() == PieceKind::Rook && p.color() == king.color() && p.move_count() == 0)
+    };
+    let row = king_pos.row;
+    if kingside {
+        (king_pos.col + 1..board.cols).rev().map(|c| Position::new(row, c)).find(is_castling_rook)
+    } else {
+        (0..king_pos.col).map(|c| Position::new(row, c)).find(is_castling_rook)
+    }
+}
+
+// Target of the castling move with the rook on rook_pos: the king's destination when the king
+// moves two files or more, otherwise the rook's square, so that it can't be mistaken for a king step
+pub fn castling_target(cols: usize, king_pos: Position, rook_pos: Position) -> Position {
+    let (king_target, _) = castling_destinations(cols, king_pos.row, rook_pos.col > king_pos.col);
+    if king_target.col.abs_diff(king_pos.col) >= 2 { king_target } else { rook_pos }
+}
+
 impl fmt::Display for King {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.color {
@@ -21,55 +88,232 @@ impl fmt::Display for King {
 }
 impl Piece for King {
     fn color(&self) -> Color { self.color }
+    fn kind(&self) -> PieceKind { PieceKind::King }
     fn move_count(&self) -> u32 { self.move_count }
     fn increase_move_count(&mut self) { self.move_count += 1; }
     fn decrease_move_count(&mut self) { self.move_count -= 1; }
     fn box_clone(&self) -> Box<dyn Piece + Send + Sync> { Box::new(self.clone()) }
 
     fn possible_moves(&self, board: &Board, pos: Position, chess_match: &ChessMatch) -> Vec<Vec<bool>> {
-        let mut mat = vec![vec![false; board.cols]; board.rows];
-        let deltas = [
-            (-1, 0), (1, 0), (0, -1), (0, 1),
-            (-1, -1), (-1, 1), (1, -1), (1, 1),
-        ];
-
-        for (dr, dc) in deltas {
-            let new_row = pos.row as isize + dr;
-            let new_col = pos.col as isize + dc;
-             if new_row >= 0 && new_row < board.rows as isize && new_col >= 0 && new_col < board.cols as isize {
-                let p = Position::new(new_row as usize, new_col as usize);
-                if !board.there_is_a_piece(p) || self.is_there_opponent_piece(p, board) {
-                    mat[p.row][p.col] = true;
-                }
-            }
-        }
+        let mut mat = self.step_moves(board, pos);
 
         // Castling
-        if self.move_count == 0 && !chess_match.check {
-            // Kingside
-            let rook_pos1 = Position::new(pos.row, pos.col + 3);
-            if let Some(piece) = board.piece(rook_pos1) {
-                if piece.move_count() == 0 && piece.to_string() == "R" {
-                    let p1 = Position::new(pos.row, pos.col + 1);
-                    let p2 = Position::new(pos.row, pos.col + 2);
-                    if !board.there_is_a_piece(p1) && !board.there_is_a_piece(p2) {
-                        mat[pos.row][pos.col + 2] = true;
-                    }
-                }
-            }
-            // Queenside
-            let rook_pos2 = Position::new(pos.row, pos.col - 4);
-            if let Some(piece) = board.piece(rook_pos2) {
-                if piece.move_count() == 0 && piece.to_string() == "R" {
-                    let p1 = Position::new(pos.row, pos.col - 1);
-                    let p2 = Position::new(pos.row, pos.col - 2);
-                    let p3 = Position::new(pos.row, pos.col - 3);
-                     if !board.there_is_a_piece(p1) && !board.there_is_a_piece(p2) && !board.there_is_a_piece(p3) {
-                        mat[pos.row][pos.col - 2] = true;
-                    }
+        if !chess_match.variant().has_castling() {
+            return mat;
+        }
+        for kingside in [true, false] {
+            if let Some(rook_pos) = castling_rook(board, pos, kingside) {
+                if self.can_castle(board, pos, rook_pos, chess_match) {
+                    let target = castling_target(board.cols, pos, rook_pos);
+                    mat[target.row][target.col] = true;
                 }
             }
         }
         mat
     }
-}
\ No newline at end of file
+
+    fn attacks(&self, board: &Board, pos: Position, _: &ChessMatch) -> Vec<Vec<bool>> {
+        self.step_moves(board, pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::square;
+
+    // White king and rooks on their home squares, black king on e8, plus the given extra pieces
+    fn castling_setup(extra: &[(&str, PieceKind, Color)]) -> ChessMatch {
+        let mut pieces = vec![
+            ("e1", PieceKind::King, Color::White),
+            ("a1", PieceKind::Rook, Color::White),
+            ("h1", PieceKind::Rook, Color::White),
+            ("e8", PieceKind::King, Color::Black),
+        ];
+        pieces.extend_from_slice(extra);
+        ChessMatch::with_pieces(&pieces)
+    }
+
+    fn place(chess_match: &mut ChessMatch, pos: Position, kind: PieceKind, color: Color) {
+        chess_match.board.place_piece(kind.to_piece(color), pos).unwrap();
+        chess_match.pieces_on_board.insert(pos);
+    }
+
+    fn can_move(chess_match: &ChessMatch, from: &str, to: &str) -> bool {
+        let (from, to) = (square(from), square(to));
+        chess_match.board.piece(from).unwrap().possible_moves(&chess_match.board, from, chess_match)[to.row][to.col]
+    }
+
+    #[test]
+    fn castles_on_both_sides_when_path_is_clear_and_safe() {
+        let chess_match = castling_setup(&[]);
+        assert!(can_move(&chess_match, "e1", "g1"));
+        assert!(can_move(&chess_match, "e1", "c1"));
+    }
+
+    #[test]
+    fn kingside_castling_moves_the_rook() {
+        let mut chess_match = castling_setup(&[]);
+        chess_match.perform_chess_move("e1".parse().unwrap(), "g1".parse().unwrap(), None).unwrap();
+        assert_eq!(chess_match.board.piece(square("g1")).unwrap().kind(), PieceKind::King);
+        assert_eq!(chess_match.board.piece(square("f1")).unwrap().kind(), PieceKind::Rook);
+        assert!(!chess_match.board.there_is_a_piece(square("h1")));
+    }
+
+    #[test]
+    fn queenside_castling_moves_the_rook() {
+        let mut chess_match = castling_setup(&[]);
+        chess_match.perform_chess_move("e1".parse().unwrap(), "c1".parse().unwrap(), None).unwrap();
+        assert_eq!(chess_match.board.piece(square("c1")).unwrap().kind(), PieceKind::King);
+        assert_eq!(chess_match.board.piece(square("d1")).unwrap().kind(), PieceKind::Rook);
+        assert!(!chess_match.board.there_is_a_piece(square("a1")));
+    }
+
+    #[test]
+    fn cannot_castle_out_of_check() {
+        let chess_match = castling_setup(&[("e4", PieceKind::Rook, Color::Black)]);
+        assert!(!can_move(&chess_match, "e1", "g1"));
+        assert!(!can_move(&chess_match, "e1", "c1"));
+    }
+
+    #[test]
+    fn cannot_castle_through_an_attacked_square() {
+        let chess_match = castling_setup(&[("f5", PieceKind::Rook, Color::Black), ("d5", PieceKind::Rook, Color::Black)]);
+        assert!(!can_move(&chess_match, "e1", "g1"));
+        assert!(!can_move(&chess_match, "e1", "c1"));
+    }
+
+    #[test]
+    fn cannot_castle_into_an_attacked_square() {
+        let chess_match = castling_setup(&[("g5", PieceKind::Rook, Color::Black), ("c5", PieceKind::Rook, Color::Black)]);
+        assert!(!can_move(&chess_match, "e1", "g1"));
+        assert!(!can_move(&chess_match, "e1", "c1"));
+    }
+
+    #[test]
+    fn queenside_castling_ignores_an_attack_on_the_rook_path() {
+        let chess_match = castling_setup(&[("b5", PieceKind::Rook, Color::Black)]);
+        assert!(can_move(&chess_match, "e1", "c1"));
+    }
+
+    #[test]
+    fn pawn_attacks_block_castling() {
+        let chess_match = castling_setup(&[("g2", PieceKind::Pawn, Color::Black)]);
+        assert!(!can_move(&chess_match, "e1", "g1"));
+        assert!(can_move(&chess_match, "e1", "c1"));
+    }
+
+    #[test]
+    fn cannot_castle_through_pieces() {
+        let chess_match = castling_setup(&[("g1", PieceKind::Knight, Color::White), ("b1", PieceKind::Knight, Color::White)]);
+        assert!(!can_move(&chess_match, "e1", "g1"));
+        assert!(!can_move(&chess_match, "e1", "c1"));
+    }
+
+    #[test]
+    fn cannot_castle_after_the_king_moved() {
+        let mut chess_match = castling_setup(&[]);
+        chess_match.board.remove_piece(square("e1"));
+        let mut king = PieceKind::King.to_piece(Color::White);
+        king.increase_move_count();
+        chess_match.board.place_piece(king, square("e1")).unwrap();
+        assert!(!can_move(&chess_match, "e1", "g1"));
+        assert!(!can_move(&chess_match, "e1", "c1"));
+    }
+
+    #[test]
+    fn cannot_castle_with_a_moved_rook() {
+        let mut chess_match = castling_setup(&[]);
+        let mut rook = chess_match.board.remove_piece(square("h1")).unwrap();
+        rook.increase_move_count();
+        chess_match.board.place_piece(rook, square("h1")).unwrap();
+        assert!(!can_move(&chess_match, "e1", "g1"));
+        assert!(can_move(&chess_match, "e1", "c1"));
+    }
+
+    #[test]
+    fn castling_partner_must_be_an_own_rook_on_its_home_square() {
+        let mut chess_match = castling_setup(&[]);
+        chess_match.board.remove_piece(square("h1"));
+        chess_match.pieces_on_board.remove(&square("h1"));
+        place(&mut chess_match, square("h1"), PieceKind::Rook, Color::Black);
+        chess_match.board.remove_piece(square("a1"));
+        chess_match.pieces_on_board.remove(&square("a1"));
+        place(&mut chess_match, square("a1"), PieceKind::Queen, Color::White);
+        assert!(!can_move(&chess_match, "e1", "g1"));
+        assert!(!can_move(&chess_match, "e1", "c1"));
+    }
+
+    #[test]
+    fn castling_does_not_panic_on_narrow_boards() {
+        let mut chess_match = ChessMatch::with_board(Board::new(2, 3).unwrap());
+        place(&mut chess_match, Position::new(1, 0), PieceKind::Rook, Color::White);
+        place(&mut chess_match, Position::new(1, 1), PieceKind::King, Color::White);
+        place(&mut chess_match, Position::new(1, 2), PieceKind::Rook, Color::White);
+        place(&mut chess_match, Position::new(0, 1), PieceKind::King, Color::Black);
+        let king_pos = Position::new(1, 1);
+        let moves = chess_match.board.piece(king_pos).unwrap().possible_moves(&chess_match.board, king_pos, &chess_match);
+        assert!(!moves[1][0] && !moves[1][2]);
+    }
+
+    fn castle(chess_match: &mut ChessMatch, from: &str, to: &str) {
+        chess_match.perform_chess_move(from.parse().unwrap(), to.parse().unwrap(), None).unwrap();
+    }
+
+    fn kind_on(chess_match: &ChessMatch, s: &str) -> Option<PieceKind> {
+        chess_match.board.piece(square(s)).map(|p| p.kind())
+    }
+
+    #[test]
+    fn castling_can_be_entered_as_the_king_taking_its_rook() {
+        let mut chess_match = castling_setup(&[]);
+        castle(&mut chess_match, "e1", "h1");
+        assert_eq!(kind_on(&chess_match, "g1"), Some(PieceKind::King));
+        assert_eq!(kind_on(&chess_match, "f1"), Some(PieceKind::Rook));
+        assert_eq!(chess_match.history().last().unwrap().mv.to_coordinates(8), "e1g1");
+    }
+
+    #[test]
+    fn chess960_castling_lands_on_the_standard_files() {
+        // King on b1 castling queenside moves one file right; the move is named by the rook's square
+        let mut chess_match = ChessMatch::with_pieces(&[
+            ("b1", PieceKind::King, Color::White),
+            ("a1", PieceKind::Rook, Color::White),
+            ("h1", PieceKind::Rook, Color::White),
+            ("e8", PieceKind::King, Color::Black),
+        ]);
+        assert!(can_move(&chess_match, "b1", "a1"));
+        assert!(can_move(&chess_match, "b1", "g1"));
+        castle(&mut chess_match, "b1", "a1");
+        assert_eq!(kind_on(&chess_match, "c1"), Some(PieceKind::King));
+        assert_eq!(kind_on(&chess_match, "d1"), Some(PieceKind::Rook));
+        assert_eq!(kind_on(&chess_match, "a1"), None);
+    }
+
+    #[test]
+    fn chess960_castling_where_king_and_rook_swap_squares() {
+        let mut chess_match = ChessMatch::with_pieces(&[
+            ("f1", PieceKind::King, Color::White),
+            ("g1", PieceKind::Rook, Color::White),
+            ("e8", PieceKind::King, Color::Black),
+        ]);
+        castle(&mut chess_match, "f1", "g1");
+        assert_eq!(kind_on(&chess_match, "g1"), Some(PieceKind::King));
+        assert_eq!(kind_on(&chess_match, "f1"), Some(PieceKind::Rook));
+        assert!(chess_match.undo().is_ok());
+        assert_eq!(kind_on(&chess_match, "f1"), Some(PieceKind::King));
+        assert_eq!(kind_on(&chess_match, "g1"), Some(PieceKind::Rook));
+    }
+
+    #[test]
+    fn chess960_castling_needs_the_destination_squares_free() {
+        // The king on g1 doesn't move, but the rook's destination f1 is taken by a bishop
+        let chess_match = ChessMatch::with_pieces(&[
+            ("g1", PieceKind::King, Color::White),
+            ("h1", PieceKind::Rook, Color::White),
+            ("f1", PieceKind::Bishop, Color::White),
+            ("e8", PieceKind::King, Color::Black),
+        ]);
+        assert!(!can_move(&chess_match, "g1", "h1"));
+    }
+}
@@ -1,6 +1,10 @@
+pub mod archbishop;
+pub mod betza_piece;
 pub mod bishop;
+pub mod chancellor;
 pub mod king;
 pub mod knight;
+pub mod movement;
 pub mod pawn;
 pub mod queen;
 pub mod rook;
\ No newline at end of file
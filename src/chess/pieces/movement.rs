@@ -0,0 +1,128 @@
+// How the pieces other than the pawn move: the lines they slide along and the squares they
+// leap to. These moves are the same in every direction, so a piece reaches a square exactly
+// when a piece of its kind on that square would reach it back, which is what attack
+// detection relies on.
+//
+// The board is given as two closures, which squares exist and which are occupied, so the
+// rectangular boards of ChessMatch and the cross-shaped board of four-player chess share
+// the same code.
+use crate::board::piece::Piece;
+use crate::board::position::Position;
+use crate::board::Board;
+use crate::chess::piece_kind::PieceKind;
+
+// Offsets are (rows, cols)
+pub const ORTHOGONAL: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+pub const DIAGONAL: [(isize, isize); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+pub const ALL_DIRECTIONS: [(isize, isize); 8] = [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (-1, 1), (1, -1), (1, 1)];
+pub const KNIGHT_JUMPS: [(isize, isize); 8] = [(-1, -2), (-2, -1), (-2, 1), (-1, 2), (1, 2), (2, 1), (2, -1), (1, -2)];
+
+// The pieces whose moves are all in this module
+const SYMMETRIC_KINDS: [PieceKind; 7] = [
+    PieceKind::King,
+    PieceKind::Queen,
+    PieceKind::Rook,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+    PieceKind::Archbishop,
+    PieceKind::Chancellor,
+];
+
+// Directions the piece slides in until it's blocked
+fn slides(kind: PieceKind) -> &'static [(isize, isize)] {
+    match kind {
+        PieceKind::Queen => &ALL_DIRECTIONS,
+        PieceKind::Rook | PieceKind::Chancellor => &ORTHOGONAL,
+        PieceKind::Bishop | PieceKind::Archbishop => &DIAGONAL,
+        _ => &[],
+    }
+}
+
+// Single steps and jumps, which nothing in between can block
+fn leaps(kind: PieceKind) -> &'static [(isize, isize)] {
+    match kind {
+        PieceKind::King => &ALL_DIRECTIONS,
+        PieceKind::Knight | PieceKind::Archbishop | PieceKind::Chancellor => &KNIGHT_JUMPS,
+        _ => &[],
+    }
+}
+
+pub fn offset(pos: Position, (dr, dc): (isize, isize), exists: impl Fn(Position) -> bool) -> Option<Position> {
+    let row = pos.row.checked_add_signed(dr)?;
+    let col = pos.col.checked_add_signed(dc)?;
+    Some(Position::new(row, col)).filter(|&p| exists(p))
+}
+
+// Squares a piece of `kind` on `pos` reaches: the empty ones and the first occupied square of
+// each line, whoever stands there. Pawns and custom pieces reach nothing here.
+pub fn reach(kind: PieceKind, pos: Position, exists: impl Fn(Position) -> bool, occupied: impl Fn(Position) -> bool) -> Vec<Position> {
+    let mut squares = leaps(kind).iter().filter_map(|&step| offset(pos, step, &exists)).collect::<Vec<_>>();
+    for &direction in slides(kind) {
+        let mut p = pos;
+        while let Some(next) = offset(p, direction, &exists) {
+            squares.push(next);
+            if occupied(next) {
+                break;
+            }
+            p = next;
+        }
+    }
+    squares
+}
+
+// Whether a piece other than a pawn attacks `pos`, `is_attacker` telling whether the square
+// holds an attacking piece of that kind
+pub fn is_attacked(
+    pos: Position,
+    exists: impl Fn(Position) -> bool,
+    occupied: impl Fn(Position) -> bool,
+    is_attacker: impl Fn(Position, PieceKind) -> bool,
+) -> bool {
+    SYMMETRIC_KINDS
+        .iter()
+        .any(|&kind| reach(kind, pos, &exists, &occupied).into_iter().any(|p| is_attacker(p, kind)))
+}
+
+// Move matrix of `piece` on a rectangular board: the squares it reaches, except those holding
+// a piece of its own color
+pub fn board_moves(piece: &dyn Piece, board: &Board, pos: Position) -> Vec<Vec<bool>> {
+    let mut mat = vec![vec![false; board.cols]; board.rows];
+    for p in reach(piece.kind(), pos, |p| board.position_exists(p), |p| board.there_is_a_piece(p)) {
+        if !board.there_is_a_piece(p) || piece.is_there_opponent_piece(p, board) {
+            mat[p.row][p.col] = true;
+        }
+    }
+    mat
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_stop_at_the_first_piece() {
+        // A rook on a1 of an 8x8 board with a piece on a3
+        let exists = |p: Position| p.row < 8 && p.col < 8;
+        let blocker = Position::new(5, 0);
+        let mut squares = reach(PieceKind::Rook, Position::new(7, 0), exists, |p| p == blocker);
+        squares.sort_by_key(|p| (p.row, p.col));
+        assert_eq!(squares.len(), 2 + 7);
+        assert_eq!(squares[0], blocker);
+
+        // The chancellor reaches the knight squares as well
+        assert_eq!(reach(PieceKind::Chancellor, Position::new(7, 0), exists, |_| false).len(), 14 + 2);
+        assert!(reach(PieceKind::Pawn, Position::new(6, 0), exists, |_| false).is_empty());
+    }
+
+    #[test]
+    fn attackers_are_found_from_the_target() {
+        let exists = |p: Position| p.row < 8 && p.col < 8;
+        let knight = Position::new(5, 5);
+        let bishop = Position::new(0, 0);
+        let occupied = |p: Position| p == knight || p == bishop;
+        let attacker = |p: Position, kind: PieceKind| (p == knight && kind == PieceKind::Knight) || (p == bishop && kind == PieceKind::Bishop);
+        assert!(is_attacked(Position::new(7, 6), exists, occupied, attacker));
+        assert!(is_attacked(Position::new(3, 3), exists, occupied, attacker));
+        assert!(!is_attacked(Position::new(3, 5), exists, occupied, attacker));
+    }
+}
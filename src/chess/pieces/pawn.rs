@@ -1,7 +1,7 @@
 use crate::board::piece::Piece;
 use crate::board::position::Position;
 use crate::board::Board;
-use crate::chess::{color::Color, ChessMatch};
+use crate::chess::{color::Color, piece_kind::PieceKind, ChessMatch};
 use std::fmt;
 
 #[derive(Clone)]
@@ -22,6 +22,7 @@ impl fmt::Display for Pawn {
 
 impl Piece for Pawn {
     fn color(&self) -> Color { self.color }
+    fn kind(&self) -> PieceKind { PieceKind::Pawn }
     fn move_count(&self) -> u32 { self.move_count }
     fn increase_move_count(&mut self) { self.move_count += 1; }
     fn decrease_move_count(&mut self) { self.move_count -= 1; }
@@ -58,7 +59,7 @@ impl Piece for Pawn {
 
         // En Passant
         if let Some(en_passant_pos) = chess_match.get_en_passant_vulnerable() {
-            if (pos.row == 3 && self.color == Color::White) || (pos.row == 4 && self.color == Color::Black) {
+            if (pos.row == 3 && self.color == Color::White) || (pos.row == board.rows - 4 && self.color == Color::Black) {
                  for &dc in &[-1, 1] {
                     let adj_col = pos.col as isize + dc;
                     if adj_col >= 0 && adj_col < board.cols as isize {
@@ -74,4 +75,78 @@ impl Piece for Pawn {
 
         mat
     }
-}
\ No newline at end of file
+
+    // A pawn only threatens the two squares diagonally ahead of it
+    fn attacks(&self, board: &Board, pos: Position, _: &ChessMatch) -> Vec<Vec<bool>> {
+        let mut mat = vec![vec![false; board.cols]; board.rows];
+        let dir: isize = if self.color == Color::White { -1 } else { 1 };
+        let row = pos.row as isize + dir;
+        if row < 0 || row >= board.rows as isize {
+            return mat;
+        }
+        for &dc in &[-1, 1] {
+            let col = pos.col as isize + dc;
+            if col >= 0 && col < board.cols as isize {
+                mat[row as usize][col as usize] = true;
+            }
+        }
+        mat
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::position::Position;
+    use crate::chess::piece_kind::PieceKind;
+    use crate::chess::ChessMatch;
+
+    fn symbol(chess_match: &ChessMatch, row: usize, col: usize) -> String {
+        chess_match.board.piece(Position::new(row, col)).unwrap().to_string()
+    }
+
+    // White's a-pawn reaches a7, from where it can take the knight on b8
+    fn pawn_on_the_seventh() -> ChessMatch {
+        let mut chess_match = ChessMatch::new();
+        chess_match.play(&[("a2", "a4"), ("b7", "b5"), ("a4", "b5"), ("a7", "a6"), ("b5", "a6"), ("g7", "g6"), ("a6", "a7"), ("g6", "g5")]);
+        chess_match
+    }
+
+    // The same pawn, with White about to be mated by Qh4
+    fn pawn_on_the_seventh_before_mate() -> ChessMatch {
+        let mut chess_match = ChessMatch::new();
+        chess_match.play(&[("a2", "a4"), ("b7", "b5"), ("a4", "b5"), ("a7", "a6"), ("b5", "a6"), ("e7", "e5"), ("a6", "a7"), ("h7", "h6"), ("f2", "f3"), ("h6", "h5"), ("g2", "g4")]);
+        chess_match
+    }
+
+    #[test]
+    fn pawn_promotes_to_the_chosen_piece() {
+        let mut chess_match = pawn_on_the_seventh();
+        chess_match.perform_chess_move("a7".parse().unwrap(), "b8".parse().unwrap(), Some(PieceKind::Rook)).unwrap();
+        assert_eq!(symbol(&chess_match, 0, 1), "♖");
+    }
+
+    #[test]
+    fn pawn_promotes_to_a_queen_by_default() {
+        let mut chess_match = pawn_on_the_seventh();
+        chess_match.perform_chess_move("a7".parse().unwrap(), "b8".parse().unwrap(), None).unwrap();
+        assert_eq!(symbol(&chess_match, 0, 1), "♕");
+    }
+
+    #[test]
+    fn promotion_choice_is_checked() {
+        let mut chess_match = pawn_on_the_seventh();
+        assert!(chess_match.perform_chess_move("a7".parse().unwrap(), "b8".parse().unwrap(), Some(PieceKind::King)).is_err());
+        assert!(chess_match.perform_chess_move("b1".parse().unwrap(), "c3".parse().unwrap(), Some(PieceKind::Queen)).is_err());
+        assert_eq!(symbol(&chess_match, 1, 0), "♙");
+    }
+
+    #[test]
+    fn undoing_a_promotion_restores_the_pawn() {
+        let mut chess_match = pawn_on_the_seventh_before_mate();
+        // Checkmate is only found after trying every white move, a7xb8 among them
+        chess_match.play(&[("d8", "h4")]);
+        assert!(chess_match.check_mate);
+        assert_eq!(symbol(&chess_match, 1, 0), "♙");
+        assert_eq!(symbol(&chess_match, 0, 1), "♞");
+    }
+}
@@ -0,0 +1,303 @@
+// Standard Algebraic Notation (SAN), as used in PGN: "Nf3", "exd5", "O-O", "e8=Q+",
+// and "N@f3" for a Crazyhouse drop.
+use crate::board::position::Position;
+use crate::chess::betza::CustomPieces;
+use crate::chess::chess_move::Move;
+use crate::chess::chess_position::ChessPosition;
+use crate::chess::piece_kind::PieceKind;
+use crate::chess::ChessMatch;
+use crate::error::ChessError;
+
+impl ChessMatch {
+    // Finds the legal move of the player to move written in SAN. Check and annotation
+    // suffixes are optional, and castling may be written with zeros.
+    pub fn parse_san(&mut self, san: &str) -> Result<Move, ChessError> {
+        let text = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        let legal_moves = self.legal_moves();
+        let (rows, cols) = (self.board.rows, self.board.cols);
+
+        let candidates = if text == "O-O" || text == "0-0" || text == "O-O-O" || text == "0-0-0" {
+            let kingside = text.len() == 3;
+            legal_moves
+                .into_iter()
+                .filter(|mv| mv.is_castling() && mv.is_kingside_castling() == kingside)
+                .collect::<Vec<_>>()
+        } else if let Some((piece, square)) = text.split_once('@') {
+            // Crazyhouse drop: "N@f3", and "P@e4" or just "@e4" for a pawn
+            let kind = match piece.chars().collect::<Vec<_>>().as_slice() {
+                [] => Some(PieceKind::Pawn),
+                [letter] if letter.is_ascii_uppercase() => self.custom_pieces.kind(*letter),
+                _ => None,
+            };
+            let target = square.parse::<ChessPosition>().ok().and_then(|square| square.to_position(rows, cols).ok());
+            let (Some(kind), Some(target)) = (kind, target) else {
+                return Err(ChessError(format!("'{}' is not a valid SAN move", san)));
+            };
+            legal_moves.into_iter().filter(|mv| mv.is_drop && mv.piece == kind && mv.target == target).collect()
+        } else {
+            let pattern = SanPattern::parse(text, rows, cols, &self.custom_pieces).ok_or_else(|| ChessError(format!("'{}' is not a valid SAN move", san)))?;
+            legal_moves.into_iter().filter(|mv| pattern.matches(mv, rows)).collect()
+        };
+
+        match candidates.as_slice() {
+            [mv] => Ok(*mv),
+            [] => Err(ChessError(format!("'{}' is not a legal move", san))),
+            _ => {
+                let options = candidates.iter().map(|mv| mv.to_coordinates(rows)).collect::<Vec<_>>().join(", ");
+                Err(ChessError(format!("'{}' is ambiguous: it could be {}", san, options)))
+            }
+        }
+    }
+
+    // Plays a move written in SAN, e.g. "Nf3" or "exd8=Q+"
+    pub fn perform_san_move(&mut self, san: &str) -> Result<Move, ChessError> {
+        let mv = self.parse_san(san)?;
+        let rows = self.board.rows;
+        if mv.is_drop {
+            return self.perform_drop(mv.piece, ChessPosition::from_position(mv.target, rows));
+        }
+        self.perform_chess_move(
+            ChessPosition::from_position(mv.source, rows),
+            ChessPosition::from_position(mv.target, rows),
+            mv.promotion,
+        )
+    }
+
+    // SAN of a legal move of the player to move, including the check or mate suffix
+    pub fn san(&mut self, mv: &Move) -> String {
+        let mut san = self.san_without_suffix(mv);
+
+        let color = self.current_player;
+        let en_passant_vulnerable = self.en_passant_vulnerable;
+        let captured_piece = self.make_move(mv);
+        self.en_passant_vulnerable = Self::double_step(mv);
+        if self.test_check(color.opponent()) {
+            san.push(if self.has_legal_moves(color.opponent()) { '+' } else { '#' });
+        }
+        self.en_passant_vulnerable = en_passant_vulnerable;
+        self.undo_move(mv, captured_piece);
+
+        san
+    }
+
+    // SAN without "+" or "#": those depend on the position after the move, which
+    // apply_move works out anyway
+    pub(super) fn san_without_suffix(&mut self, mv: &Move) -> String {
+        if mv.is_castling() {
+            return if mv.is_kingside_castling() { "O-O" } else { "O-O-O" }.to_string();
+        }
+        let rows = self.board.rows;
+        if mv.is_drop {
+            return mv.to_coordinates(rows);
+        }
+
+        let mut san = String::new();
+        if mv.piece == PieceKind::Pawn {
+            if mv.is_capture() {
+                san.push(file(mv.source, rows));
+            }
+        } else {
+            san.push(mv.piece.letter());
+            san.push_str(&self.disambiguation(mv));
+        }
+        if mv.is_capture() {
+            san.push('x');
+        }
+        san.push_str(&ChessPosition::from_position(mv.target, rows).to_string());
+        if let Some(promotion) = mv.promotion {
+            san.push('=');
+            san.push(promotion.letter());
+        }
+        san
+    }
+
+    // File, rank or both of the source square, when another piece of the same kind
+    // could also legally move to the target
+    fn disambiguation(&mut self, mv: &Move) -> String {
+        let color = self.current_player;
+        let rows = self.board.rows;
+        let rivals = self
+            .pseudo_legal_moves(color)
+            .into_iter()
+            .filter(|other| other.piece == mv.piece && other.target == mv.target && other.source != mv.source && !other.is_drop)
+            .filter(|other| self.is_legal(other))
+            .map(|other| other.source)
+            .collect::<Vec<_>>();
+
+        if rivals.is_empty() {
+            String::new()
+        } else if rivals.iter().all(|pos| pos.col != mv.source.col) {
+            file(mv.source, rows).to_string()
+        } else if rivals.iter().all(|pos| pos.row != mv.source.row) {
+            rank(mv.source, rows).to_string()
+        } else {
+            ChessPosition::from_position(mv.source, rows).to_string()
+        }
+    }
+}
+
+// The parts of a non-castling SAN move, which a legal move must agree with
+struct SanPattern {
+    piece: PieceKind,
+    from_file: Option<char>,
+    from_rank: Option<u8>,
+    capture: bool,
+    target: Position,
+    promotion: Option<PieceKind>,
+}
+
+impl SanPattern {
+    fn parse(text: &str, rows: usize, cols: usize, custom_pieces: &CustomPieces) -> Option<Self> {
+        let mut chars = text.chars().collect::<Vec<_>>();
+
+        // Promotion: "e8=Q", also accepted without the "="
+        let mut promotion = None;
+        if let Some(&last) = chars.last() {
+            if last.is_ascii_uppercase() {
+                // Kings too, for Antichess; the legal moves decide what is allowed
+                promotion = Some(custom_pieces.kind(last).filter(|&kind| kind != PieceKind::Pawn)?);
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+            }
+        }
+
+        let piece = match chars.first() {
+            Some(&c) if c.is_ascii_uppercase() => {
+                chars.remove(0);
+                custom_pieces.kind(c)?
+            }
+            _ => PieceKind::Pawn,
+        };
+
+        // The target is a file letter and a rank, which has two digits on boards of 10 ranks or more
+        let digits = chars.iter().rev().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 || chars.len() < digits + 1 {
+            return None;
+        }
+        let target: String = chars.split_off(chars.len() - digits - 1).into_iter().collect();
+        let target = target.parse::<ChessPosition>().ok()?.to_position(rows, cols).ok()?;
+
+        let capture = chars.last() == Some(&'x');
+        if capture {
+            chars.pop();
+        }
+
+        // What is left is the disambiguation: a file, a rank or both
+        let mut from_file = None;
+        if let Some(&c) = chars.first().filter(|c| c.is_ascii_lowercase()) {
+            from_file = Some(c);
+            chars.remove(0);
+        }
+        let from_rank = match chars.iter().collect::<String>() {
+            rank if rank.is_empty() => None,
+            rank if rank.chars().all(|c| c.is_ascii_digit()) => Some(rank.parse::<u8>().ok()?),
+            _ => return None,
+        };
+
+        Some(SanPattern { piece, from_file, from_rank, capture, target, promotion })
+    }
+
+    fn matches(&self, mv: &Move, rows: usize) -> bool {
+        !mv.is_castling()
+            && !mv.is_drop
+            && mv.piece == self.piece
+            && mv.target == self.target
+            && mv.promotion == self.promotion
+            && (!self.capture || mv.is_capture())
+            && self.from_file.is_none_or(|f| file(mv.source, rows) == f)
+            && self.from_rank.is_none_or(|r| rank(mv.source, rows) == r)
+            // Only pawn captures name the pawn's file
+            && (self.piece != PieceKind::Pawn || mv.is_capture() == self.from_file.is_some())
+    }
+}
+
+fn file(pos: Position, rows: usize) -> char {
+    ChessPosition::from_position(pos, rows).col
+}
+
+fn rank(pos: Position, rows: usize) -> u8 {
+    ChessPosition::from_position(pos, rows).row
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess::ChessMatch;
+
+    fn san_of(fen: &str, coordinate: &str) -> String {
+        let mut chess_match = ChessMatch::from_fen(fen).unwrap();
+        let mv = chess_match
+            .legal_moves()
+            .into_iter()
+            .find(|mv| mv.to_coordinates(8) == coordinate)
+            .unwrap_or_else(|| panic!("{} is not legal in {}", coordinate, fen));
+        chess_match.san(&mv)
+    }
+
+    #[test]
+    fn pieces_pawns_and_captures() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(san_of(start, "g1f3"), "Nf3");
+        assert_eq!(san_of(start, "e2e4"), "e4");
+        let open = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+        assert_eq!(san_of(open, "e4d5"), "exd5");
+        assert_eq!(san_of(open, "f1b5"), "Bb5+");
+        assert_eq!(san_of("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", "e5f6"), "exf6");
+    }
+
+    #[test]
+    fn castling_promotion_and_mate() {
+        let castling = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(san_of(castling, "e1g1"), "O-O");
+        assert_eq!(san_of(castling, "e1c1"), "O-O-O");
+        assert_eq!(san_of("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q"), "a8=Q+");
+        assert_eq!(san_of("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8n"), "a8=N");
+        assert_eq!(san_of("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8"), "Ra8#");
+    }
+
+    #[test]
+    fn disambiguation() {
+        // Knights on b1 and f1 (same rank): file
+        assert_eq!(san_of("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "b1d2"), "Nbd2");
+        // Rooks on a1 and a5 (same file): rank
+        assert_eq!(san_of("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1a3"), "R1a3");
+        // Queens on e4, h4 and h1 all reach e1, and h4 shares its file with h1 and its rank with e4: full square
+        assert_eq!(san_of("1k6/8/8/8/4Q2Q/8/8/K6Q w - - 0 1", "h4e1"), "Qh4e1");
+        // A pinned rival doesn't count: the knight on e3 can't leave the e-file
+        assert_eq!(san_of("k3r3/8/8/8/8/N3N3/8/4K3 w - - 0 1", "a3c4"), "Nc4");
+    }
+
+    fn parse(fen: &str, san: &str) -> Result<String, String> {
+        let mut chess_match = ChessMatch::from_fen(fen).unwrap();
+        chess_match.parse_san(san).map(|mv| mv.to_coordinates(8)).map_err(|e| e.0)
+    }
+
+    #[test]
+    fn parsing() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(parse(start, "Nf3"), Ok("g1f3".to_string()));
+        assert_eq!(parse(start, "e4!?"), Ok("e2e4".to_string()));
+        assert_eq!(parse("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "0-0-0"), Ok("e1c1".to_string()));
+        assert_eq!(parse("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a8=R"), Ok("a7a8r".to_string()));
+        assert_eq!(parse("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a8N"), Ok("a7a8n".to_string()));
+        assert_eq!(parse("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "R1a3"), Ok("a1a3".to_string()));
+        assert_eq!(parse("1k6/8/8/8/4Q2Q/8/8/K6Q w - - 0 1", "Qh4e1"), Ok("h4e1".to_string()));
+
+        assert_eq!(parse(start, "e5"), Err("'e5' is not a legal move".to_string()));
+        assert_eq!(parse(start, "Zf3"), Err("'Zf3' is not a valid SAN move".to_string()));
+        assert_eq!(parse("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a8"), Err("'a8' is not a legal move".to_string()));
+        assert!(parse("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "Nd2").unwrap_err().contains("ambiguous"));
+    }
+
+    #[test]
+    fn crazyhouse_drops() {
+        let fen = "4k3/8/8/8/8/8/8/1N2K3[NP] w - - 0 1";
+        assert_eq!(san_of(fen, "N@d6"), "N@d6+");
+        assert_eq!(parse(fen, "P@e4"), Ok("P@e4".to_string()));
+        assert_eq!(parse(fen, "@e4"), Ok("P@e4".to_string()));
+        assert_eq!(parse(fen, "P@e1"), Err("'P@e1' is not a legal move".to_string()));
+        // A knight that can also be dropped on d2 needs no disambiguation
+        assert_eq!(san_of(fen, "b1d2"), "Nd2");
+    }
+}
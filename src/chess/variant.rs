@@ -0,0 +1,227 @@
+// Game variants a match can be played as, and the Chess960 starting positions.
+use crate::chess::piece_kind::PieceKind;
+use crate::error::ChessError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Variant {
+    #[default]
+    Standard,
+    // Fischer Random: the back rank is shuffled, with the king between the rooks and the
+    // bishops on opposite colors
+    Chess960,
+    // Captured pieces join the capturer's hand and can be dropped back onto the board as a move
+    Crazyhouse,
+    // Captures explode, removing the capturer and every piece but the pawns around the target
+    // square; blowing up the enemy king wins
+    Atomic,
+    // Captures are compulsory and the king is an ordinary piece; the player who loses all their
+    // pieces or has no legal moves wins
+    Antichess,
+    // Capablanca chess: a 10x8 board with the archbishop and the chancellor added to each side
+    Capablanca,
+    // Dark chess (fog of war): each player only sees the squares their pieces stand on or can
+    // move to. There is no check, and capturing the enemy king wins.
+    DarkChess,
+    // Each player sees only their own pieces and an umpire tells them what happened, see kriegspiel
+    Kriegspiel,
+    // Crazyhouse for two teams of two on two boards: a captured piece goes to the hand of the
+    // capturer's partner on the other board, see bughouse
+    Bughouse,
+    // Standard rules, and giving check for the third time also wins
+    ThreeCheck,
+    // Standard rules, and bringing the king to one of the four center squares also wins
+    KingOfTheHill,
+}
+
+impl Variant {
+    // Antichess has no castling
+    pub fn has_castling(self) -> bool {
+        self != Variant::Antichess
+    }
+
+    // Whether a king may not be left attacked. Without check, in Antichess and dark chess, the
+    // king can be captured like any other piece and castles through attacked squares.
+    pub fn has_check(self) -> bool {
+        !matches!(self, Variant::Antichess | Variant::DarkChess)
+    }
+
+    // Whether pieces in hand can be dropped onto the board
+    pub fn has_drops(self) -> bool {
+        matches!(self, Variant::Crazyhouse | Variant::Bughouse)
+    }
+
+    // Whether the players are kept from seeing the enemy pieces and moves
+    pub fn hides_pieces(self) -> bool {
+        matches!(self, Variant::DarkChess | Variant::Kriegspiel)
+    }
+
+    // Ranks and files of the board, (rows, cols)
+    pub fn board_size(self) -> (usize, usize) {
+        match self {
+            Variant::Capablanca => (8, 10),
+            _ => (8, 8),
+        }
+    }
+
+    // Value of the PGN "Variant" tag, which standard games leave out
+    pub fn pgn_name(self) -> Option<&'static str> {
+        match self {
+            Variant::Standard => None,
+            Variant::Chess960 => Some("Chess960"),
+            Variant::Crazyhouse => Some("Crazyhouse"),
+            Variant::Atomic => Some("Atomic"),
+            Variant::Antichess => Some("Antichess"),
+            Variant::Capablanca => Some("Capablanca"),
+            Variant::DarkChess => Some("Dark chess"),
+            Variant::Kriegspiel => Some("Kriegspiel"),
+            Variant::Bughouse => Some("Bughouse"),
+            Variant::ThreeCheck => Some("Three-check"),
+            Variant::KingOfTheHill => Some("King of the Hill"),
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Variant::Standard => write!(f, "standard"),
+            Variant::Chess960 => write!(f, "chess960"),
+            Variant::Crazyhouse => write!(f, "crazyhouse"),
+            Variant::Atomic => write!(f, "atomic"),
+            Variant::Antichess => write!(f, "antichess"),
+            Variant::Capablanca => write!(f, "capablanca"),
+            Variant::DarkChess => write!(f, "dark"),
+            Variant::Kriegspiel => write!(f, "kriegspiel"),
+            Variant::Bughouse => write!(f, "bughouse"),
+            Variant::ThreeCheck => write!(f, "threecheck"),
+            Variant::KingOfTheHill => write!(f, "kingofthehill"),
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = ChessError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "standard" | "chess" => Ok(Variant::Standard),
+            "chess960" | "960" | "fischerandom" | "fischer random" => Ok(Variant::Chess960),
+            "crazyhouse" | "zh" => Ok(Variant::Crazyhouse),
+            "atomic" => Ok(Variant::Atomic),
+            "antichess" | "losing chess" | "giveaway" => Ok(Variant::Antichess),
+            "capablanca" => Ok(Variant::Capablanca),
+            "kriegspiel" | "krieg" => Ok(Variant::Kriegspiel),
+            "bughouse" | "bug" => Ok(Variant::Bughouse),
+            "threecheck" | "three-check" | "three check" | "3check" => Ok(Variant::ThreeCheck),
+            "kingofthehill" | "king of the hill" | "king-of-the-hill" | "koth" => Ok(Variant::KingOfTheHill),
+            "dark" | "dark chess" | "darkchess" | "fog of war" | "fogofwar" | "fog" => Ok(Variant::DarkChess),
+            _ => Err(ChessError(format!("Unknown variant '{}'", s))),
+        }
+    }
+}
+
+pub const CHESS960_POSITIONS: u32 = 960;
+
+// Index of the standard setup among the Chess960 positions
+pub const STANDARD_CHESS960_INDEX: u32 = 518;
+
+// White's back rank, from the a-file to the h-file, of Chess960 position `index` (0 to 959)
+// in the Scharnagl numbering, where 518 is the standard setup
+pub fn chess960_back_rank(index: u32) -> Result<[PieceKind; 8], ChessError> {
+    if index >= CHESS960_POSITIONS {
+        return Err(ChessError(format!(
+            "Chess960 positions are numbered 0 to {}, found {}",
+            CHESS960_POSITIONS - 1,
+            index
+        )));
+    }
+
+    let mut rank: [Option<PieceKind>; 8] = [None; 8];
+    let mut n = index as usize;
+
+    // Light-squared bishop on b, d, f or h; dark-squared one on a, c, e or g
+    rank[n % 4 * 2 + 1] = Some(PieceKind::Bishop);
+    n /= 4;
+    rank[n % 4 * 2] = Some(PieceKind::Bishop);
+    n /= 4;
+
+    // The queen on one of the 6 files left, then the knights on 2 of the 5 left
+    let queen = n % 6;
+    n /= 6;
+    place_on_empty(&mut rank, queen, PieceKind::Queen);
+    const KNIGHTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+    let (first, second) = KNIGHTS[n];
+    // The second knight goes first, so that placing it doesn't shift the first one's empty square
+    place_on_empty(&mut rank, second, PieceKind::Knight);
+    place_on_empty(&mut rank, first, PieceKind::Knight);
+
+    // Rook, king and rook on the three files left, in that order
+    for kind in [PieceKind::Rook, PieceKind::King, PieceKind::Rook] {
+        place_on_empty(&mut rank, 0, kind);
+    }
+
+    Ok(rank.map(|kind| kind.unwrap()))
+}
+
+// Puts the piece on the nth empty square of the rank, counting from the a-file
+fn place_on_empty(rank: &mut [Option<PieceKind>; 8], nth: usize, kind: PieceKind) {
+    let square = rank.iter_mut().filter(|square| square.is_none()).nth(nth).unwrap();
+    *square = Some(kind);
+}
+
+// Chess960 position picked from a seed, for callers with no index of their own
+pub fn chess960_index_from_seed(seed: u64) -> u32 {
+    // splitmix64 finalizer, so that consecutive seeds don't give neighbouring positions
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z % CHESS960_POSITIONS as u64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use PieceKind::*;
+
+    #[test]
+    fn known_positions() {
+        assert_eq!(chess960_back_rank(518).unwrap(), [Rook, Knight, Bishop, Queen, King, Bishop, Knight, Rook]);
+        assert_eq!(chess960_back_rank(0).unwrap(), [Bishop, Bishop, Queen, Knight, Knight, Rook, King, Rook]);
+        assert_eq!(chess960_back_rank(959).unwrap(), [Rook, King, Rook, Knight, Knight, Queen, Bishop, Bishop]);
+        assert!(chess960_back_rank(960).is_err());
+    }
+
+    #[test]
+    fn every_position_follows_the_rules() {
+        let mut seen = std::collections::HashSet::new();
+        for index in 0..CHESS960_POSITIONS {
+            let rank = chess960_back_rank(index).unwrap();
+            let files = |kind: PieceKind| (0..8).filter(|&col| rank[col] == kind).collect::<Vec<_>>();
+            let (bishops, rooks, king) = (files(Bishop), files(Rook), files(King)[0]);
+            assert_ne!(bishops[0] % 2, bishops[1] % 2, "position {}", index);
+            assert!(rooks[0] < king && king < rooks[1], "position {}", index);
+            assert!(seen.insert(rank));
+        }
+        assert!(chess960_index_from_seed(42) < CHESS960_POSITIONS);
+    }
+
+    #[test]
+    fn variant_names() {
+        assert_eq!("Chess960".parse::<Variant>().unwrap(), Variant::Chess960);
+        assert_eq!("standard".parse::<Variant>().unwrap(), Variant::Standard);
+        assert_eq!("Crazyhouse".parse::<Variant>().unwrap(), Variant::Crazyhouse);
+        assert_eq!("atomic".parse::<Variant>().unwrap(), Variant::Atomic);
+        assert_eq!("Antichess".parse::<Variant>().unwrap(), Variant::Antichess);
+        assert_eq!("Capablanca".parse::<Variant>().unwrap(), Variant::Capablanca);
+        assert_eq!("fog of war".parse::<Variant>().unwrap(), Variant::DarkChess);
+        assert_eq!("Kriegspiel".parse::<Variant>().unwrap(), Variant::Kriegspiel);
+        assert_eq!("bughouse".parse::<Variant>().unwrap(), Variant::Bughouse);
+        assert_eq!("Three-check".parse::<Variant>().unwrap(), Variant::ThreeCheck);
+        assert_eq!("koth".parse::<Variant>().unwrap(), Variant::KingOfTheHill);
+        assert!("crazy".parse::<Variant>().is_err());
+    }
+}
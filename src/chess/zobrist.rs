@@ -0,0 +1,86 @@
+// Zobrist keys used to hash chess positions.
+//
+// A position hash is the XOR of one key per piece on its square, plus keys for the
+// side to move, each castling right, the en passant file and, in Crazyhouse, the pieces
+// in hand. ChessMatch keeps the piece part up to date incrementally as pieces are placed
+// and removed.
+use crate::chess::color::Color;
+use crate::chess::piece_kind::PieceKind;
+use lazy_static::lazy_static;
+
+// Largest board supported by the key table, in squares
+pub const MAX_SQUARES: usize = 256;
+// The built-in pieces and one kind per letter for custom ones
+const PIECE_KINDS: usize = 8 + 26;
+const COLORS: usize = 2;
+const MAX_FILES: usize = 16;
+// Pieces of one kind in hand with a key of their own; larger counts reuse them
+const MAX_HAND: usize = 16;
+
+struct Keys {
+    pieces: Vec<u64>,
+    side_to_move: u64,
+    castling: [u64; 4],
+    en_passant: [u64; MAX_FILES],
+    hand: Vec<u64>,
+}
+
+lazy_static! {
+    static ref KEYS: Keys = {
+        // Fixed seed, so hashes are stable between runs and can be stored by other tools
+        let mut state = 0x9E37_79B9_7F4A_7C15_u64;
+        let pieces = (0..PIECE_KINDS * COLORS * MAX_SQUARES).map(|_| splitmix64(&mut state)).collect();
+        let side_to_move = splitmix64(&mut state);
+        let castling = [(); 4].map(|_| splitmix64(&mut state));
+        let en_passant = [(); MAX_FILES].map(|_| splitmix64(&mut state));
+        let hand = (0..PIECE_KINDS * COLORS * MAX_HAND).map(|_| splitmix64(&mut state)).collect();
+        Keys { pieces, side_to_move, castling, en_passant, hand }
+    };
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn kind_index(kind: PieceKind) -> usize {
+    match kind {
+        PieceKind::King => 0,
+        PieceKind::Queen => 1,
+        PieceKind::Rook => 2,
+        PieceKind::Bishop => 3,
+        PieceKind::Knight => 4,
+        PieceKind::Pawn => 5,
+        PieceKind::Archbishop => 6,
+        PieceKind::Chancellor => 7,
+        PieceKind::Custom(letter) => 8 + (letter as u8 - b'A') as usize,
+    }
+}
+
+// `square` is the square index on the board (row * cols + col)
+pub fn piece_key(kind: PieceKind, color: Color, square: usize) -> u64 {
+    assert!(square < MAX_SQUARES, "Board too large for Zobrist hashing");
+    KEYS.pieces[(kind_index(kind) * COLORS + color.index()) * MAX_SQUARES + square]
+}
+
+// Included when Black is to move
+pub fn side_to_move_key() -> u64 {
+    KEYS.side_to_move
+}
+
+pub fn castling_key(color: Color, kingside: bool) -> u64 {
+    KEYS.castling[color.index() * 2 + kingside as usize]
+}
+
+// Included only when an en passant capture is actually available on that file
+pub fn en_passant_key(file: usize) -> u64 {
+    KEYS.en_passant[file % MAX_FILES]
+}
+
+// One key per piece in hand: the nth piece of that kind (counting from 0) adds its own key
+pub fn hand_key(kind: PieceKind, color: Color, nth: usize) -> u64 {
+    KEYS.hand[(kind_index(kind) * COLORS + color.index()) * MAX_HAND + nth % MAX_HAND]
+}
@@ -1,22 +1,37 @@
 // src/client.rs
-use crate::network::GameMessage;
+use crate::network::{FourPieceView, GameMessage, PieceView};
 use crate::ui;
 use crate::chess::color::Color;
+use crate::chess::chess_position::ChessPosition;
+use crate::chess::four_player::{FourPlayerMode, Seat};
+use crate::chess::piece_kind::PieceKind;
 use tokio::net::TcpStream;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::time::{timeout, Duration};
 use std::io::{self, Write};
+use std::str::FromStr;
 
 const AMARELO: &str = "\x1b[33m";
 const CIANO: &str = "\x1b[36m";
 const RESET: &str = "\x1b[0m";
 const VERDE: &str = "\x1b[32m";
+// Kriegspiel: quantos anúncios do árbitro ficam visíveis abaixo do tabuleiro
+const UMPIRE_LINES: usize = 8;
+
+// Bughouse: tabuleiro do parceiro, de quem é a vez nele e as mãos das Brancas e das Pretas
+type PartnerView = (Vec<Vec<Option<PieceView>>>, Color, (Vec<PieceView>, Vec<PieceView>));
 
 pub async fn run_client(addr: &str) -> Result<(), Box<dyn std::error::Error>> {
     let mut socket = TcpStream::connect(addr).await?;
     println!("Connected to server at {}", addr);
     
     let mut my_color: Option<Color> = None;
+    // Xadrez a quatro: lugar atribuído pelo servidor
+    let mut my_seat: Option<Seat> = None;
+    // Kriegspiel: anúncios do árbitro desde o início da partida
+    let mut umpire_log: Vec<String> = Vec::new();
+    // Bughouse: última posição recebida do tabuleiro do parceiro
+    let mut partner_board: Option<PartnerView> = None;
     let mut waiting_for_opponent = false;
     let mut frame_index: usize = 0;
     const NUM_FRAMES: usize = 6;
@@ -54,29 +69,38 @@ pub async fn run_client(addr: &str) -> Result<(), Box<dyn std::error::Error>> {
                 println!("{}✓ Assigned color: {:?}{}", VERDE, color, RESET);
             }
             
-            GameMessage::WaitingForOpponent => {
-                if !waiting_for_opponent {
-                    waiting_for_opponent = true;
-                    print!("\n");
-                }
+            GameMessage::WaitingForOpponent if !waiting_for_opponent => {
+                waiting_for_opponent = true;
+                println!();
             }
             
-            GameMessage::GameState { board, turn_color, is_check, is_check_mate, message, captured_by_white, captured_by_black } => {
+            GameMessage::GameState { board, turn_color, is_check, is_check_mate, variant_win, is_draw, can_claim_draw, message, captured_by_white, captured_by_black, hands, visible, checks, clocks } => {
                 // Parar de aguardar - jogo começou
                 if waiting_for_opponent {
                     waiting_for_opponent = false;
-                    print!("\r{:80}\r", ""); // Limpa a linha do lobby
-                    io::stdout().flush().unwrap();
-                    println!("\n{}╔════════════════════════════════╗", CIANO);
-                    println!("║   🎮 Partida iniciada! 🎮      ║");
-                    println!("╚════════════════════════════════╝{}\n", RESET);
-                    std::thread::sleep(Duration::from_secs(1));
+                    show_match_started();
                 }
                 
                 ui::clear_screen();
-                ui::print_board(&board, my_color, &captured_by_white, &captured_by_black);
+                ui::print_board(&board, my_color, &captured_by_white, &captured_by_black, hands.as_ref(), visible.as_ref());
                 println!("\n{}", message);
+                if !umpire_log.is_empty() {
+                    println!("\n{}Umpire:{}", CIANO, RESET);
+                    for announcement in umpire_log.iter().skip(umpire_log.len().saturating_sub(UMPIRE_LINES)) {
+                        println!("  {}", announcement);
+                    }
+                }
+                if let Some((board, turn_color, hands)) = &partner_board {
+                    print_partner_board(board, *turn_color, hands, my_color);
+                }
                 
+                if let Some((white_checks, black_checks)) = checks {
+                    println!("Checks given: White {}/3, Black {}/3", white_checks, black_checks);
+                }
+                if let Some((white_clock, black_clock)) = clocks {
+                    println!("Clock: White {}, Black {}", format_clock(white_clock), format_clock(black_clock));
+                }
+
                 if is_check { 
                     println!("\n{}⚠️  CHECK! ⚠️{}", AMARELO, RESET); 
                 }
@@ -87,13 +111,49 @@ pub async fn run_client(addr: &str) -> Result<(), Box<dyn std::error::Error>> {
                     println!("\n{}🏆 CHECKMATE! Winner: {:?} 🏆{}", VERDE, turn_color, RESET);
                     break;
                 }
+
+                if let Some((winner, reason)) = variant_win {
+                    println!("\n{}🏆 {}! Winner: {:?} 🏆{}", VERDE, reason.to_string().to_uppercase(), winner, RESET);
+                    break;
+                }
+
+                // Empate: não há jogada a fazer, o servidor envia GameEnd em seguida
+                if is_draw {
+                    println!("\n{}🤝 DRAW! 🤝{}", CIANO, RESET);
+                    continue;
+                }
                 
                 if let Some(my_color) = my_color {
                     if turn_color == my_color {
                         println!("\n{}▶ YOUR TURN ({:?})!{}", AMARELO, my_color, RESET);
-                        let source = ui::read_input("Source (e.g., e2): ");
-                        let target = ui::read_input("Target (e.g., e4): ");
-                        let move_msg = GameMessage::MakeMove { source, target };
+                        if can_claim_draw {
+                            println!("{}½ You may claim a draw: type 'draw' as the source.{}", CIANO, RESET);
+                        }
+                        let prompt = if hands.is_some() {
+                            "Move (e.g., e4, Nf3, O-O), drop (e.g., N@f3) or source square (e.g., e2): "
+                        } else {
+                            "Move (e.g., e4, Nf3, O-O) or source square (e.g., e2): "
+                        };
+                        let input = ui::read_input(prompt);
+                        let move_msg = if can_claim_draw && input.eq_ignore_ascii_case("draw") {
+                            GameMessage::ClaimDraw
+                        } else if is_own_piece(&board, &input, my_color) {
+                            // Casa com peça própria: modo antigo, pergunta o destino
+                            let source = input;
+                            let target = ui::read_input("Target (e.g., e4): ");
+                            let promotion = if is_promotion_move(&board, &source, &target) {
+                                Some(read_promotion())
+                            } else {
+                                None
+                            };
+                            GameMessage::MakeMove { source, target, promotion }
+                        } else if let Some((source, target, promotion)) = parse_coordinate_move(&input) {
+                            GameMessage::MakeMove { source, target, promotion }
+                        } else if let Some((kind, square)) = parse_drop(&input) {
+                            GameMessage::DropPiece { kind, square }
+                        } else {
+                            GameMessage::MakeSanMove { san: input }
+                        };
                         let serialized = serde_json::to_string(&move_msg).unwrap();
                         send_packet(&mut socket, &serialized).await?;
                     } else {
@@ -104,8 +164,84 @@ pub async fn run_client(addr: &str) -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
             
-            GameMessage::GameEnd { winner } => {
-                println!("\n{}🏁 Game finished. Winner: {:?}{}", CIANO, winner, RESET);
+            // Mostrado abaixo do próprio tabuleiro, sem limpar a tela; o próximo GameState redesenha tudo
+            GameMessage::PartnerBoard { board, turn_color, hands } => {
+                print_partner_board(&board, turn_color, &hands, my_color);
+                partner_board = Some((board, turn_color, hands));
+            }
+
+            GameMessage::Umpire(announcements) => {
+                let line = announcements.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+                println!("\n{}📣 {}{}", CIANO, line, RESET);
+                umpire_log.push(line);
+            }
+
+            GameMessage::AssignSeat(seat) => {
+                my_seat = Some(seat);
+
+                print!("\r{:80}\r", "");
+                io::stdout().flush().unwrap();
+                println!("{}✓ Assigned seat: {}{}", VERDE, ui::seat_colored(&seat.to_string(), seat, false), RESET);
+            }
+
+            GameMessage::FourPlayerState { board, turn, mode, points, eliminated, in_check, message } => {
+                if waiting_for_opponent {
+                    waiting_for_opponent = false;
+                    show_match_started();
+                }
+
+                ui::clear_screen();
+                ui::print_four_player_board(&board, my_seat, &points, &eliminated);
+                println!("\n{}", message);
+                if mode == FourPlayerMode::Teams {
+                    println!("Teams: Red and Yellow against Blue and Green");
+                }
+
+                for seat in &in_check {
+                    println!("\n{}⚠️  CHECK on {}! ⚠️{}", AMARELO, seat, RESET);
+                }
+
+                println!("\nTurn: {}", ui::seat_colored(&turn.to_string(), turn, false));
+
+                match my_seat {
+                    Some(seat) if eliminated.contains(&seat) => println!("\n👀 You are out of the game; watching the others play..."),
+                    Some(seat) if seat == turn => {
+                        println!("\n{}▶ YOUR TURN ({})!{}", AMARELO, seat, RESET);
+                        let input = ui::read_input("Move (e.g., h2h4) or source square (e.g., h2): ");
+                        let move_msg = if is_own_four_player_piece(&board, &input, seat) {
+                            // Casa com peça própria: pergunta o destino (peão promove a Dama)
+                            let target = ui::read_input("Target (e.g., h4): ");
+                            GameMessage::MakeMove { source: input, target, promotion: None }
+                        } else {
+                            let (source, target, promotion) = parse_coordinate_move(&input).unwrap_or((input, String::new(), None));
+                            GameMessage::MakeMove { source, target, promotion }
+                        };
+                        let serialized = serde_json::to_string(&move_msg).unwrap();
+                        send_packet(&mut socket, &serialized).await?;
+                    }
+                    Some(_) => println!("\n⏳ Waiting for the other players..."),
+                    None => println!("\n⏳ Server hasn't assigned your seat yet..."),
+                }
+            }
+
+            GameMessage::FourPlayerEnd { winners, points, reason } => {
+                let names = winners.iter().map(Seat::to_string).collect::<Vec<_>>();
+                if names.is_empty() {
+                    println!("\n{}🏁 Game finished ({}). No winner.{}", CIANO, reason, RESET);
+                } else {
+                    println!("\n{}🏁 Game finished ({}). Winner: {}{}", CIANO, reason, names.join(" and "), RESET);
+                }
+                for (seat, seat_points) in points {
+                    println!("  {}: {}", ui::seat_colored(&seat.to_string(), seat, false), seat_points);
+                }
+                break;
+            }
+
+            GameMessage::GameEnd { winner, reason } => {
+                match winner {
+                    Some(winner) => println!("\n{}🏁 Game finished ({}). Winner: {:?}{}", CIANO, reason, winner, RESET),
+                    None => println!("\n{}🏁 Game finished ({}). No winner.{}", CIANO, reason, RESET),
+                }
                 break;
             }
             
@@ -120,6 +256,116 @@ pub async fn run_client(addr: &str) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+// Bughouse: o parceiro joga com a outra cor, então o tabuleiro dele aparece do ponto de vista dela
+fn print_partner_board(board: &[Vec<Option<PieceView>>], turn_color: Color, hands: &(Vec<PieceView>, Vec<PieceView>), my_color: Option<Color>) {
+    println!("\n{}Partner's board (turn: {:?}):{}", CIANO, turn_color, RESET);
+    ui::print_board(board, my_color.map(Color::opponent), &[], &[], Some(hands), None);
+}
+
+// Tempo do relógio em minutos e segundos, ex.: 4:05
+fn format_clock(seconds: u64) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+// Verifica se a entrada é uma casa com uma peça do jogador (origem de uma jogada)
+fn is_own_piece(board: &[Vec<Option<PieceView>>], input: &str, my_color: Color) -> bool {
+    let Ok(square) = ChessPosition::from_str(input) else {
+        return false;
+    };
+    let Ok(pos) = square.to_position(board.len(), board[0].len()) else {
+        return false;
+    };
+    matches!(&board[pos.row][pos.col], Some(pv) if pv.color == my_color)
+}
+
+// Xadrez a quatro: verifica se a entrada é uma casa com uma peça viva do jogador
+fn is_own_four_player_piece(board: &[Vec<Option<FourPieceView>>], input: &str, my_seat: Seat) -> bool {
+    let Ok(square) = ChessPosition::from_str(input) else {
+        return false;
+    };
+    let Ok(pos) = square.to_position(board.len(), board.len()) else {
+        return false;
+    };
+    matches!(&board[pos.row][pos.col], Some(pv) if pv.seat == my_seat && !pv.dead)
+}
+
+// Jogada em notação de coordenadas, ex.: "e2e4", "e7e8q" ou, no 10x8, "j2j4"
+fn parse_coordinate_move(input: &str) -> Option<(String, String, Option<PieceKind>)> {
+    if !input.is_ascii() {
+        return None;
+    }
+    // Cada casa é uma letra seguida do número da fileira, que pode ter dois dígitos
+    let square_len = |s: &str| 1 + s.bytes().skip(1).take_while(u8::is_ascii_digit).count();
+    let (source, rest) = input.split_at(square_len(input).min(input.len()));
+    let (target, promotion) = rest.split_at(square_len(rest).min(rest.len()));
+    if promotion.len() > 1 {
+        return None;
+    }
+    ChessPosition::from_str(source).ok()?;
+    ChessPosition::from_str(target).ok()?;
+    let promotion = match promotion {
+        "" => None,
+        letter => Some(PieceKind::from_str(letter).ok().filter(|&kind| is_promotion_letter(kind))?),
+    };
+    Some((source.to_string(), target.to_string(), promotion))
+}
+
+// Crazyhouse: peça da mão e casa, ex.: "N@f3" ou "@e4" (sem letra: peão)
+fn parse_drop(input: &str) -> Option<(PieceKind, String)> {
+    let (piece, square) = input.split_once('@')?;
+    let kind = match piece {
+        "" => PieceKind::Pawn,
+        letter if letter.len() == 1 => PieceKind::from_letter(letter.chars().next()?)?,
+        _ => return None,
+    };
+    ChessPosition::from_str(square).ok()?;
+    Some((kind, square.to_string()))
+}
+
+// Verifica, pelo tabuleiro recebido, se a jogada leva um peão à última fileira
+fn is_promotion_move(board: &[Vec<Option<PieceView>>], source: &str, target: &str) -> bool {
+    let (Ok(source), Ok(target)) = (ChessPosition::from_str(source), ChessPosition::from_str(target)) else {
+        return false;
+    };
+    let rows = board.len();
+    let Ok(pos) = source.to_position(rows, board[0].len()) else {
+        return false;
+    };
+    match &board[pos.row][pos.col] {
+        Some(pv) => pv.kind == PieceKind::Pawn && (target.row == 1 || target.row as usize == rows),
+        None => false,
+    }
+}
+
+// Peças aceitas na promoção; o Rei só vale no Antichess e o Arcebispo e o Chanceler só no
+// Capablanca, o que o servidor confere
+fn is_promotion_letter(kind: PieceKind) -> bool {
+    kind.is_promotion_choice() || matches!(kind, PieceKind::King | PieceKind::Archbishop | PieceKind::Chancellor)
+}
+
+fn read_promotion() -> PieceKind {
+    loop {
+        let input = ui::read_input("Promote to (q, r, b, n; k in Antichess; a, c in Capablanca) [q]: ");
+        if input.is_empty() {
+            return PieceKind::Queen;
+        }
+        match PieceKind::from_str(&input) {
+            Ok(kind) if is_promotion_letter(kind) => return kind,
+            Ok(_) => eprintln!("{}Choose one of q, r, b, n, k, a or c.{}", AMARELO, RESET),
+            Err(e) => eprintln!("{}{}{}", AMARELO, e, RESET),
+        }
+    }
+}
+
+fn show_match_started() {
+    print!("\r{:80}\r", ""); // Limpa a linha do lobby
+    io::stdout().flush().unwrap();
+    println!("\n{}╔════════════════════════════════╗", CIANO);
+    println!("║   🎮 Partida iniciada! 🎮      ║");
+    println!("╚════════════════════════════════╝{}\n", RESET);
+    std::thread::sleep(Duration::from_secs(1));
+}
+
 fn show_lobby_frame(frame_index: usize) {
     const SPINNER_FRAMES: [&str; 6] = ["♟", "♞", "♝", "♜", "♛", "♚"];
     
@@ -0,0 +1,8 @@
+// src/lib.rs
+pub mod board;
+pub mod chess;
+pub mod client;
+pub mod error;
+pub mod network;
+pub mod server;
+pub mod ui;
@@ -1,13 +1,10 @@
 // src/main.rs
-mod board;
-mod chess;
-mod error;
-mod ui;
-mod network;
-mod server;
-mod client;
-
+use rust_chess::chess::{custom_variant::CustomVariant, fen::START_FEN, ChessMatch};
+use rust_chess::{client, server};
+use rust_chess::server::ServerConfig;
 use std::env;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 fn show_banner() {
     println!(r#"
@@ -36,8 +33,9 @@ async fn main() {
     if args.len() < 2 {
         show_banner();
         println!("\nUsage:");
-        println!("  Run Server: cargo run -- server <address> (default: 127.0.0.1:8080)");
+        println!("  Run Server: cargo run -- server <address> (default: 127.0.0.1:8080) [--pgn-dir <dir>] [--variant standard|chess960|crazyhouse|atomic|antichess|capablanca|dark|kriegspiel|bughouse|threecheck|kingofthehill] [--variant-file <file.json>] [--four-player ffa|teams] [--time <minutes>]");
         println!("  Run Client: cargo run -- client <address>");
+        println!("  Run Perft:  cargo run --release -- perft <depth> [fen] (default: start position)");
         return;
     }
 
@@ -46,8 +44,79 @@ async fn main() {
     match mode.as_str() {
         "server" => {
             show_banner();
-            let addr = if args.len() > 2 { &args[2] } else { "127.0.0.1:8080" };
-            if let Err(e) = server::run_server(addr).await {
+            let mut config = ServerConfig::default();
+            let mut positional = Vec::new();
+            let mut variant_given = false;
+            let mut options = args[2..].iter();
+            while let Some(arg) = options.next() {
+                match arg.as_str() {
+                    "--pgn-dir" => match options.next() {
+                        Some(dir) => config.pgn_dir = Some(dir.into()),
+                        None => {
+                            println!("Missing directory after --pgn-dir");
+                            return;
+                        }
+                    },
+                    "--variant" => match options.next().map(|name| name.parse()) {
+                        Some(Ok(variant)) => {
+                            config.variant = variant;
+                            variant_given = true;
+                        }
+                        Some(Err(e)) => {
+                            println!("{} (use 'standard', 'chess960', 'crazyhouse', 'atomic', 'antichess', 'capablanca', 'dark', 'kriegspiel', 'bughouse', 'threecheck' or 'kingofthehill')", e);
+                            return;
+                        }
+                        None => {
+                            println!("Missing variant after --variant");
+                            return;
+                        }
+                    },
+                    "--variant-file" => match options.next().map(|path| CustomVariant::load(Path::new(path))) {
+                        Some(Ok(custom)) => {
+                            config.variant = custom.variant;
+                            config.custom_variant = Some(custom);
+                        }
+                        Some(Err(e)) => {
+                            println!("{}", e);
+                            return;
+                        }
+                        None => {
+                            println!("Missing file after --variant-file");
+                            return;
+                        }
+                    },
+                    "--four-player" => match options.next().map(|mode| mode.parse()) {
+                        Some(Ok(mode)) => config.four_player = Some(mode),
+                        Some(Err(e)) => {
+                            println!("{} (use 'ffa' or 'teams')", e);
+                            return;
+                        }
+                        None => {
+                            println!("Missing mode after --four-player");
+                            return;
+                        }
+                    },
+                    "--time" => match options.next().map(|minutes| minutes.parse::<u64>()) {
+                        Some(Ok(minutes)) if minutes > 0 => config.time_control = Some(Duration::from_secs(minutes * 60)),
+                        Some(_) => {
+                            println!("The time control must be a whole number of minutes greater than 0");
+                            return;
+                        }
+                        None => {
+                            println!("Missing minutes after --time");
+                            return;
+                        }
+                    },
+                    _ => positional.push(arg.as_str()),
+                }
+            }
+            // The variant file names its own base variant, so the two options don't mix
+            if variant_given && config.custom_variant.is_some() {
+                println!("Use either --variant or --variant-file, not both");
+                return;
+            }
+            let addr = positional.first().copied().unwrap_or("127.0.0.1:8080");
+            if let Err(e) = server::run_server(addr, config).await {
                 eprintln!("Server error: {}", e);
             }
         }
@@ -59,8 +128,43 @@ async fn main() {
                 eprintln!("Client error: {}", e);
             }
         }
+        "perft" => {
+            let depth = match args.get(2).map(|d| d.parse::<u32>()) {
+                Some(Ok(depth)) => depth,
+                _ => {
+                    println!("Usage: perft <depth> [fen]");
+                    return;
+                }
+            };
+            // The FEN may come quoted or split over several arguments
+            let fen = if args.len() > 3 { args[3..].join(" ") } else { START_FEN.to_string() };
+            run_perft(depth, &fen);
+        }
         _ => {
-            println!("Invalid mode. Use 'server' or 'client'.");
+            println!("Invalid mode. Use 'server', 'client' or 'perft'.");
+        }
+    }
+}
+
+// Prints the node count below each legal move (divide) and the total
+fn run_perft(depth: u32, fen: &str) {
+    let mut chess_match = match ChessMatch::from_fen(fen) {
+        Ok(chess_match) => chess_match,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
         }
+    };
+
+    let start = Instant::now();
+    let divide = chess_match.divide(depth);
+    let elapsed = start.elapsed();
+
+    for (mv, nodes) in &divide {
+        println!("{}: {}", mv.to_coordinates(chess_match.board.rows), nodes);
     }
-}
\ No newline at end of file
+    let total: u64 = if depth == 0 { 1 } else { divide.iter().map(|(_, nodes)| nodes).sum() };
+    println!("\nMoves: {}", divide.len());
+    println!("Nodes searched: {}", total);
+    println!("Time: {:.3}s", elapsed.as_secs_f64());
+}
@@ -1,29 +1,135 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use crate::chess::color::Color;
+use crate::chess::four_player::{FourPlayerMode, Seat};
+use crate::chess::kriegspiel::Announcement;
+use crate::chess::piece_kind::PieceKind;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PieceView {
     pub symbol: String,
+    pub kind: PieceKind,
     pub color: Color,
 }
 
+// Peça do xadrez a quatro: a cor vem do lugar do jogador; `dead` indica que o dono foi eliminado
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FourPieceView {
+    pub symbol: String,
+    pub kind: PieceKind,
+    pub seat: Seat,
+    pub dead: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum GameMessage {
     // Mensagem enviada pelo servidor para informar ao cliente qual cor o servidor atribuiu a ele.
     AssignColor(Color),
+    // Xadrez a quatro: lugar (e cor) atribuído ao cliente
+    AssignSeat(Seat),
     // Solicitação do cliente para entrar na fila/entrar na partida (sem corpo por enquanto)
     Join,
-    MakeMove { source: String, target: String },
+    // `promotion` só é usado quando um peão chega à última fileira (padrão: Dama)
+    MakeMove { source: String, target: String, promotion: Option<PieceKind> },
+    // Jogada em notação algébrica (SAN), ex.: "Nf3", "exd5", "O-O", "e8=Q"
+    MakeSanMove { san: String },
+    // Crazyhouse: coloca uma peça da mão numa casa vazia, ex.: Cavalo em "f3"
+    DropPiece { kind: PieceKind, square: String },
     GameState { 
         board: Vec<Vec<Option<PieceView>>>,
         turn_color: Color,
         is_check: bool,
         is_check_mate: bool,
+        // Vencedor e motivo de uma vitória por regra da variante (ex.: rei explodido no Atomic)
+        variant_win: Option<(Color, GameEndReason)>,
+        is_draw: bool,
+        can_claim_draw: bool,
         message: String,
         captured_by_white: Vec<PieceView>,
         captured_by_black: Vec<PieceView>,
+        // Crazyhouse: peças na mão das brancas e das pretas (fora do Crazyhouse: None)
+        hands: Option<(Vec<PieceView>, Vec<PieceView>)>,
+        // Xadrez às cegas: casas que o jogador enxerga; as outras vêm vazias (fora dele: None)
+        visible: Option<Vec<Vec<bool>>>,
+        // Three-check: xeques dados pelas brancas e pelas pretas (fora dele: None)
+        checks: Option<(u8, u8)>,
+        // Relógio: segundos restantes das brancas e das pretas (partida sem controle de tempo: None)
+        clocks: Option<(u64, u64)>,
+    },
+    // Xadrez a quatro: tabuleiro 14x14 em cruz (as casas dos cantos vêm sempre vazias)
+    FourPlayerState {
+        board: Vec<Vec<Option<FourPieceView>>>,
+        turn: Seat,
+        mode: FourPlayerMode,
+        // Pontos de cada jogador (só contam no todos contra todos)
+        points: Vec<(Seat, u32)>,
+        eliminated: Vec<Seat>,
+        in_check: Vec<Seat>,
+        message: String,
     },
+    // Xadrez a quatro: fim da partida; sem vencedores é empate
+    FourPlayerEnd { winners: Vec<Seat>, points: Vec<(Seat, u32)>, reason: GameEndReason },
+    // Bughouse: posição do tabuleiro do parceiro, enviada a cada lance jogado nele
+    PartnerBoard { board: Vec<Vec<Option<PieceView>>>, turn_color: Color, hands: (Vec<PieceView>, Vec<PieceView>) },
+    // Kriegspiel: anúncios do árbitro, enviados aos dois jogadores a cada tentativa de lance
+    Umpire(Vec<Announcement>),
+    // O jogador da vez reivindica empate (tripla repetição ou regra dos 50 lances)
+    ClaimDraw,
     WaitingForOpponent,
-    GameEnd { winner: Option<Color> },
+    GameEnd { winner: Option<Color>, reason: GameEndReason },
     Error(String),
-}
\ No newline at end of file
+}
+
+// Motivo do fim da partida, enviado junto com GameEnd
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameEndReason {
+    Checkmate,
+    Stalemate,
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    FiftyMoveRule,
+    SeventyFiveMoveRule,
+    InsufficientMaterial,
+    Timeout,
+    // O tempo acabou, mas o adversário só tem o rei: empate
+    TimeoutVsInsufficientMaterial,
+    // Atomic: o rei foi destruído por uma explosão
+    KingExploded,
+    // Antichess: o vencedor perdeu todas as peças
+    NoPiecesLeft,
+    // Antichess: o vencedor não tem lances legais
+    NoLegalMoves,
+    // Xadrez a quatro e xadrez às cegas: o rei foi capturado
+    KingCaptured,
+    // Xadrez a quatro (todos contra todos): só resta um jogador, e vence quem tem mais pontos
+    LastPlayerStanding,
+    // Three-check: o vencedor deu o terceiro xeque
+    ThreeChecks,
+    // King of the Hill: o Rei do vencedor chegou ao centro (d4, d5, e4 ou e5)
+    KingOfTheHill,
+    Disconnect,
+}
+
+impl fmt::Display for GameEndReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameEndReason::Checkmate => write!(f, "checkmate"),
+            GameEndReason::Stalemate => write!(f, "stalemate"),
+            GameEndReason::ThreefoldRepetition => write!(f, "threefold repetition"),
+            GameEndReason::FivefoldRepetition => write!(f, "fivefold repetition"),
+            GameEndReason::FiftyMoveRule => write!(f, "50-move rule"),
+            GameEndReason::SeventyFiveMoveRule => write!(f, "75-move rule"),
+            GameEndReason::InsufficientMaterial => write!(f, "insufficient material"),
+            GameEndReason::Timeout => write!(f, "time out"),
+            GameEndReason::TimeoutVsInsufficientMaterial => write!(f, "time out against a bare king"),
+            GameEndReason::KingExploded => write!(f, "king exploded"),
+            GameEndReason::NoPiecesLeft => write!(f, "no pieces left"),
+            GameEndReason::NoLegalMoves => write!(f, "no legal moves"),
+            GameEndReason::KingCaptured => write!(f, "king captured"),
+            GameEndReason::LastPlayerStanding => write!(f, "last player standing"),
+            GameEndReason::ThreeChecks => write!(f, "three checks"),
+            GameEndReason::KingOfTheHill => write!(f, "king reached the hill"),
+            GameEndReason::Disconnect => write!(f, "player disconnected"),
+        }
+    }
+}
@@ -1,18 +1,45 @@
 // src/server.rs
-use crate::chess::{ChessMatch, color::Color, chess_position::ChessPosition};
-use crate::network::GameMessage;
+use crate::chess::bughouse::{BughouseResult, BOARDS};
+use crate::chess::piece_kind::PieceKind;
+use crate::chess::{ChessMatch, color::Color, chess_position::ChessPosition, custom_variant::CustomVariant, pgn, variant::Variant};
+use crate::chess::four_player::{FourPlayerMatch, FourPlayerMode, Seat};
+use crate::chess::kriegspiel::Umpire;
+use crate::error::ChessError;
+use crate::network::{GameEndReason, GameMessage};
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::net::TcpListener;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use tokio::sync::Mutex;
 use std::sync::Arc;
 
-pub async fn run_server(addr: &str) -> Result<(), Box<dyn std::error::Error>> {
+// Opções do servidor definidas na linha de comando
+#[derive(Debug, Clone, Default)]
+pub struct ServerConfig {
+    // Pasta onde o PGN de cada partida encerrada é gravado (nenhuma: não grava)
+    pub pgn_dir: Option<PathBuf>,
+    // Variante jogada em todas as partidas (no Chess960, cada partida sorteia sua posição inicial);
+    // com --variant-file, é a variante base do arquivo
+    pub variant: Variant,
+    // Variante lida de um arquivo (--variant-file), com peças em notação Betza
+    pub custom_variant: Option<CustomVariant>,
+    // Xadrez a quatro (--four-player): o lobby espera quatro jogadores em vez de dois
+    pub four_player: Option<FourPlayerMode>,
+    // Tempo de cada jogador para a partida inteira (--time); sem ele, não há relógio
+    pub time_control: Option<Duration>,
+}
+
+pub async fn run_server(addr: &str, config: ServerConfig) -> Result<(), Box<dyn std::error::Error>> {
     let listener = TcpListener::bind(addr).await?;
     println!("Server listening on {}", addr);
     println!("Waiting for players to connect...");
 
-    let waiting: Arc<Mutex<Option<tokio::net::TcpStream>>> = Arc::new(Mutex::new(None));
+    let waiting: Arc<Mutex<Vec<tokio::net::TcpStream>>> = Arc::new(Mutex::new(Vec::new()));
+    // Bughouse: duas duplas, uma partida em cada um dos dois tabuleiros
+    let bughouse = config.four_player.is_none() && config.variant == Variant::Bughouse;
+    let players_per_match = if config.four_player.is_some() || bughouse { 4 } else { 2 };
 
     loop {
         let (socket, peer) = listener.accept().await?;
@@ -20,23 +47,34 @@ pub async fn run_server(addr: &str) -> Result<(), Box<dyn std::error::Error>> {
 
         let waiting_clone = waiting.clone();
 
-        // Tenta parear imediatamente: se houver um jogador esperando, pegue-o e crie uma partida
+        // Tenta formar uma partida: se já houver jogadores esperando suficientes, pegue-os
         let mut slot = waiting_clone.lock().await;
-        if slot.is_none() {
-            // Não há adversário: mande WaitingForOpponent e guarde o socket
+        if slot.len() + 1 < players_per_match {
+            // Faltam adversários: mande WaitingForOpponent e guarde o socket
             let mut s = socket;
             let waiting_msg = serde_json::to_string(&GameMessage::WaitingForOpponent).unwrap();
             let _ = send_packet(&mut s, &waiting_msg).await;
-            *slot = Some(s);
-            println!("Player stored in waiting slot — waiting opponent...");
+            slot.push(s);
+            println!("Player stored in waiting slot — waiting opponents ({}/{})...", slot.len(), players_per_match);
         } else {
-            // Há alguém esperando: retire e crie uma partida
-            let opponent = slot.take().unwrap();
-            println!("Starting a new match between two players...");
+            // Há jogadores suficientes: retire-os da fila e crie uma partida
+            let mut players: Vec<_> = slot.drain(..).collect();
+            players.push(socket);
+            println!("Starting a new match between {} players...", players_per_match);
 
             // Spawn uma task para rodar a partida sem bloquear o accept loop
+            let config = config.clone();
             tokio::spawn(async move {
-                if let Err(e) = run_match(opponent, socket).await {
+                let result = match config.four_player {
+                    Some(mode) => run_four_player_match(players, mode).await,
+                    None if bughouse => run_bughouse(players, config).await,
+                    None => {
+                        let socket = players.pop().unwrap();
+                        let opponent = players.pop().unwrap();
+                        run_match(opponent, socket, config).await
+                    }
+                };
+                if let Err(e) = result {
                     eprintln!("Match error: {}", e);
                 }
             });
@@ -44,14 +82,14 @@ pub async fn run_server(addr: &str) -> Result<(), Box<dyn std::error::Error>> {
     }
 }
 
-async fn send_packet(socket: &mut tokio::net::TcpStream, msg: &str) -> Result<(), std::io::Error> {
+async fn send_packet(socket: &mut (impl AsyncWriteExt + Unpin), msg: &str) -> Result<(), std::io::Error> {
     let len = msg.len() as u32;
     socket.write_u32(len).await?;
     socket.write_all(msg.as_bytes()).await?;
     Ok(())
 }
 
-async fn read_packet(socket: &mut tokio::net::TcpStream) -> Result<String, std::io::Error> {
+async fn read_packet(socket: &mut (impl AsyncReadExt + Unpin)) -> Result<String, std::io::Error> {
     let len = socket.read_u32().await?;
     let mut buf = vec![0u8; len as usize];
     socket.read_exact(&mut buf).await?;
@@ -59,7 +97,7 @@ async fn read_packet(socket: &mut tokio::net::TcpStream) -> Result<String, std::
 }
 
 // Executa o ciclo de jogo para duas conexões — conecta o loop do jogo, envia mensagens e processa jogadas.
-async fn run_match(mut socket_a: tokio::net::TcpStream, mut socket_b: tokio::net::TcpStream) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+async fn run_match(mut socket_a: tokio::net::TcpStream, mut socket_b: tokio::net::TcpStream, config: ServerConfig) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     // Decide as cores: A = White, B = Black
     let assign_a = serde_json::to_string(&GameMessage::AssignColor(Color::White)).unwrap();
     let assign_b = serde_json::to_string(&GameMessage::AssignColor(Color::Black)).unwrap();
@@ -67,66 +105,477 @@ async fn run_match(mut socket_a: tokio::net::TcpStream, mut socket_b: tokio::net
     let _ = send_packet(&mut socket_a, &assign_a).await;
     let _ = send_packet(&mut socket_b, &assign_b).await;
 
-    let mut chess_match = ChessMatch::new();
+    // Endereços guardados para o PGN, pois depois de uma desconexão não dá mais para consultá-los
+    let address = |addr: std::io::Result<std::net::SocketAddr>| addr.map(|a| a.to_string()).unwrap_or_else(|_| "?".to_string());
+    let white = address(socket_a.peer_addr());
+    let black = address(socket_b.peer_addr());
+    let site = address(socket_a.local_addr());
+
+    let seed = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0);
+    let mut chess_match = match &config.custom_variant {
+        Some(custom) => custom.new_match(seed)?,
+        None => ChessMatch::for_variant(config.variant, seed),
+    };
+    // Vencedor e motivo, quando a partida termina
+    let mut outcome: Option<(Option<Color>, GameEndReason)> = None;
+    // Mensagem enviada junto com o estado: o último lance em SAN (antes do primeiro lance, a variante)
+    let mut message = match chess_match.variant() {
+        Variant::Standard => String::new(),
+        Variant::Chess960 => format!("Chess960: {}", chess_match.to_fen()),
+        Variant::Crazyhouse => "Crazyhouse: captured pieces can be dropped (e.g., N@f3)".to_string(),
+        Variant::Atomic => "Atomic: captures explode the pieces around them; explode the enemy king to win".to_string(),
+        Variant::Antichess => "Antichess: captures are compulsory; lose all your pieces to win".to_string(),
+        Variant::Capablanca => "Capablanca: 10x8 board with the archbishop (A) and the chancellor (C)".to_string(),
+        Variant::DarkChess => "Dark chess: you only see the squares your pieces can reach; capture the enemy king to win".to_string(),
+        Variant::Kriegspiel => "Kriegspiel: you only see your own pieces; the umpire announces illegal tries, captures and checks".to_string(),
+        Variant::Bughouse => "Bughouse: pieces you capture go to your partner on the other board".to_string(),
+        Variant::ThreeCheck => "Three-check: give check three times to win".to_string(),
+        Variant::KingOfTheHill => "King of the Hill: bring your king to d4, d5, e4 or e5 to win".to_string(),
+    };
+    // Kriegspiel: o árbitro confere as tentativas e guarda o histórico delas
+    let mut umpire = Umpire::new();
+    // Tempo restante das brancas e das pretas; o relógio da vez corre enquanto o servidor espera o lance
+    let mut clocks = config.time_control.map(|limit| [limit; 2]);
+    if let Some(custom) = &config.custom_variant {
+        message = custom.description();
+    }
 
     loop {
         let current_turn = chess_match.get_current_player();
 
-        // Envia estado para ambos os jogadores
-        let state_msg = chess_match.to_game_state("".to_string());
-        let serialized = serde_json::to_string(&state_msg).unwrap();
-        if let Err(e) = send_packet(&mut socket_a, &serialized).await { eprintln!("Error sending state to A: {}", e); break; }
-        if let Err(e) = send_packet(&mut socket_b, &serialized).await { eprintln!("Error sending state to B: {}", e); break; }
+        // Envia estado para ambos os jogadores, cada um com o que pode ver (no xadrez às cegas e
+        // no Kriegspiel, o lance do adversário também fica escondido)
+        let state_for = |color: Color| {
+            let opponent_moved = chess_match.history().last().is_some_and(|record| record.mv.color != color);
+            let message = if chess_match.variant().hides_pieces() && opponent_moved {
+                format!("{:?} has moved", color.opponent())
+            } else {
+                message.clone()
+            };
+            let mut state = chess_match.to_game_state_for(color, message);
+            if let GameMessage::GameState { clocks: state_clocks, .. } = &mut state {
+                *state_clocks = clocks.map(|[white, black]| (white.as_secs(), black.as_secs()));
+            }
+            serde_json::to_string(&state).unwrap()
+        };
+        let (state_a, state_b) = (state_for(Color::White), state_for(Color::Black));
+        if let Err(e) = send_packet(&mut socket_a, &state_a).await { eprintln!("Error sending state to A: {}", e); break; }
+        if let Err(e) = send_packet(&mut socket_b, &state_b).await { eprintln!("Error sending state to B: {}", e); break; }
 
         if chess_match.check_mate {
             println!("Match finished (checkmate). Winner: {:?}", current_turn);
-            let game_end = serde_json::to_string(&GameMessage::GameEnd { winner: Some(current_turn) }).unwrap();
+            let game_end = serde_json::to_string(&GameMessage::GameEnd { winner: Some(current_turn), reason: GameEndReason::Checkmate }).unwrap();
             let _ = send_packet(&mut socket_a, &game_end).await;
             let _ = send_packet(&mut socket_b, &game_end).await;
+            outcome = Some((Some(current_turn), GameEndReason::Checkmate));
             break;
         }
 
-        // Aguarda jogada do jogador da vez
-        let result = if current_turn == Color::White {
-            read_packet(&mut socket_a).await
-        } else {
-            read_packet(&mut socket_b).await
+        // Vitória por regra da variante (rei explodido, peças esgotadas no Antichess...)
+        if let Some((winner, reason)) = chess_match.variant_win() {
+            println!("Match finished ({}). Winner: {:?}", reason, winner);
+            let game_end = serde_json::to_string(&GameMessage::GameEnd { winner: Some(winner), reason }).unwrap();
+            let _ = send_packet(&mut socket_a, &game_end).await;
+            let _ = send_packet(&mut socket_b, &game_end).await;
+            outcome = Some((Some(winner), reason));
+            break;
+        }
+
+        if let Some(reason) = chess_match.draw_reason() {
+            println!("Match finished (draw by {}).", reason);
+            let game_end = serde_json::to_string(&GameMessage::GameEnd { winner: None, reason }).unwrap();
+            let _ = send_packet(&mut socket_a, &game_end).await;
+            let _ = send_packet(&mut socket_b, &game_end).await;
+            outcome = Some((None, reason));
+            break;
+        }
+
+        // Aguarda jogada do jogador da vez, no máximo pelo tempo que resta no relógio dele
+        let socket = if current_turn == Color::White { &mut socket_a } else { &mut socket_b };
+        let started = Instant::now();
+        let result = match clocks {
+            Some(clocks) => match tokio::time::timeout(clocks[current_turn.index()], read_packet(socket)).await {
+                Ok(result) => result,
+                Err(_) => {
+                    // Queda de bandeira: perde, a menos que o adversário só tenha o rei
+                    let (winner, reason) = chess_match.timeout_result(current_turn);
+                    println!("Match finished ({}). Winner: {:?}", reason, winner);
+                    let game_end = serde_json::to_string(&GameMessage::GameEnd { winner, reason }).unwrap();
+                    let _ = send_packet(&mut socket_a, &game_end).await;
+                    let _ = send_packet(&mut socket_b, &game_end).await;
+                    outcome = Some((winner, reason));
+                    break;
+                }
+            },
+            None => read_packet(socket).await,
         };
+        if let Some(clocks) = &mut clocks {
+            let clock = &mut clocks[current_turn.index()];
+            *clock = clock.saturating_sub(started.elapsed());
+        }
 
         let move_json = match result {
             Ok(s) => s,
             Err(e) => {
                 eprintln!("Read error (player disconnected?): {}", e);
                 // avisa o outro player que jogo terminou
-                let _ = send_packet(&mut socket_a, &serde_json::to_string(&GameMessage::GameEnd { winner: None }).unwrap()).await;
-                let _ = send_packet(&mut socket_b, &serde_json::to_string(&GameMessage::GameEnd { winner: None }).unwrap()).await;
+                let game_end = serde_json::to_string(&GameMessage::GameEnd { winner: None, reason: GameEndReason::Disconnect }).unwrap();
+                let _ = send_packet(&mut socket_a, &game_end).await;
+                let _ = send_packet(&mut socket_b, &game_end).await;
+                outcome = Some((None, GameEndReason::Disconnect));
                 break;
             }
         };
 
         let request: GameMessage = serde_json::from_str(&move_json)?;
 
-        if let GameMessage::MakeMove { source, target } = request {
+        let result = match request {
+            // Kriegspiel: o árbitro anuncia o resultado da tentativa aos dois jogadores
+            GameMessage::MakeMove { source, target, promotion } if chess_match.variant() == Variant::Kriegspiel => {
+                match (ChessPosition::from_str(&source), ChessPosition::from_str(&target)) {
+                    (Ok(s), Ok(t)) => match umpire.attempt(&mut chess_match, s, t, promotion) {
+                        Ok(announcements) => {
+                            println!("Umpire in match: {} -> {}: {:?}", source, target, announcements);
+                            let umpire_msg = serde_json::to_string(&GameMessage::Umpire(announcements)).unwrap();
+                            let _ = send_packet(&mut socket_a, &umpire_msg).await;
+                            let _ = send_packet(&mut socket_b, &umpire_msg).await;
+                            Ok(())
+                        }
+                        Err(e) => Err(e),
+                    },
+                    (Err(e), _) | (_, Err(e)) => Err(e),
+                }
+            }
+            // Os erros de SAN (lance ambíguo, captura sem peça) revelariam as peças do adversário
+            GameMessage::MakeSanMove { .. } if chess_match.variant() == Variant::Kriegspiel => {
+                Err(ChessError("In Kriegspiel, moves are given as source and target squares (e.g., e2e4)".to_string()))
+            }
+            request => play_request(&mut chess_match, request),
+        };
+
+        if result.is_ok() {
+            if let Some(last_move) = last_move_message(&chess_match) {
+                message = last_move;
+            }
+        }
+
+        if let Err(e) = result {
+            // Envia erro para o jogador da vez
+            let err_msg = serde_json::to_string(&GameMessage::Error(e.0)).unwrap();
+            if current_turn == Color::White {
+                let _ = send_packet(&mut socket_a, &err_msg).await;
+            } else {
+                let _ = send_packet(&mut socket_b, &err_msg).await;
+            }
+        }
+    }
+
+    if let (Some(dir), Some((winner, reason))) = (&config.pgn_dir, outcome) {
+        write_pgn(dir, &chess_match, [&white, &black], &site, winner, reason);
+    }
+
+    Ok(())
+}
+
+// Aplica o pedido do jogador da vez: lance em coordenadas ou em SAN, drop ou pedido de empate
+fn play_request(chess_match: &mut ChessMatch, request: GameMessage) -> Result<(), ChessError> {
+    match request {
+        GameMessage::MakeMove { source, target, promotion } => {
             // Tenta aplicar o movimento
-            let s_pos = ChessPosition::from_str(&source);
-            let t_pos = ChessPosition::from_str(&target);
+            match (ChessPosition::from_str(&source), ChessPosition::from_str(&target)) {
+                (Ok(s), Ok(t)) => chess_match.perform_chess_move(s, t, promotion).map(|_| {
+                    println!("Move in match: {} -> {}", source, target);
+                }),
+                _ => Ok(()),
+            }
+        }
+        GameMessage::MakeSanMove { san } => chess_match.perform_san_move(&san).map(|mv| {
+            println!("Move in match: {} ({})", san, mv.to_coordinates(chess_match.board.rows));
+        }),
+        GameMessage::DropPiece { kind, square } => match ChessPosition::from_str(&square) {
+            Ok(target) => chess_match.perform_drop(kind, target).map(|mv| {
+                println!("Move in match: {}", mv.to_coordinates(chess_match.board.rows));
+            }),
+            Err(e) => Err(e),
+        },
+        // Se aceito, o próximo ciclo do loop envia GameEnd aos jogadores
+        GameMessage::ClaimDraw => chess_match.claim_draw().map(|reason| {
+            println!("Draw claimed in match: {}", reason);
+        }),
+        _ => Ok(()),
+    }
+}
+
+// Último lance em SAN com o número do lance, ex.: "Last move: 12... Nf6"
+fn last_move_message(chess_match: &ChessMatch) -> Option<String> {
+    let record = chess_match.history().last()?;
+    let number = chess_match.history().len().div_ceil(2);
+    let dots = if record.mv.color == Color::White { "." } else { "..." };
+    Some(format!("Last move: {}{} {}", number, dots, record.san))
+}
+
+// Grava o PGN de uma partida encerrada; `players` são os endereços das Brancas e das Pretas
+fn write_pgn(dir: &std::path::Path, chess_match: &ChessMatch, players: [&str; 2], site: &str, winner: Option<Color>, reason: GameEndReason) {
+    // Partida abandonada não tem resultado
+    let result = if reason == GameEndReason::Disconnect { "*" } else { pgn::result_token(winner) };
+    let date = pgn::today();
+    let pgn = chess_match.to_pgn(&[
+        ("Event", "Rust Chess online match"),
+        ("Site", site),
+        ("Date", &date),
+        ("Round", "-"),
+        ("White", players[0]),
+        ("Black", players[1]),
+        ("Result", result),
+        ("Termination", pgn::termination(reason)),
+    ]);
+    match save_pgn(dir, &pgn) {
+        Ok(path) => println!("Game saved to {}", path.display()),
+        Err(e) => eprintln!("Error saving PGN to {}: {}", dir.display(), e),
+    }
+}
+
+// Bughouse: o que um tabuleiro avisa ao outro
+enum BoardEvent {
+    // Peça capturada que vai para a mão do parceiro: cor de quem a recebe e tipo
+    Piece(Color, PieceKind),
+    // Posição atual do tabuleiro (PartnerBoard), mostrada aos jogadores do outro tabuleiro
+    Position(GameMessage),
+    // A partida de um tabuleiro terminou, e com ela a das duplas
+    Ended(BughouseResult),
+}
+
+// Bughouse: os dois primeiros jogadores ficam no tabuleiro 0 (Brancas e Pretas) e os outros dois
+// no tabuleiro 1; cada tabuleiro roda seu próprio loop e os dois conversam por canais
+async fn run_bughouse(sockets: Vec<tokio::net::TcpStream>, config: ServerConfig) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (to_second, from_first) = mpsc::unbounded_channel();
+    let (to_first, from_second) = mpsc::unbounded_channel();
+    let mut sockets = sockets.into_iter();
+    let mut seats = || [sockets.next().unwrap(), sockets.next().unwrap()];
+    let (first, second) = (seats(), seats());
+    let (first, second) = tokio::join!(
+        run_bughouse_board(0, first, to_second, from_second, config.clone()),
+        run_bughouse_board(BOARDS - 1, second, to_first, from_first, config),
+    );
+    first.and(second)
+}
+
+async fn run_bughouse_board(
+    board: usize,
+    sockets: [tokio::net::TcpStream; 2],
+    to_partner: UnboundedSender<BoardEvent>,
+    mut from_partner: UnboundedReceiver<BoardEvent>,
+    config: ServerConfig,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let address = |addr: std::io::Result<std::net::SocketAddr>| addr.map(|a| a.to_string()).unwrap_or_else(|_| "?".to_string());
+    let players = [address(sockets[0].peer_addr()), address(sockets[1].peer_addr())];
+    let site = address(sockets[0].local_addr());
+    let index = |color: Color| (color == Color::Black) as usize;
 
-            if let (Ok(s), Ok(t)) = (s_pos, t_pos) {
-                match chess_match.perform_chess_move(s, t) {
-                    Ok(_) => println!("Move in match: {} -> {}", source, target),
+    // Os dois jogadores são lidos ao mesmo tempo que os avisos do outro tabuleiro: uma task por
+    // jogador repassa os pacotes dele para o canal `moves`
+    let (moves_tx, mut moves) = mpsc::unbounded_channel();
+    let [white, black] = sockets;
+    let mut writers = Vec::new();
+    for (socket, color) in [(white, Color::White), (black, Color::Black)] {
+        let (mut reader, mut writer) = socket.into_split();
+        let assign = serde_json::to_string(&GameMessage::AssignColor(color)).unwrap();
+        let _ = send_packet(&mut writer, &assign).await;
+        writers.push(writer);
+        let moves_tx = moves_tx.clone();
+        tokio::spawn(async move {
+            loop {
+                let packet = read_packet(&mut reader).await;
+                let failed = packet.is_err();
+                if moves_tx.send((color, packet)).is_err() || failed {
+                    break;
+                }
+            }
+        });
+    }
+    drop(moves_tx);
+
+    let seed = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0);
+    let mut chess_match = match &config.custom_variant {
+        Some(custom) => custom.new_match(seed)?,
+        None => ChessMatch::for_variant(Variant::Bughouse, seed),
+    };
+    let mut message = format!("Bughouse (board {}): pieces you capture go to your partner on the other board", board + 1);
+    let mut outcome: Option<BughouseResult> = None;
+    let mut state_changed = true;
+
+    loop {
+        if state_changed {
+            state_changed = false;
+            let state = serde_json::to_string(&chess_match.to_game_state(message.clone())).unwrap();
+            for writer in writers.iter_mut() {
+                let _ = send_packet(writer, &state).await;
+            }
+            let _ = to_partner.send(BoardEvent::Position(chess_match.to_partner_board()));
+
+            if let Some(result) = chess_match.bughouse_result(board) {
+                println!("Bughouse finished on board {} ({}). Winning team: {:?}", board + 1, result.reason, result.winning_team);
+                let _ = to_partner.send(BoardEvent::Ended(result));
+                outcome = Some(result);
+                break;
+            }
+        }
+
+        tokio::select! {
+            Some(event) = from_partner.recv() => match event {
+                BoardEvent::Piece(color, kind) => {
+                    chess_match.receive_piece(color, kind);
+                    // Só quem aguarda recebe o novo estado: o jogador da vez já está digitando o
+                    // lance e a peça nova já pode ser colocada por ele
+                    let waiting = chess_match.get_current_player().opponent();
+                    let state = serde_json::to_string(&chess_match.to_game_state(message.clone())).unwrap();
+                    let _ = send_packet(&mut writers[index(waiting)], &state).await;
+                }
+                BoardEvent::Position(view) => {
+                    let view = serde_json::to_string(&view).unwrap();
+                    for writer in writers.iter_mut() {
+                        let _ = send_packet(writer, &view).await;
+                    }
+                }
+                BoardEvent::Ended(result) => {
+                    outcome = Some(result);
+                    break;
+                }
+            },
+            Some((color, packet)) = moves.recv() => {
+                let packet = match packet {
+                    Ok(packet) => packet,
                     Err(e) => {
-                        // Envia erro para o jogador da vez
-                        let err_msg = serde_json::to_string(&GameMessage::Error(e.0)).unwrap();
-                        if current_turn == Color::White {
-                            let _ = send_packet(&mut socket_a, &err_msg).await;
-                        } else {
-                            let _ = send_packet(&mut socket_b, &err_msg).await;
+                        eprintln!("Read error on board {} (player disconnected?): {}", board + 1, e);
+                        let result = BughouseResult { winning_team: None, reason: GameEndReason::Disconnect };
+                        let _ = to_partner.send(BoardEvent::Ended(result));
+                        outcome = Some(result);
+                        break;
+                    }
+                };
+                let result = if color != chess_match.get_current_player() {
+                    Err(ChessError("It's not your turn".to_string()))
+                } else {
+                    let moves_played = chess_match.history().len();
+                    serde_json::from_str(&packet)
+                        .map_err(|e| ChessError(e.to_string()))
+                        .and_then(|request| play_request(&mut chess_match, request))
+                        .map(|_| chess_match.history().len() > moves_played)
+                };
+                match result {
+                    Ok(moved) => {
+                        // A peça capturada vai para a mão do parceiro de quem capturou
+                        if let Some((color, kind)) = chess_match.passed_piece().filter(|_| moved) {
+                            let _ = to_partner.send(BoardEvent::Piece(color, kind));
                         }
+                        if let Some(last_move) = last_move_message(&chess_match) {
+                            message = last_move;
+                        }
+                        state_changed = true;
+                    }
+                    Err(e) => {
+                        // Depois do erro, o jogador recebe o estado de novo para tentar outro lance, como no run_match
+                        let err_msg = serde_json::to_string(&GameMessage::Error(e.0)).unwrap();
+                        let state = serde_json::to_string(&chess_match.to_game_state(message.clone())).unwrap();
+                        let writer = &mut writers[index(color)];
+                        let _ = send_packet(writer, &err_msg).await;
+                        let _ = send_packet(writer, &state).await;
                     }
                 }
             }
+            else => break,
+        }
+    }
+
+    if let Some(result) = outcome {
+        let game_end = serde_json::to_string(&GameMessage::GameEnd { winner: result.winner_on(board), reason: result.reason }).unwrap();
+        for writer in writers.iter_mut() {
+            let _ = send_packet(writer, &game_end).await;
+        }
+        if let Some(dir) = &config.pgn_dir {
+            write_pgn(dir, &chess_match, [&players[0], &players[1]], &site, result.winner_on(board), result.reason);
         }
     }
 
     Ok(())
+}
+
+// Xadrez a quatro: os jogadores recebem os lugares na ordem em que chegaram (Vermelho, Azul,
+// Amarelo, Verde). Quem desconecta é eliminado, e a partida segue sem ele.
+async fn run_four_player_match(mut sockets: Vec<tokio::net::TcpStream>, mode: FourPlayerMode) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    for (socket, seat) in sockets.iter_mut().zip(Seat::ALL) {
+        let assign = serde_json::to_string(&GameMessage::AssignSeat(seat)).unwrap();
+        let _ = send_packet(socket, &assign).await;
+    }
+
+    let mut game = FourPlayerMatch::new(mode);
+    let mut message = match mode {
+        FourPlayerMode::FreeForAll => "Four-player chess, free-for-all: captures and checkmates score points".to_string(),
+        FourPlayerMode::Teams => "Four-player chess, teams: Red and Yellow against Blue and Green".to_string(),
+    };
+
+    loop {
+        // Envia o estado para todos; quem já saiu simplesmente não recebe
+        let serialized = serde_json::to_string(&game.to_state(message.clone())).unwrap();
+        for socket in sockets.iter_mut() {
+            let _ = send_packet(socket, &serialized).await;
+        }
+
+        if let Some(result) = game.result() {
+            println!("Four-player match finished ({}). Winners: {:?}", result.reason, result.winners);
+            let points = Seat::ALL.into_iter().map(|seat| (seat, game.points(seat))).collect();
+            let game_end = serde_json::to_string(&GameMessage::FourPlayerEnd { winners: result.winners.clone(), points, reason: result.reason }).unwrap();
+            for socket in sockets.iter_mut() {
+                let _ = send_packet(socket, &game_end).await;
+            }
+            break;
+        }
+
+        // Aguarda jogada do jogador da vez
+        let seat = game.current_player();
+        let move_json = match read_packet(&mut sockets[seat.index()]).await {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("Read error ({} disconnected?): {}", seat, e);
+                game.resign(seat);
+                message = format!("{} left the game", seat);
+                continue;
+            }
+        };
+
+        // Um pacote inválido vira um erro para quem o mandou, sem derrubar a partida dos outros
+        let result = match serde_json::from_str(&move_json).map_err(|e| ChessError(e.to_string())) {
+            Ok(GameMessage::MakeMove { source, target, promotion }) => match (ChessPosition::from_str(&source), ChessPosition::from_str(&target)) {
+                (Ok(s), Ok(t)) => game.perform_move(s, t, promotion).map(|mv| {
+                    println!("Move in four-player match: {} {}", seat, mv.to_coordinates());
+                    message = format!("Last move: {} {}", seat, mv.to_coordinates());
+                }),
+                (Err(e), _) | (_, Err(e)) => Err(e),
+            },
+            Ok(_) => Err(ChessError("Only moves given as source and target squares are accepted in four-player chess".to_string())),
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = result {
+            // Envia erro para o jogador da vez
+            let err_msg = serde_json::to_string(&GameMessage::Error(e.0)).unwrap();
+            let _ = send_packet(&mut sockets[seat.index()], &err_msg).await;
+        }
+    }
+
+    Ok(())
+}
+
+// Grava o PGN num arquivo novo na pasta, com nome baseado no horário
+fn save_pgn(dir: &std::path::Path, pgn: &str) -> Result<PathBuf, std::io::Error> {
+    std::fs::create_dir_all(dir)?;
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+    let mut path = dir.join(format!("game-{}.pgn", millis));
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("game-{}-{}.pgn", millis, n));
+        n += 1;
+    }
+    std::fs::write(&path, pgn)?;
+    Ok(path)
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
 // src/ui.rs
+use crate::board::position::Position;
 use crate::chess::color::Color;
-use crate::network::PieceView;
+use crate::chess::four_player::{is_on_board, Seat};
+use crate::network::{FourPieceView, PieceView};
 use colored::*;
 use std::io::{self, Write};
 
@@ -17,42 +19,60 @@ pub fn read_input(prompt: &str) -> String {
 }
 
 pub fn print_board(
-    board: &Vec<Vec<Option<PieceView>>>,
+    board: &[Vec<Option<PieceView>>],
     my_color: Option<Color>,
-    captured_by_white: &Vec<PieceView>,
-    captured_by_black: &Vec<PieceView>,
+    captured_by_white: &[PieceView],
+    captured_by_black: &[PieceView],
+    hands: Option<&(Vec<PieceView>, Vec<PieceView>)>,
+    visible: Option<&Vec<Vec<bool>>>,
 ) {
     let perspective_white = my_color.unwrap_or(Color::White) == Color::White;
 
+    // No Crazyhouse, mostra as peças na mão (que podem voltar ao tabuleiro) no lugar das capturadas
+    let (white_label, white_pieces, black_label, black_pieces) = match hands {
+        Some((white_hand, black_hand)) => ("Mão das Brancas:", white_hand.as_slice(), "Mão das Pretas:", black_hand.as_slice()),
+        None => ("Brancas capturaram:", captured_by_white, "Pretas capturaram:", captured_by_black),
+    };
+
     // Cabeçalho de peças capturadas
-    print!("\n{} ", "Brancas capturaram:".green());
-    print_captured_from_views(captured_by_white);
+    print!("\n{} ", white_label.green());
+    print_captured_from_views(white_pieces);
     println!();
 
-    // Tabuleiro com borda fixa
-    println!("  {}", "┌─────────────────┐");
+    // Tabuleiro com borda do tamanho dele (8x8, ou 10x8 no Capablanca)
+    let ranks = board.len();
+    let files = board.first().map_or(0, Vec::len);
+    let width = ranks.to_string().len();
+    let margin = " ".repeat(width + 1);
+    let border = "─".repeat(2 * files + 1);
+    println!("{}┌{}┐", margin, border);
 
     let rows: Vec<usize> = if perspective_white {
-        (0..8).collect()
+        (0..ranks).collect()
     } else {
-        (0..8).rev().collect()
+        (0..ranks).rev().collect()
     };
 
     for i in rows {
-        print!("{} {} ", (8 - i).to_string(), "│");
-        for j in 0..8 {
-            let piece = &board[i][j];
+        print!("{:>width$} │ ", ranks - i);
+        for (j, piece) in board[i].iter().enumerate() {
+            // Xadrez às cegas: casa fora da vista do jogador
+            if visible.is_some_and(|visible| !visible[i][j]) {
+                print!("{} ", "▒".truecolor(70, 70, 70));
+                continue;
+            }
             print_piece(piece);
         }
-        println!("{} {}", "│", (8 - i).to_string());
+        println!("│ {}", ranks - i);
     }
 
-    println!("  {}", "└─────────────────┘");
-    println!("    a b c d e f g h");
+    println!("{}└{}┘", margin, border);
+    let letters = (b'a'..).take(files).map(|c| (c as char).to_string()).collect::<Vec<_>>();
+    println!("{}  {}", margin, letters.join(" "));
 
     // Peças capturadas pelo outro jogador
-    print!("\n{} ", "Pretas capturaram:".green());
-    print_captured_from_views(captured_by_black);
+    print!("\n{} ", black_label.green());
+    print_captured_from_views(black_pieces);
     println!("\n");
 }
 
@@ -84,4 +104,73 @@ fn print_captured_from_views(captured: &[PieceView]) {
             print!("{} ", symbol.bright_black());
         }
     }
-}
\ No newline at end of file
+}
+// Xadrez a quatro: desenha o tabuleiro em cruz girado para que o jogador fique embaixo
+// (sem lugar ainda, do ponto de vista do Vermelho)
+pub fn print_four_player_board(board: &[Vec<Option<FourPieceView>>], my_seat: Option<Seat>, points: &[(Seat, u32)], eliminated: &[Seat]) {
+    let size = board.len();
+    let last = size - 1;
+    let seat = my_seat.unwrap_or(Seat::Red);
+    // Casa do tabuleiro mostrada na linha `r` e coluna `c` da tela
+    let square = |r: usize, c: usize| match seat {
+        Seat::Red => Position::new(r, c),
+        Seat::Yellow => Position::new(last - r, last - c),
+        Seat::Blue => Position::new(c, last - r),
+        Seat::Green => Position::new(last - c, r),
+    };
+    // Girado para o Azul ou o Verde, as linhas da tela são colunas do tabuleiro e vice-versa
+    let sideways = matches!(seat, Seat::Blue | Seat::Green);
+    let file = |pos: Position| ((b'a' + pos.col as u8) as char).to_string();
+    let rank = |pos: Position| (size - pos.row).to_string();
+
+    println!();
+    for r in 0..size {
+        let middle = square(r, size / 2);
+        let label = if sideways { file(middle) } else { rank(middle) };
+        print!("{:>2} ", label);
+        for c in 0..size {
+            let pos = square(r, c);
+            if !is_on_board(pos) {
+                print!("   ");
+                continue;
+            }
+            match &board[pos.row][pos.col] {
+                Some(p) => print!(" {} ", seat_colored(&p.symbol, p.seat, p.dead)),
+                None => print!(" {} ", "·".truecolor(100, 100, 100)),
+            }
+        }
+        println!(" {}", label);
+    }
+    let labels = (0..size)
+        .map(|c| {
+            let middle = square(size / 2, c);
+            let label = if sideways { rank(middle) } else { file(middle) };
+            format!("{:^3}", label)
+        })
+        .collect::<String>();
+    println!("   {}", labels);
+
+    // Pontos e jogadores eliminados
+    println!();
+    for (seat, seat_points) in points {
+        let name = seat_colored(&seat.to_string(), *seat, false);
+        if eliminated.contains(seat) {
+            println!("  {}: {} pontos {}", name, seat_points, "(eliminado)".truecolor(150, 150, 150));
+        } else {
+            println!("  {}: {} pontos", name, seat_points);
+        }
+    }
+}
+
+// Cor de cada jogador no xadrez a quatro; peças de jogadores eliminados ficam cinza
+pub fn seat_colored(text: &str, seat: Seat, dead: bool) -> ColoredString {
+    if dead {
+        return text.truecolor(120, 120, 120);
+    }
+    match seat {
+        Seat::Red => text.bright_red(),
+        Seat::Blue => text.bright_blue(),
+        Seat::Yellow => text.bright_yellow(),
+        Seat::Green => text.bright_green(),
+    }
+}